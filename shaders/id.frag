@@ -0,0 +1,14 @@
+#version 430 core
+
+in vec4 vColor;
+in vec3 vNormal;
+
+uniform uint id;
+
+out vec4 outColor;
+
+// The id spread over the red, green and blue channels, 8 bits each
+void main() {
+    uvec3 bytes = uvec3(id, id >> 8u, id >> 16u) & 255u;
+    outColor = vec4(vec3(bytes) / 255.0, 1.0);
+}
@@ -0,0 +1,20 @@
+#version 430 core
+
+layout (location = 0) in vec4 position;
+layout (location = 1) in vec4 color;
+layout (location = 2) in vec3 normal;
+layout (location = 3) in mat4 model; // Per instance, occupying locations 3 to 6
+
+uniform mat4 modelViewProj;
+
+out vec4 vColor;
+out vec3 vNormal;
+
+void main() {
+    gl_Position = modelViewProj * model * position;
+    vColor = color;
+
+    // The inverse transpose, so that normals stay normal to scaled surfaces
+    mat3 normalMatrix = transpose(inverse(mat3(model)));
+    vNormal = normalize(normalMatrix * normal);
+}
@@ -3,12 +3,25 @@
 in vec4 vColor;
 in vec3 vNormal;
 
+uniform vec4 tint;
+
+// Must be kept in sync with MAX_LIGHTS in renderer.rs
+const int MAX_LIGHTS = 4;
+
+uniform vec3 ambient;
+uniform int lightCount;
+uniform vec3 lightDirections[MAX_LIGHTS]; // Normalized
+uniform vec3 lightColors[MAX_LIGHTS];
+
 out vec4 outColor;
 
 void main() {
-    vec3 lightDirection = normalize(vec3(0.8, -0.5, 0.6));
-    float ndotl = max(0.0, dot(vNormal, -lightDirection));
+    vec3 light = ambient;
+    for (int i = 0; i < lightCount; i++) {
+        light += lightColors[i] * max(0.0, dot(vNormal, -lightDirections[i]));
+    }
 
-    vec3 lit = vColor.rgb * ndotl;
-    outColor = vec4(lit, vColor.a);
+    vec4 color = vColor * tint;
+    vec3 lit = color.rgb * light;
+    outColor = vec4(lit, color.a);
 }
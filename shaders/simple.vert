@@ -14,6 +14,7 @@ void main() {
     gl_Position = modelViewProj * model * position;
     vColor = color;
 
-    mat3 normalMatrix = mat3(model);
+    // The inverse transpose, so that normals stay normal to scaled surfaces
+    mat3 normalMatrix = transpose(inverse(mat3(model)));
     vNormal = normalize(normalMatrix * normal);
 }
\ No newline at end of file
@@ -0,0 +1,290 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Condvar, Mutex, Weak};
+use std::thread;
+
+use image::RgbaImage;
+
+use crate::mesh::{Helicopter, Mesh};
+use crate::shader::ShaderSource;
+use crate::terrain_generator::{load_heightmap, Heightmap};
+
+// Everything read from disk goes through here. Relative paths are looked up in each of the roots
+// in turn, and loading happens on worker threads, handing out handles which become ready later.
+// Asking for the same asset again while someone still holds a handle to it gives another handle
+// to the same load. Once the last handle is gone, so is the asset.
+// Loading can't touch OpenGL, as the context belongs to the render thread, so shaders are loaded
+// as source code and compiled by whoever waits for them.
+
+const WORKER_COUNT: usize = 2;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+enum LoadState<T> {
+    Loading,
+    Loaded(Arc<T>),
+    Failed(String),
+}
+
+struct Slot<T> {
+    path  : PathBuf, // As resolved, or as asked for if it couldn't be
+    state : Mutex<LoadState<T>>,
+    done  : Condvar,
+}
+
+impl<T> Slot<T> {
+    fn finish(&self, result: Result<T, String>) {
+        *self.state.lock().unwrap() = match result {
+            Ok(asset) => LoadState::Loaded(Arc::new(asset)),
+            Err(e) => LoadState::Failed(e),
+        };
+        self.done.notify_all();
+    }
+}
+
+pub struct Handle<T> {
+    slot: Arc<Slot<T>>,
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Handle { slot: Arc::clone(&self.slot) }
+    }
+}
+
+#[allow(dead_code)]
+impl<T> Handle<T> {
+    pub fn path(&self) -> &Path {
+        &self.slot.path
+    }
+
+    // Whether loading is over, whichever way it went
+    pub fn is_ready(&self) -> bool {
+        !matches!(*self.slot.state.lock().unwrap(), LoadState::Loading)
+    }
+
+    // None while still loading
+    pub fn try_get(&self) -> Option<Result<Arc<T>, String>> {
+        match &*self.slot.state.lock().unwrap() {
+            LoadState::Loading => None,
+            LoadState::Loaded(asset) => Some(Ok(Arc::clone(asset))),
+            LoadState::Failed(e) => Some(Err(e.clone())),
+        }
+    }
+
+    // Blocks until loading is over
+    pub fn wait(&self) -> Result<Arc<T>, String> {
+        let mut state = self.slot.state.lock().unwrap();
+        loop {
+            match &*state {
+                LoadState::Loading => state = self.slot.done.wait(state).unwrap(),
+                LoadState::Loaded(asset) => return Ok(Arc::clone(asset)),
+                LoadState::Failed(e) => return Err(e.clone()),
+            }
+        }
+    }
+}
+
+// The registry only holds weak references, so that it doesn't keep assets alive by itself
+trait WeakSlot: Send {
+    fn is_alive(&self) -> bool;
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: Send + Sync + 'static> WeakSlot for Weak<Slot<T>> {
+    fn is_alive(&self) -> bool {
+        self.strong_count() > 0
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+pub struct AssetManager {
+    pub roots : Vec<PathBuf>, // Searched in order for relative paths
+    jobs      : Option<mpsc::Sender<Job>>, // None once shutting down
+    workers   : Vec<thread::JoinHandle<()>>,
+    assets    : HashMap<(TypeId, PathBuf), Box<dyn WeakSlot>>,
+}
+
+impl AssetManager {
+    pub fn new(roots: Vec<PathBuf>) -> Self {
+        let (jobs, job_receiver) = mpsc::channel::<Job>();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        let workers = (0..WORKER_COUNT).map(|_| {
+            let jobs = Arc::clone(&job_receiver);
+            thread::spawn(move || loop {
+                let job = match jobs.lock().unwrap().recv() {
+                    Ok(job) => job,
+                    Err(_) => break, // The manager is gone
+                };
+                job();
+            })
+        }).collect();
+
+        AssetManager {
+            roots,
+            jobs    : Some(jobs),
+            workers,
+            assets  : HashMap::new(),
+        }
+    }
+
+    // Where a file is, trying each of the roots in turn unless the path is absolute
+    pub fn resolve(&self, path: &str) -> Result<PathBuf, String> {
+        let path = Path::new(path);
+        let candidates: Vec<PathBuf> = if path.is_absolute() {
+            vec![path.to_path_buf()]
+        } else {
+            self.roots.iter().map(|root| root.join(path)).collect()
+        };
+        candidates.iter().find(|candidate| candidate.is_file()).cloned().ok_or_else(|| {
+            let tried: Vec<String> = candidates.iter().map(|candidate| candidate.display().to_string()).collect();
+            format!("Couldn't find {}, tried {}", path.display(), tried.join(", "))
+        })
+    }
+
+    // Starts loading an asset with the loader on a worker, unless it is already loaded or being
+    // loaded. There should only be one loader for every type of asset, as they share the cache.
+    pub fn load<T: Send + Sync + 'static>(&mut self, path: &str, loader: fn(&Path) -> Result<T, String>) -> Handle<T> {
+        self.assets.retain(|_, slot| slot.is_alive());
+
+        let resolved = self.resolve(path);
+        let key_path = resolved.as_ref().map_or_else(|_| PathBuf::from(path), |resolved| resolved.clone());
+        let key = (TypeId::of::<T>(), key_path.clone());
+        if let Some(slot) = self.assets.get(&key).and_then(|slot| slot.as_any().downcast_ref::<Weak<Slot<T>>>()?.upgrade()) {
+            return Handle { slot };
+        }
+
+        let slot = Arc::new(Slot { path: key_path, state: Mutex::new(LoadState::Loading), done: Condvar::new() });
+        self.assets.insert(key, Box::new(Arc::downgrade(&slot)));
+
+        let jobs = match (resolved, &self.jobs) {
+            (Ok(_), Some(jobs)) => jobs,
+            (Err(e), _) => { slot.finish(Err(e)); return Handle { slot } },
+            (Ok(_), None) => unreachable!("The jobs are only gone while dropping"),
+        };
+        let job_slot = Arc::clone(&slot);
+        let sent = jobs.send(Box::new(move || {
+            // A loader panicking shouldn't leave anyone waiting forever
+            let result = panic::catch_unwind(AssertUnwindSafe(|| loader(&job_slot.path)))
+                .unwrap_or_else(|_| Err("The loader panicked".to_string()));
+            job_slot.finish(result.map_err(|e| format!("{}: {}", job_slot.path.display(), e)));
+        }));
+        if sent.is_err() {
+            slot.finish(Err("The asset workers are gone".to_string()));
+        }
+        Handle { slot }
+    }
+
+    // An OBJ file with a single mesh in it, parsed once and then read from the mesh cache
+    pub fn mesh(&mut self, path: &str) -> Handle<Mesh> {
+        self.load(path, Mesh::load_cached)
+    }
+
+    // The parts of a helicopter model, cached like meshes
+    pub fn helicopter(&mut self, path: &str) -> Handle<Helicopter> {
+        self.load(path, Helicopter::load_cached)
+    }
+
+    pub fn heightmap(&mut self, path: &str) -> Handle<Heightmap> {
+        self.load(path, load_heightmap)
+    }
+
+    pub fn shader(&mut self, path: &str) -> Handle<ShaderSource> {
+        self.load(path, ShaderSource::load)
+    }
+
+    #[allow(dead_code)]
+    pub fn texture(&mut self, path: &str) -> Handle<RgbaImage> {
+        self.load(path, |path| Ok(image::open(path).map_err(|e| e.to_string())?.into_rgba8()))
+    }
+
+    // How many assets are loaded or being loaded, and still held on to
+    #[allow(dead_code)]
+    pub fn live_count(&self) -> usize {
+        self.assets.values().filter(|slot| slot.is_alive()).count()
+    }
+}
+
+impl Drop for AssetManager {
+    // Let the workers finish what they were asked to load, and stop
+    fn drop(&mut self) {
+        self.jobs = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::time::Duration;
+
+    fn read_text(path: &Path) -> Result<String, String> {
+        fs::read_to_string(path).map_err(|e| e.to_string())
+    }
+
+    fn panic_on_it(_: &Path) -> Result<String, String> {
+        panic!("the loader broke")
+    }
+
+    #[test]
+    fn assets_are_shared_while_held_and_freed_after() {
+        let directory = std::env::temp_dir().join(format!("gloom-assets-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("note.txt"), "hello").unwrap();
+        let mut assets = AssetManager::new(vec![PathBuf::from("/nonexistent"), directory.clone()]);
+
+        let first = assets.load("note.txt", read_text);
+        let absolute = directory.join("note.txt");
+        let second = assets.load(&absolute.to_string_lossy(), read_text);
+        assert_eq!(first.path(), absolute);
+        assert!(Arc::ptr_eq(&first.wait().unwrap(), &second.wait().unwrap()));
+        assert_eq!(*first.wait().unwrap(), "hello");
+        assert_eq!(assets.live_count(), 1);
+
+        // Once the worker lets go of it too, it's gone, and loading it again reads the file again
+        drop((first, second));
+        for _ in 0..1000 {
+            if assets.live_count() == 0 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(assets.live_count(), 0);
+        fs::write(directory.join("note.txt"), "hello again").unwrap();
+        assert_eq!(*assets.load("note.txt", read_text).wait().unwrap(), "hello again");
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn failures_are_errors_and_leave_the_workers_working() {
+        let directory = std::env::temp_dir().join(format!("gloom-assets-failing-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("note.txt"), "hello").unwrap();
+        let mut assets = AssetManager::new(vec![directory.clone()]);
+
+        assert!(assets.load("missing.txt", read_text).wait().unwrap_err().starts_with("Couldn't find missing.txt"));
+        let not_a_texture = assets.texture("note.txt").wait().err().unwrap();
+        assert!(not_a_texture.starts_with(&directory.join("note.txt").display().to_string()), "{}", not_a_texture);
+
+        // More of them than there are workers, so that every worker has had one
+        let panicked: Vec<Handle<String>> = (0..=WORKER_COUNT).map(|i| {
+            fs::write(directory.join(format!("{}.txt", i)), "").unwrap();
+            assets.load(&format!("{}.txt", i), panic_on_it)
+        }).collect();
+        for handle in &panicked {
+            assert!(handle.wait().unwrap_err().ends_with("The loader panicked"));
+        }
+        assert_eq!(*assets.load("note.txt", read_text).wait().unwrap(), "hello");
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
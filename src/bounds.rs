@@ -0,0 +1,177 @@
+extern crate nalgebra_glm as glm;
+
+// Axis aligned bounding box
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min : glm::Vec3,
+    pub max : glm::Vec3,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingSphere {
+    pub center : glm::Vec3,
+    pub radius : f32,
+}
+
+impl Aabb {
+    // From a flat list of x, y, z coordinates. None if there are no points.
+    pub fn from_positions(positions: &[f32]) -> Option<Aabb> {
+        let mut points = positions.chunks_exact(3).map(|p| glm::vec3(p[0], p[1], p[2]));
+        let first = points.next()?;
+        Some(points.fold(Aabb { min: first, max: first }, |aabb, p| Aabb {
+            min: glm::min2(&aabb.min, &p),
+            max: glm::max2(&aabb.max, &p),
+        }))
+    }
+
+    pub fn center(&self) -> glm::Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn half_extents(&self) -> glm::Vec3 {
+        (self.max - self.min) * 0.5
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: glm::min2(&self.min, &other.min),
+            max: glm::max2(&self.max, &other.max),
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn contains(&self, point: &glm::Vec3) -> bool {
+        (0..3).all(|i| self.min[i] <= point[i] && point[i] <= self.max[i])
+    }
+
+    // The smallest box containing this box after an affine transformation
+    pub fn transformed(&self, matrix: &glm::Mat4) -> Aabb {
+        let center = matrix * glm::vec4(self.center().x, self.center().y, self.center().z, 1.0);
+        let half_extents = self.half_extents();
+        let linear = glm::mat4_to_mat3(matrix);
+        let extents = glm::vec3(
+            (0..3).map(|j| linear[(0, j)].abs() * half_extents[j]).sum(),
+            (0..3).map(|j| linear[(1, j)].abs() * half_extents[j]).sum(),
+            (0..3).map(|j| linear[(2, j)].abs() * half_extents[j]).sum(),
+        );
+        let center = center.xyz();
+        Aabb { min: center - extents, max: center + extents }
+    }
+
+    pub fn bounding_sphere(&self) -> BoundingSphere {
+        BoundingSphere { center: self.center(), radius: glm::length(&self.half_extents()) }
+    }
+}
+
+impl BoundingSphere {
+    // A sphere around the center of the bounding box, with the radius of the furthest point.
+    // Not the tightest sphere possible, but never larger than the sphere around the box.
+    pub fn from_positions(positions: &[f32]) -> Option<BoundingSphere> {
+        let center = Aabb::from_positions(positions)?.center();
+        let radius = positions.chunks_exact(3)
+            .map(|p| glm::distance(&center, &glm::vec3(p[0], p[1], p[2])))
+            .fold(0.0, f32::max);
+        Some(BoundingSphere { center, radius })
+    }
+
+    // The sphere is assumed to be uniformly scaled by the largest scaling of the transformation
+    pub fn transformed(&self, matrix: &glm::Mat4) -> BoundingSphere {
+        let center = matrix * glm::vec4(self.center.x, self.center.y, self.center.z, 1.0);
+        let linear = glm::mat4_to_mat3(matrix);
+        let scale = (0..3).map(|j| glm::length(&linear.column(j).into_owned())).fold(0.0, f32::max);
+        BoundingSphere { center: center.xyz(), radius: self.radius * scale }
+    }
+}
+
+// The six planes bounding what a camera can see, pointing inwards
+#[derive(Clone, Copy, Debug)]
+pub struct Frustum {
+    pub planes: [glm::Vec4; 6], // (normal, distance) so that dot(normal, p) + distance >= 0 inside
+}
+
+impl Frustum {
+    // Extracts the planes from a view projection matrix, following Gribb & Hartmann. A point is
+    // inside when -w <= x, y, z <= w in clip space, and each of those inequalities is a plane.
+    pub fn from_view_projection(m: &glm::Mat4) -> Frustum {
+        let row = |i: usize| glm::vec4(m[(i, 0)], m[(i, 1)], m[(i, 2)], m[(i, 3)]);
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+        let normalize = |p: glm::Vec4| p / glm::length(&p.xyz());
+        Frustum {
+            planes: [
+                normalize(w + x), // left
+                normalize(w - x), // right
+                normalize(w + y), // bottom
+                normalize(w - y), // top
+                normalize(w + z), // near
+                normalize(w - z), // far
+            ],
+        }
+    }
+
+    // Conservative: boxes near the corners of the frustum may be reported as visible
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            // The corner of the box furthest along the plane normal
+            let corner = glm::vec3(
+                if plane.x >= 0.0 { aabb.max.x } else { aabb.min.x },
+                if plane.y >= 0.0 { aabb.max.y } else { aabb.min.y },
+                if plane.z >= 0.0 { aabb.max.z } else { aabb.min.z },
+            );
+            glm::dot(&plane.xyz(), &corner) + plane.w >= 0.0
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Looking down -z from the origin, a quarter turn across, so the frustum is as wide as it is far
+    fn frustum() -> Frustum {
+        Frustum::from_view_projection(&glm::perspective(1.0, std::f32::consts::FRAC_PI_2, 0.1, 100.0))
+    }
+
+    fn cube(center: glm::Vec3, half_size: f32) -> Aabb {
+        Aabb { min: center - glm::vec3(half_size, half_size, half_size), max: center + glm::vec3(half_size, half_size, half_size) }
+    }
+
+    #[test]
+    fn boxes_inside_the_frustum_are_visible() {
+        let frustum = frustum();
+        assert!(frustum.intersects_aabb(&cube(glm::vec3(0.0, 0.0, -10.0), 1.0)));
+        assert!(frustum.intersects_aabb(&cube(glm::vec3(8.0, -8.0, -10.0), 1.0)));
+        assert!(frustum.intersects_aabb(&cube(glm::vec3(0.0, 0.0, -1000.0), 2000.0)));
+    }
+
+    #[test]
+    fn boxes_outside_the_frustum_are_not() {
+        let frustum = frustum();
+        assert!(!frustum.intersects_aabb(&cube(glm::vec3(0.0, 0.0, 10.0), 1.0)));          // behind
+        assert!(!frustum.intersects_aabb(&cube(glm::vec3(-25.0, 0.0, -10.0), 5.0)));      // left
+        assert!(!frustum.intersects_aabb(&cube(glm::vec3(0.0, 25.0, -10.0), 5.0)));       // above
+        assert!(!frustum.intersects_aabb(&cube(glm::vec3(0.0, 0.0, -200.0), 1.0)));       // too far
+        assert!(!frustum.intersects_aabb(&cube(glm::vec3(0.0, 0.0, -0.04), 0.05)));       // too near
+    }
+
+    #[test]
+    fn boxes_across_the_edges_are_visible() {
+        let frustum = frustum();
+        assert!(frustum.intersects_aabb(&cube(glm::vec3(-10.0, 0.0, -10.0), 2.0)));
+        assert!(frustum.intersects_aabb(&cube(glm::vec3(0.0, 0.0, -100.0), 2.0)));
+        assert!(frustum.intersects_aabb(&cube(glm::vec3(0.0, 0.0, 0.0), 1.0)));
+    }
+
+    #[test]
+    fn transformed_boxes_contain_the_transformed_corners() {
+        let model = glm::rotate_y(&glm::translation(&glm::vec3(0.0, 0.0, -10.0)), std::f32::consts::FRAC_PI_4);
+        let transformed = cube(glm::zero(), 1.0).transformed(&model);
+        let expected = glm::vec3(2f32.sqrt(), 1.0, 2f32.sqrt());
+        assert!(glm::distance(&transformed.center(), &glm::vec3(0.0, 0.0, -10.0)) < 1e-5);
+        assert!(glm::distance(&transformed.half_extents(), &expected) < 1e-5);
+
+        // Moved into view, and then out of it
+        let frustum = frustum();
+        assert!(!frustum.intersects_aabb(&cube(glm::zero(), 1.0).transformed(&glm::translation(&glm::vec3(0.0, 0.0, 10.0)))));
+        assert!(frustum.intersects_aabb(&transformed));
+    }
+}
@@ -0,0 +1,285 @@
+extern crate nalgebra_glm as glm;
+
+use std::f32::consts::FRAC_PI_2;
+
+use crate::scene_graph::SceneNode;
+
+// Looking straight up or down makes the view direction parallel to the world up vector
+const MAX_PITCH: f32 = FRAC_PI_2 - 0.01;
+
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    Perspective  { fov_y: f32, near: f32, far: f32 }, // Field of view in radians
+    Orthographic { height: f32, near: f32, far: f32 }, // Height of the view volume in world units
+}
+
+// A camera looking along its yaw and pitch, without any roll. With both at zero it looks down
+// the negative Z axis, a positive yaw turns it to the left and a positive pitch tilts it up.
+#[derive(Clone, Copy, Debug)]
+pub struct Camera {
+    pub position     : glm::Vec3,
+    pub yaw          : f32, // measured in radians
+    pub pitch        : f32, // measured in radians
+    pub projection   : Projection,
+    pub aspect_ratio : f32,
+}
+
+impl Camera {
+    pub fn new(position: glm::Vec3, yaw: f32, pitch: f32, projection: Projection, aspect_ratio: f32) -> Self {
+        Camera { position, yaw, pitch: pitch.clamp(-MAX_PITCH, MAX_PITCH), projection, aspect_ratio }
+    }
+
+    pub fn forward(&self) -> glm::Vec3 {
+        glm::vec3(
+            -self.yaw.sin() * self.pitch.cos(),
+            self.pitch.sin(),
+            -self.yaw.cos() * self.pitch.cos(),
+        )
+    }
+
+    pub fn right(&self) -> glm::Vec3 {
+        glm::vec3(self.yaw.cos(), 0.0, -self.yaw.sin())
+    }
+
+    #[allow(dead_code)]
+    pub fn up(&self) -> glm::Vec3 {
+        glm::cross(&self.right(), &self.forward())
+    }
+
+    // Turn to face the given point
+    pub fn look_at(&mut self, point: &glm::Vec3) {
+        let direction = point - self.position;
+        if glm::length(&direction) > 0.0 {
+            let direction = glm::normalize(&direction);
+            self.yaw = (-direction.x).atan2(-direction.z);
+            self.pitch = direction.y.clamp(-1.0, 1.0).asin().clamp(-MAX_PITCH, MAX_PITCH);
+        }
+    }
+
+    pub fn view_matrix(&self) -> glm::Mat4 {
+        let rotation = glm::rotate_x(&glm::identity(), -self.pitch) * glm::rotate_y(&glm::identity(), -self.yaw);
+        rotation * glm::translation(&(-self.position))
+    }
+
+    pub fn projection_matrix(&self) -> glm::Mat4 {
+        match self.projection {
+            Projection::Perspective { fov_y, near, far } => {
+                glm::perspective(self.aspect_ratio, fov_y, near, far)
+            }
+            Projection::Orthographic { height, near, far } => {
+                let (half_width, half_height) = (height * self.aspect_ratio * 0.5, height * 0.5);
+                glm::ortho(-half_width, half_width, -half_height, half_height, near, far)
+            }
+        }
+    }
+
+    pub fn view_projection_matrix(&self) -> glm::Mat4 {
+        self.projection_matrix() * self.view_matrix()
+    }
+}
+
+// What the player asks of the camera this frame. Every controller interprets it in its own way.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CameraInput {
+    pub movement : glm::Vec3, // Right, up and forward, each between -1 and 1
+    pub look     : glm::Vec2, // How far to turn right and up, in radians
+}
+
+pub trait CameraController {
+    // Called when the controller takes over the camera, to carry on from where it is
+    fn activate(&mut self, _camera: &Camera) { }
+
+    // Called when something in the scene is selected, for controllers which keep to a target
+    fn focus_on(&mut self, _node: &SceneNode) { }
+
+    fn update(&mut self, camera: &mut Camera, input: &CameraInput, delta_time: f32);
+}
+
+// First person flying, moving relative to where the camera looks
+pub struct FreeFlyController {
+    pub speed : f32, // In units per second
+}
+
+impl CameraController for FreeFlyController {
+    fn update(&mut self, camera: &mut Camera, input: &CameraInput, delta_time: f32) {
+        camera.yaw -= input.look.x;
+        camera.pitch = (camera.pitch + input.look.y).clamp(-MAX_PITCH, MAX_PITCH);
+
+        let movement = camera.right() * input.movement.x
+                     + glm::vec3(0.0, 1.0, 0.0) * input.movement.y
+                     + camera.forward() * input.movement.z;
+        camera.position += movement * self.speed * delta_time;
+    }
+}
+
+// Circling around a point, moving closer or further away with forward movement
+pub struct OrbitController {
+    pub target       : glm::Vec3,
+    pub distance     : f32,
+    pub min_distance : f32,
+    pub zoom_speed   : f32, // In units per second
+}
+
+impl CameraController for OrbitController {
+    // Orbit around whatever is in front of the camera
+    fn activate(&mut self, camera: &Camera) {
+        self.target = camera.position + camera.forward() * self.distance;
+    }
+
+    // Orbit around the middle of the node and everything below it
+    fn focus_on(&mut self, node: &SceneNode) {
+        self.target = match &node.world_bounds {
+            Some(bounds) => bounds.center(),
+            None => (node.world_transform * glm::vec4(0.0, 0.0, 0.0, 1.0)).xyz(),
+        };
+    }
+
+    fn update(&mut self, camera: &mut Camera, input: &CameraInput, delta_time: f32) {
+        camera.yaw -= input.look.x;
+        camera.pitch = (camera.pitch + input.look.y).clamp(-MAX_PITCH, MAX_PITCH);
+        self.distance = (self.distance - input.movement.z * self.zoom_speed * delta_time).max(self.min_distance);
+
+        camera.position = self.target - camera.forward() * self.distance;
+    }
+}
+
+// A chase camera, trailing behind a scene node and smoothly catching up with it. The node's world
+// transform has to be kept up to date with SceneNode::update_world.
+pub struct FollowController {
+    pub target    : *const SceneNode,
+    pub offset    : glm::Vec3, // Where to be, in the coordinates of the target
+    pub look_at   : glm::Vec3, // What to look at, in the coordinates of the target
+    pub stiffness : f32,       // How quickly to catch up. Higher is snappier
+}
+
+impl CameraController for FollowController {
+    fn focus_on(&mut self, node: &SceneNode) {
+        self.target = node;
+    }
+
+    fn update(&mut self, camera: &mut Camera, _input: &CameraInput, delta_time: f32) {
+        let target = unsafe { &*self.target };
+        let to_world = |p: &glm::Vec3| (target.world_transform * glm::vec4(p.x, p.y, p.z, 1.0)).xyz();
+
+        // Exponential smoothing, which behaves the same regardless of the frame rate
+        let blend = 1.0 - (-self.stiffness * delta_time).exp();
+        camera.position = glm::lerp(&camera.position, &to_world(&self.offset), blend);
+        camera.look_at(&to_world(&self.look_at));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: &glm::Vec3, expected: &glm::Vec3) {
+        assert!(glm::distance(actual, expected) < 1e-4, "{} != {}", actual, expected);
+    }
+
+    fn transform(matrix: &glm::Mat4, p: &glm::Vec3) -> glm::Vec3 {
+        let p = matrix * glm::vec4(p.x, p.y, p.z, 1.0);
+        p.xyz() / p.w
+    }
+
+    fn camera(yaw: f32, pitch: f32) -> Camera {
+        let projection = Projection::Perspective { fov_y: 1.0, near: 0.1, far: 100.0 };
+        Camera::new(glm::vec3(3.0, -2.0, 5.0), yaw, pitch, projection, 1.5)
+    }
+
+    #[test]
+    fn the_view_looks_down_negative_z_whichever_way_the_camera_faces() {
+        for &(yaw, pitch) in &[(0.0, 0.0), (1.0, 0.3), (-2.5, -1.2), (3.0, 1.5)] {
+            let camera = camera(yaw, pitch);
+            let view = camera.view_matrix();
+            assert_close(&transform(&view, &camera.position), &glm::vec3(0.0, 0.0, 0.0));
+            assert_close(&transform(&view, &(camera.position + camera.forward())), &glm::vec3(0.0, 0.0, -1.0));
+            assert_close(&transform(&view, &(camera.position + camera.right())), &glm::vec3(1.0, 0.0, 0.0));
+            assert_close(&transform(&view, &(camera.position + camera.up())), &glm::vec3(0.0, 1.0, 0.0));
+        }
+    }
+
+    #[test]
+    fn looking_at_a_point_puts_it_straight_ahead() {
+        let mut camera = camera(0.0, 0.0);
+        let point = glm::vec3(-4.0, 1.0, 2.0);
+        camera.look_at(&point);
+        let ahead = transform(&camera.view_matrix(), &point);
+        assert_close(&ahead, &glm::vec3(0.0, 0.0, -glm::distance(&camera.position, &point)));
+    }
+
+    #[test]
+    fn free_flying_moves_along_where_the_camera_looks() {
+        let mut camera = camera(0.7, 0.2);
+        let mut controller = FreeFlyController { speed: 4.0 };
+        let start = camera.position;
+        let input = CameraInput { movement: glm::vec3(0.0, 0.0, 1.0), look: glm::vec2(0.0, 10.0) };
+        controller.update(&mut camera, &input, 0.5);
+        assert_eq!(camera.pitch, MAX_PITCH);
+        assert_close(&camera.position, &(start + camera.forward() * 2.0));
+    }
+
+    #[test]
+    fn orbiting_keeps_the_same_distance_to_the_target() {
+        let mut camera = camera(0.0, 0.0);
+        let mut controller = OrbitController { target: glm::zero(), distance: 5.0, min_distance: 1.0, zoom_speed: 2.0 };
+        controller.activate(&camera);
+        let target = controller.target;
+        assert_close(&target, &(camera.position + camera.forward() * 5.0));
+
+        let looks = [glm::vec2(0.3, 0.1), glm::vec2(-1.0, 0.8), glm::vec2(2.0, 5.0), glm::vec2(0.5, -10.0)];
+        for look in &looks {
+            controller.update(&mut camera, &CameraInput { movement: glm::zero(), look: *look }, 0.1);
+            assert!((glm::distance(&camera.position, &target) - 5.0).abs() < 1e-4);
+            assert_close(&transform(&camera.view_matrix(), &target), &glm::vec3(0.0, 0.0, -5.0));
+        }
+
+        // Zooming in stops at the minimum distance
+        controller.update(&mut camera, &CameraInput { movement: glm::vec3(0.0, 0.0, 1.0), look: glm::zero() }, 10.0);
+        assert!((glm::distance(&camera.position, &target) - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn following_catches_up_with_the_target() {
+        let mut node = SceneNode::new();
+        node.world_transform = glm::translation(&glm::vec3(10.0, 0.0, -20.0));
+        let mut camera = camera(0.0, 0.0);
+        let mut controller = FollowController {
+            target    : &**node,
+            offset    : glm::vec3(0.0, 2.0, 6.0),
+            look_at   : glm::vec3(0.0, 0.0, 0.0),
+            stiffness : 5.0,
+        };
+
+        let before = glm::distance(&camera.position, &glm::vec3(10.0, 2.0, -14.0));
+        controller.update(&mut camera, &CameraInput::default(), 0.1);
+        assert!(glm::distance(&camera.position, &glm::vec3(10.0, 2.0, -14.0)) < before);
+        for _ in 0..100 {
+            controller.update(&mut camera, &CameraInput::default(), 0.1);
+        }
+        assert_close(&camera.position, &glm::vec3(10.0, 2.0, -14.0));
+        let ahead = transform(&camera.view_matrix(), &glm::vec3(10.0, 0.0, -20.0));
+        assert!(ahead.x.abs() < 1e-4 && ahead.y.abs() < 1e-4 && ahead.z < 0.0);
+    }
+
+    #[test]
+    fn the_orthographic_projection_maps_its_bounds_to_the_unit_cube() {
+        let mut camera = camera(0.0, 0.0);
+        camera.projection = Projection::Orthographic { height: 4.0, near: 1.0, far: 11.0 };
+        camera.aspect_ratio = 2.0;
+        let projection = camera.projection_matrix();
+        assert_close(&transform(&projection, &glm::vec3(4.0, 2.0, -1.0)), &glm::vec3(1.0, 1.0, -1.0));
+        assert_close(&transform(&projection, &glm::vec3(-4.0, -2.0, -11.0)), &glm::vec3(-1.0, -1.0, 1.0));
+        assert_close(&transform(&projection, &glm::vec3(0.0, 0.0, -6.0)), &glm::vec3(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn the_perspective_projection_maps_its_frustum_to_the_unit_cube() {
+        let camera = camera(0.0, 0.0);
+        let projection = camera.projection_matrix();
+        let half_height = (0.5f32).tan();
+        let corner = glm::vec3(half_height * 1.5, half_height, -1.0);
+        assert_close(&transform(&projection, &(corner * 0.1)), &glm::vec3(1.0, 1.0, -1.0));
+        assert_close(&transform(&projection, &(corner * 100.0)), &glm::vec3(1.0, 1.0, 1.0));
+    }
+}
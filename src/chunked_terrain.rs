@@ -0,0 +1,248 @@
+extern crate nalgebra_glm as glm;
+
+use std::collections::{HashMap, HashSet};
+use std::mem::ManuallyDrop;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+use crate::mesh::{Ground, Mesh, Terrain};
+use crate::renderer::Renderer;
+use crate::scene_graph::{Node, SceneNode};
+use crate::terrain_generator::TerrainGenerator;
+
+// Endless procedural terrain, split into square chunks which come and go around the camera.
+// Chunks are generated on worker threads, and uploaded a few at a time as they are done, so that
+// a frame never waits for them. Chunks which end up far from the camera are freed again.
+
+// At most this many finished chunks are uploaded per update, to spread the work over frames
+const UPLOADS_PER_UPDATE: usize = 2;
+
+// Chunks asked for but not yet uploaded, per worker. Kept low so that the chunks asked for
+// follow the camera closely, rather than piling up where it used to be.
+const REQUESTS_PER_WORKER: usize = 2;
+
+type ChunkCoordinates = (i32, i32); // Along X and Z, chunk (0, 0) starting at the origin
+
+// What the workers send back
+struct GeneratedChunk {
+    coordinates : ChunkCoordinates,
+    mesh        : Mesh,
+    terrain     : Terrain,
+}
+
+struct LoadedChunk {
+    node    : Node,
+    mesh_id : u32,
+    terrain : Terrain, // For answering ground queries
+}
+
+pub struct ChunkedTerrain {
+    pub chunk_size     : f32,
+    pub load_distance  : f32, // Chunks with their middle closer to the camera than this are loaded
+    pub evict_distance : f32, // And those further than this are freed
+    requests           : Option<mpsc::Sender<ChunkCoordinates>>, // None once shutting down
+    generated          : mpsc::Receiver<GeneratedChunk>,
+    workers            : Vec<thread::JoinHandle<()>>,
+    requested          : HashSet<ChunkCoordinates>,
+    loaded             : HashMap<ChunkCoordinates, LoadedChunk>,
+}
+
+impl ChunkedTerrain {
+    // Chunks of chunk_size by chunk_size units with resolution vertices along each side
+    pub fn new(generator: TerrainGenerator, chunk_size: f32, resolution: usize, load_distance: f32) -> Self {
+        let (request_sender, request_receiver) = mpsc::channel::<ChunkCoordinates>();
+        let request_receiver = Arc::new(Mutex::new(request_receiver));
+        let (generated_sender, generated) = mpsc::channel();
+
+        // Leave a core for the render thread
+        let worker_count = thread::available_parallelism().map_or(1, |n| n.get().saturating_sub(1)).clamp(1, 4);
+        let workers = (0..worker_count).map(|_| {
+            let requests = Arc::clone(&request_receiver);
+            let generated = generated_sender.clone();
+            thread::spawn(move || loop {
+                // The lock is only held while waiting for a request, not while generating
+                let coordinates = match requests.lock().unwrap().recv() {
+                    Ok(coordinates) => coordinates,
+                    Err(_) => break, // The terrain is gone
+                };
+                let corner = glm::vec2(coordinates.0 as f32, coordinates.1 as f32) * chunk_size;
+                let mesh = generator.generate_area(&corner, chunk_size, resolution);
+                let terrain = Terrain::new(&mesh);
+                if generated.send(GeneratedChunk { coordinates, mesh, terrain }).is_err() {
+                    break;
+                }
+            })
+        }).collect();
+
+        ChunkedTerrain {
+            chunk_size,
+            load_distance,
+            evict_distance : load_distance + chunk_size, // Some slack, so that chunks don't flicker in and out
+            requests       : Some(request_sender),
+            generated,
+            workers,
+            requested      : HashSet::new(),
+            loaded         : HashMap::new(),
+        }
+    }
+
+    fn chunk_at(&self, x: f32, z: f32) -> ChunkCoordinates {
+        ((x / self.chunk_size).floor() as i32, (z / self.chunk_size).floor() as i32)
+    }
+
+    // How far the middle of a chunk is from a point, seen from above
+    fn distance_to(&self, coordinates: ChunkCoordinates, position: &glm::Vec3) -> f32 {
+        let middle = (glm::vec2(coordinates.0 as f32, coordinates.1 as f32) + glm::vec2(0.5, 0.5)) * self.chunk_size;
+        glm::distance(&middle, &glm::vec2(position.x, position.z))
+    }
+
+    // Upload finished chunks as children of the parent node, free far away ones, and ask for
+    // missing ones, closest first. The chunks are uploaded to every renderer in the same order,
+    // so that their mesh ids agree.
+    pub fn update(&mut self, position: &glm::Vec3, parent: &mut SceneNode, renderers: &mut [&mut dyn Renderer]) {
+        for chunk in self.generated.try_iter().take(UPLOADS_PER_UPDATE).collect::<Vec<_>>() {
+            self.requested.remove(&chunk.coordinates);
+            if self.distance_to(chunk.coordinates, position) > self.evict_distance {
+                continue; // The camera moved on while it was being generated
+            }
+
+            let ids: Vec<(u32, i32)> = renderers.iter_mut().map(|renderer| renderer.upload_mesh(&chunk.mesh)).collect();
+            let (mesh_id, index_count) = ids[0];
+            debug_assert!(ids.iter().all(|&(id, _)| id == mesh_id));
+
+            let mut node = SceneNode::from_vao(mesh_id, index_count);
+            node.local_bounds = Some(chunk.mesh.aabb);
+            parent.add_child(&node);
+            self.loaded.insert(chunk.coordinates, LoadedChunk { node, mesh_id, terrain: chunk.terrain });
+        }
+
+        let far_away: Vec<ChunkCoordinates> = self.loaded.keys()
+            .filter(|&&coordinates| self.distance_to(coordinates, position) > self.evict_distance)
+            .copied()
+            .collect();
+        for coordinates in far_away {
+            let chunk = self.loaded.remove(&coordinates).unwrap();
+            parent.remove_child(&**chunk.node);
+            for renderer in renderers.iter_mut() {
+                renderer.delete_mesh(chunk.mesh_id);
+            }
+            drop(ManuallyDrop::into_inner(chunk.node));
+        }
+
+        let requests = match &self.requests {
+            Some(requests) => requests,
+            None => return,
+        };
+        let reach = (self.load_distance / self.chunk_size).ceil() as i32;
+        let center = self.chunk_at(position.x, position.z);
+        let mut missing: Vec<(f32, ChunkCoordinates)> = vec![];
+        for z in center.1 - reach..=center.1 + reach {
+            for x in center.0 - reach..=center.0 + reach {
+                let distance = self.distance_to((x, z), position);
+                if distance <= self.load_distance && !self.loaded.contains_key(&(x, z)) && !self.requested.contains(&(x, z)) {
+                    missing.push((distance, (x, z)));
+                }
+            }
+        }
+        missing.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let free_slots = (self.workers.len() * REQUESTS_PER_WORKER).saturating_sub(self.requested.len());
+        for (_, coordinates) in missing.into_iter().take(free_slots) {
+            if requests.send(coordinates).is_ok() {
+                self.requested.insert(coordinates);
+            }
+        }
+    }
+
+    // Like update, but waits until every chunk within the load distance is there. For when
+    // frames have to come out the same every time, more than they have to come quickly.
+    pub fn update_blocking(&mut self, position: &glm::Vec3, parent: &mut SceneNode, renderers: &mut [&mut dyn Renderer]) {
+        loop {
+            self.update(position, parent, renderers);
+            // Missing chunks are asked for as long as there is room, so none asked for means none missing
+            if self.requested.is_empty() || self.requests.is_none() {
+                break;
+            }
+            thread::sleep(std::time::Duration::from_millis(1));
+        }
+    }
+}
+
+impl Ground for ChunkedTerrain {
+    // None where the chunk isn't loaded yet
+    fn height_at(&self, x: f32, z: f32) -> Option<f32> {
+        self.loaded.get(&self.chunk_at(x, z))?.terrain.height_at(x, z)
+    }
+
+    fn normal_at(&self, x: f32, z: f32) -> Option<glm::Vec3> {
+        self.loaded.get(&self.chunk_at(x, z))?.terrain.normal_at(x, z)
+    }
+}
+
+impl Drop for ChunkedTerrain {
+    // Let the workers finish what they are doing and stop. The chunk nodes are left to the scene.
+    fn drop(&mut self) {
+        self.requests = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::RecordingRenderer;
+
+    // The chunks which should be loaded with the camera at a position, within a distance of it
+    fn within(terrain: &ChunkedTerrain, position: &glm::Vec3, distance: f32) -> HashSet<ChunkCoordinates> {
+        let center = terrain.chunk_at(position.x, position.z);
+        (center.1 - 5..=center.1 + 5)
+            .flat_map(|z| (center.0 - 5..=center.0 + 5).map(move |x| (x, z)))
+            .filter(|&coordinates| terrain.distance_to(coordinates, position) <= distance)
+            .collect()
+    }
+
+    fn loaded(terrain: &ChunkedTerrain) -> HashSet<ChunkCoordinates> {
+        terrain.loaded.keys().copied().collect()
+    }
+
+    #[test]
+    fn chunks_come_and_go_with_the_camera() {
+        let mut terrain = ChunkedTerrain::new(TerrainGenerator::default(), 10.0, 3, 15.0);
+        let mut parent = SceneNode::new();
+        let mut renderer = RecordingRenderer::default();
+        let live_meshes = |renderer: &RecordingRenderer| renderer.index_counts.iter().filter(|&&count| count > 0).count();
+
+        // A few chunks at a time, however many are done
+        let start = glm::vec3(0.0, 0.0, 0.0);
+        while terrain.loaded.len() < within(&terrain, &start, 15.0).len() {
+            let before = renderer.index_counts.len();
+            terrain.update(&start, &mut parent, &mut [&mut renderer]);
+            assert!(renderer.index_counts.len() - before <= UPLOADS_PER_UPDATE);
+            assert!(terrain.requested.len() <= terrain.workers.len() * REQUESTS_PER_WORKER);
+            thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert_eq!(loaded(&terrain), within(&terrain, &start, 15.0));
+        assert_eq!(parent.n_children(), terrain.loaded.len());
+        assert!(terrain.height_at(1.0, -1.0).is_some());
+        assert!(terrain.height_at(40.0, 0.0).is_none());
+
+        // Two chunks aside, what came within reach is loaded, what is only a little out of it is
+        // kept, and the rest is freed
+        let aside = glm::vec3(20.0, 0.0, 0.0);
+        assert!(loaded(&terrain).contains(&(-1, 0)));
+        let kept = &loaded(&terrain) & &within(&terrain, &aside, terrain.evict_distance);
+        assert!(kept.iter().any(|&coordinates| terrain.distance_to(coordinates, &aside) > 15.0));
+        terrain.update_blocking(&aside, &mut parent, &mut [&mut renderer]);
+        assert_eq!(loaded(&terrain), &kept | &within(&terrain, &aside, 15.0));
+        assert!(!loaded(&terrain).contains(&(-1, 0)));
+
+        // Far away, nothing of the old chunks is left, not even their meshes
+        let far = glm::vec3(1000.0, 0.0, 1000.0);
+        terrain.update_blocking(&far, &mut parent, &mut [&mut renderer]);
+        assert_eq!(loaded(&terrain), within(&terrain, &far, 15.0));
+        assert_eq!(parent.n_children(), terrain.loaded.len());
+        assert_eq!(live_meshes(&renderer), terrain.loaded.len());
+    }
+}
@@ -0,0 +1,180 @@
+// The command line. Options come as "--name value" or "--name=value", and the scene file, if
+// any, as the one argument which isn't an option.
+
+pub const USAGE: &str = "\
+Usage: gloom-rs [OPTIONS] [SCENE]
+
+Shows the scene described in the SCENE file, or the built in lunar scene without one.
+
+Options:
+  --size <W>x<H>      Size of the window, or of the frames when headless [default: 800x600]
+  --fullscreen        Cover the whole screen, on the monitor the window would be on
+  --no-vsync          Don't wait for the display between frames
+  --msaa <SAMPLES>    Samples per pixel for multisampling, 0 to turn it off [default: 0]
+  --shaders <DIR>     Where to load the shaders from [default: shaders]
+  --frames <COUNT>    Quit after this many frames
+  --headless          Render on the CPU without opening a window, one frame unless --frames says
+                      otherwise. Time advances by a fixed step every frame.
+  --capture <PATH>    Save the last frame to an image. A {} in the path is replaced by the
+                      number of the frame, and saves every frame.
+  -h, --help          Show this, and quit
+";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Options {
+    pub width        : u32,
+    pub height       : u32,
+    pub fullscreen   : bool,
+    pub vsync        : bool,
+    pub msaa_samples : u16,
+    pub shader_dir   : String,
+    pub frames       : Option<u64>,
+    pub headless     : bool,
+    pub capture      : Option<String>,
+    pub scene        : Option<String>,
+    pub help         : bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            width        : 800,
+            height       : 600,
+            fullscreen   : false,
+            vsync        : true,
+            msaa_samples : 0,
+            shader_dir   : "shaders".to_string(),
+            frames       : None,
+            headless     : false,
+            capture      : None,
+            scene        : None,
+            help         : false,
+        }
+    }
+}
+
+fn parse_size(value: &str) -> Result<(u32, u32), String> {
+    let size = value.split_once('x').and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)));
+    match size {
+        Some((width, height)) if width > 0 && height > 0 => Ok((width, height)),
+        _ => Err(format!("Invalid size {}, expected something like 800x600", value)),
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(option: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("Invalid value {} for {}", value, option))
+}
+
+impl Options {
+    // The arguments without the name of the program
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Options, String> {
+        let mut options = Options::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if !arg.starts_with('-') {
+                if options.scene.is_some() {
+                    return Err(format!("Unexpected argument {}, there can only be one scene", arg));
+                }
+                options.scene = Some(arg);
+                continue;
+            }
+
+            let (option, attached_value) = match arg.split_once('=') {
+                Some((option, value)) => (option.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+            let takes_value = matches!(option.as_str(), "--size" | "--msaa" | "--shaders" | "--frames" | "--capture");
+            let value = match (takes_value, attached_value) {
+                (true, Some(value)) => value,
+                (true, None) => args.next().ok_or_else(|| format!("{} needs a value", option))?,
+                (false, Some(_)) => return Err(format!("{} doesn't take a value", option)),
+                (false, None) => String::new(),
+            };
+
+            match option.as_str() {
+                "--size" => (options.width, options.height) = parse_size(&value)?,
+                "--fullscreen" => options.fullscreen = true,
+                "--no-vsync" => options.vsync = false,
+                "--msaa" => options.msaa_samples = parse_number(&option, &value)?,
+                "--shaders" => options.shader_dir = value,
+                "--frames" => match parse_number(&option, &value)? {
+                    0 => return Err("--frames should be at least 1".to_string()),
+                    frames => options.frames = Some(frames),
+                },
+                "--headless" => options.headless = true,
+                "--capture" => options.capture = Some(value),
+                "-h" | "--help" => options.help = true,
+                _ => return Err(format!("Unknown option {}", option)),
+            }
+        }
+        Ok(options)
+    }
+
+    // Where to save a frame, if it should be saved. last is whether it's the last frame.
+    pub fn capture_path(&self, frame: u64, last: bool) -> Option<String> {
+        let capture = self.capture.as_ref()?;
+        if capture.contains("{}") {
+            Some(capture.replace("{}", &format!("{:05}", frame)))
+        } else if last {
+            Some(capture.clone())
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Options, String> {
+        Options::parse(args.split_whitespace().map(str::to_string))
+    }
+
+    #[test]
+    fn options_are_read_either_way_they_are_given() {
+        let sized = Options { width: 1024, height: 768, ..Options::default() };
+        let cases = [
+            ("",                                 Options::default()),
+            ("--size=1024x768",                  sized.clone()),
+            ("--size 1024x768",                  sized),
+            ("--help",                           Options { help: true, ..Options::default() }),
+            ("-h",                               Options { help: true, ..Options::default() }),
+            ("moon.json --no-vsync --msaa=4",    Options { scene: Some("moon.json".to_string()), vsync: false, msaa_samples: 4, ..Options::default() }),
+            ("--headless --frames 3 --capture f{}.png",
+             Options { headless: true, frames: Some(3), capture: Some("f{}.png".to_string()), ..Options::default() }),
+        ];
+        for (args, expected) in cases {
+            assert_eq!(parse(args), Ok(expected), "{}", args);
+        }
+    }
+
+    #[test]
+    fn mistakes_are_errors() {
+        let cases = [
+            ("--size=0x600",      "Invalid size 0x600"),
+            ("--size 800x",       "Invalid size 800x"),
+            ("--size 800",        "Invalid size 800"),
+            ("--size",            "--size needs a value"),
+            ("a.json b.json",     "there can only be one scene"),
+            ("--wat",             "Unknown option --wat"),
+            ("--fullscreen=yes",  "--fullscreen doesn't take a value"),
+            ("--msaa many",       "Invalid value many for --msaa"),
+            ("--frames 0",        "--frames should be at least 1"),
+        ];
+        for (args, expected) in cases {
+            let error = parse(args).unwrap_err();
+            assert!(error.contains(expected), "{}: {}", args, error);
+        }
+    }
+
+    #[test]
+    fn captures_are_numbered_or_only_of_the_last_frame() {
+        let numbered = parse("--capture frame_{}.png").unwrap();
+        assert_eq!(numbered.capture_path(7, false).as_deref(), Some("frame_00007.png"));
+        let last = parse("--capture last.png").unwrap();
+        assert_eq!(last.capture_path(7, false), None);
+        assert_eq!(last.capture_path(8, true).as_deref(), Some("last.png"));
+        assert_eq!(Options::default().capture_path(8, true), None);
+    }
+}
@@ -0,0 +1,377 @@
+extern crate nalgebra_glm as glm;
+
+use std::collections::{HashMap, HashSet};
+use std::f32::consts::TAU;
+
+use crate::flight::{self, Attitude, Controls, FlightModel, FlightState};
+use crate::mesh::Ground;
+use crate::physics::{self, RigidBody};
+use crate::scene_graph::SceneNode;
+use crate::toolbox::{self, Heading};
+
+// Behavior which can be given to any node of a scene. Every kind of component has a storage of
+// its own, which keeps them along with the node they belong to, and a system which updates all
+// of them at once every tick of the simulation. A node has at most one component of each kind.
+
+pub type Entity = *mut SceneNode;
+
+// The components are kept in the order they were added, and found by their node through an index
+pub struct Storage<T> {
+    entries : Vec<(Entity, T)>,
+    index   : HashMap<*const SceneNode, usize>, // Where in the entries each node's component is
+}
+
+impl<T> Default for Storage<T> {
+    fn default() -> Self {
+        Storage { entries: vec![], index: HashMap::new() }
+    }
+}
+
+#[allow(dead_code)]
+impl<T> Storage<T> {
+    // Returns the component the node had before, if it had one
+    pub fn insert(&mut self, entity: Entity, component: T) -> Option<T> {
+        match self.get_mut(entity) {
+            Some(existing) => Some(std::mem::replace(existing, component)),
+            None => {
+                self.index.insert(entity, self.entries.len());
+                self.entries.push((entity, component));
+                None
+            }
+        }
+    }
+
+    // Those added after it move down a place
+    pub fn remove(&mut self, entity: Entity) -> Option<T> {
+        let index = self.index.remove(&(entity as *const SceneNode))?;
+        for (later, _) in &self.entries[index + 1..] {
+            *self.index.get_mut(&(*later as *const SceneNode)).unwrap() -= 1;
+        }
+        Some(self.entries.remove(index).1)
+    }
+
+    pub fn get(&self, entity: *const SceneNode) -> Option<&T> {
+        self.index.get(&entity).map(|&i| &self.entries[i].1)
+    }
+
+    pub fn get_mut(&mut self, entity: *const SceneNode) -> Option<&mut T> {
+        let i = *self.index.get(&entity)?;
+        Some(&mut self.entries[i].1)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &T)> {
+        self.entries.iter().map(|(entity, component)| (*entity, component))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Entity, &mut T)> {
+        self.entries.iter_mut().map(|(entity, component)| (*entity, component))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [(Entity, T)] {
+        &mut self.entries
+    }
+}
+
+// Turning around one of its axes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Spinner {
+    pub axis  : usize,
+    pub speed : f32, // In radians per second
+}
+
+// How hard path followers with a rigid body are pulled towards where they should be, as an
+// acceleration per unit of distance, and how much their velocity is evened out with that of the
+// path, on top of following the turns of the path. Critically damped, so that they catch up
+// without overshooting.
+const FOLLOW_STIFFNESS: f32 = 20.0;
+const FOLLOW_DAMPING: f32 = 8.94; // 2 * sqrt(FOLLOW_STIFFNESS)
+
+// Flying along toolbox::simple_heading_animation, at a fixed altitude above the ground. Nodes
+// with a rigid body are steered along it by forces, so that they can be pushed off it, others
+// are moved right onto it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PathFollower {
+    pub phase    : f32, // How many seconds ahead of the others along the path
+    pub altitude : f32,
+}
+
+// Sliding up to travel units along an axis from where it's closed, when told to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DoorController {
+    pub axis    : usize,
+    pub travel  : f32,
+    pub speed   : f32,       // In units per second
+    pub closed  : glm::Vec3, // Where the node is with the door closed
+    pub opening : f32,       // How far open it is, from 0 to travel
+    pub command : f32,       // 1 to open, -1 to close and 0 to leave it be, or anything in between
+}
+
+impl DoorController {
+    // Closed where the node is now
+    pub fn new(node: &SceneNode, axis: usize, travel: f32, speed: f32) -> Self {
+        DoorController { axis, travel, speed, closed: node.position, opening: 0.0, command: 0.0 }
+    }
+}
+
+// Flown as a helicopter by its controls, see flight.rs, unless a path follower is steering it.
+// Needs a rigid body to push around. Its rotors, every spinner below the node, turn as fast as the
+// engine runs them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FlightController {
+    pub model    : FlightModel,
+    pub controls : Controls,
+    pub state    : FlightState,
+    pub rotors   : Vec<(Entity, f32)>, // Along with how fast they turn at full speed
+}
+
+impl FlightController {
+    // Turned the way the node is now, with the rotors at full speed
+    pub fn new(node: &SceneNode, model: FlightModel, rotors: Vec<(Entity, f32)>) -> Self {
+        FlightController {
+            model,
+            controls : Controls::default(),
+            state    : FlightState { attitude: Attitude::from_rotation(&node.rotation), rotor_speed: 1.0 },
+            rotors,
+        }
+    }
+
+    // How fast a rotor turns at full speed, if it's one of them
+    pub fn full_speed(&self, rotor: *const SceneNode) -> Option<f32> {
+        self.rotors.iter().find(|&&(r, _)| std::ptr::eq(r, rotor)).map(|&(_, speed)| speed)
+    }
+}
+
+#[derive(Default)]
+pub struct World {
+    pub spinners  : Storage<Spinner>,
+    pub followers : Storage<PathFollower>,
+    pub doors     : Storage<DoorController>,
+    pub bodies    : Storage<RigidBody>,
+    pub aircraft  : Storage<FlightController>,
+}
+
+impl World {
+    // Every node with a component, once each
+    pub fn entities(&self) -> Vec<Entity> {
+        let mut entities: Vec<Entity> = vec![];
+        let mut seen = HashSet::new();
+        let all = self.spinners.iter().map(|(e, _)| e)
+            .chain(self.followers.iter().map(|(e, _)| e))
+            .chain(self.doors.iter().map(|(e, _)| e))
+            .chain(self.bodies.iter().map(|(e, _)| e))
+            .chain(self.aircraft.iter().map(|(e, _)| e));
+        for entity in all {
+            if seen.insert(entity) {
+                entities.push(entity);
+            }
+        }
+        entities
+    }
+
+    // Put the path followers where they are at time, those with a rigid body already going as
+    // fast as the path does, and have the aircraft turned the way they were put
+    pub fn start(&mut self, time: f32, timestep: f32, ground: &dyn Ground) {
+        for (node, follower) in self.followers.iter() {
+            let (position, rotation) = follower.target(time, ground);
+            let node = unsafe { &mut *node };
+            node.position = position;
+            node.rotation = rotation;
+            if let Some(body) = self.bodies.get_mut(node) {
+                if !body.is_fixed() {
+                    body.velocity = follower.velocity(time - timestep, timestep, ground);
+                }
+            }
+        }
+        for (node, aircraft) in self.aircraft.iter_mut() {
+            aircraft.state.attitude = Attitude::from_rotation(&unsafe { &*node }.rotation);
+        }
+    }
+
+    // One tick of the simulation, ending at time
+    pub fn update(&mut self, time: f32, timestep: f32, ground: &dyn Ground) {
+        spin(&self.spinners, timestep);
+        follow_paths(&self.followers, &mut self.bodies, time, timestep, ground);
+        fly(&mut self.aircraft, &self.followers, &mut self.bodies, &mut self.spinners, timestep, ground);
+        move_doors(&mut self.doors, timestep);
+        physics::step(self.bodies.as_mut_slice(), &physics::GRAVITY, timestep, ground);
+    }
+}
+
+pub fn spin(spinners: &Storage<Spinner>, timestep: f32) {
+    for (node, spinner) in spinners.iter() {
+        let node = unsafe { &mut *node };
+        node.rotation[spinner.axis] = (node.rotation[spinner.axis] + spinner.speed * timestep).rem_euclid(TAU);
+    }
+}
+
+impl PathFollower {
+    // Where to be at a time, and how to be turned
+    pub fn target(&self, time: f32, ground: &dyn Ground) -> (glm::Vec3, glm::Vec3) {
+        let heading: Heading = toolbox::simple_heading_animation(time + self.phase);
+        let height = ground.height_at(heading.x, heading.z).unwrap_or(0.0) + self.altitude;
+        (glm::vec3(heading.x, height, heading.z), glm::vec3(heading.pitch, heading.yaw, heading.roll))
+    }
+
+    // How fast it goes along the path over a tick starting at time
+    pub fn velocity(&self, time: f32, timestep: f32, ground: &dyn Ground) -> glm::Vec3 {
+        (self.target(time + timestep, ground).0 - self.target(time, ground).0) / timestep
+    }
+}
+
+pub fn follow_paths(followers: &Storage<PathFollower>, bodies: &mut Storage<RigidBody>, time: f32, timestep: f32, ground: &dyn Ground) {
+    for (node, follower) in followers.iter() {
+        let node = unsafe { &mut *node };
+        let (position, rotation) = follower.target(time, ground);
+        node.rotation = rotation;
+        match bodies.get_mut(node) {
+            Some(body) if !body.is_fixed() => {
+                // Where the node should be as the tick starts, how fast the tick before should
+                // have left it going, and how fast it has to go over this one to end up on target
+                let start = time - timestep;
+                let (expected_position, _) = follower.target(start, ground);
+                let expected_velocity = follower.velocity(start - timestep, timestep, ground);
+                let path_velocity = follower.velocity(start, timestep, ground);
+                let acceleration = (path_velocity - expected_velocity) / timestep
+                                 + (expected_position - node.position) * FOLLOW_STIFFNESS
+                                 + (expected_velocity - body.velocity) * FOLLOW_DAMPING
+                                 - physics::GRAVITY;
+                body.add_force(&(acceleration * body.mass()));
+            }
+            _ => node.position = position,
+        }
+    }
+}
+
+pub fn fly(aircraft: &mut Storage<FlightController>, followers: &Storage<PathFollower>, bodies: &mut Storage<RigidBody>,
+           spinners: &mut Storage<Spinner>, timestep: f32, ground: &dyn Ground) {
+    for (node, controller) in aircraft.iter_mut() {
+        let node = unsafe { &mut *node };
+        let followed = followers.get(node).is_some();
+        if followed {
+            controller.state.attitude = Attitude::from_rotation(&node.rotation);
+        }
+        let height = ground.height_at(node.position.x, node.position.z).map_or(f32::INFINITY, |height| node.position.y - height);
+        match bodies.get_mut(node) {
+            Some(body) if !followed && !body.is_fixed() => {
+                let acceleration = flight::step(&controller.model, &controller.controls, &mut controller.state,
+                                                &body.velocity, height, &physics::GRAVITY, timestep);
+                body.add_force(&(acceleration * body.mass()));
+                body.angular_velocity = glm::zero();
+                node.rotation = controller.state.attitude.rotation();
+            }
+            // Along for the ride, with only the rotors up to it
+            _ => flight::spool(&controller.model, &controller.controls, &mut controller.state, timestep),
+        }
+        for &(rotor, speed) in &controller.rotors {
+            if let Some(spinner) = spinners.get_mut(rotor) {
+                spinner.speed = speed * controller.state.rotor_speed;
+            }
+        }
+    }
+}
+
+pub fn move_doors(doors: &mut Storage<DoorController>, timestep: f32) {
+    for (node, door) in doors.iter_mut() {
+        let node = unsafe { &mut *node };
+        door.opening = (door.opening + door.command * door.speed * timestep).clamp(0.0, door.travel);
+        node.position = door.closed;
+        node.position[door.axis] += door.opening;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::AssetManager;
+    use crate::renderer::RecordingRenderer;
+    use crate::scene_file::{LoadedScene, SceneDescription};
+
+    #[test]
+    fn components_are_found_by_their_node_after_others_are_removed() {
+        let mut nodes: Vec<_> = (0..4).map(|_| SceneNode::new()).collect();
+        let entities: Vec<Entity> = nodes.iter_mut().map(|node| &mut ***node as *mut SceneNode).collect();
+        let mut storage = Storage::default();
+        for (i, &entity) in entities.iter().enumerate() {
+            assert_eq!(storage.insert(entity, i), None);
+        }
+        assert_eq!(storage.insert(entities[2], 20), Some(2));
+        assert_eq!(storage.remove(entities[1]), Some(1));
+        assert_eq!(storage.remove(entities[1]), None);
+
+        assert_eq!(storage.get(entities[1]), None);
+        *storage.get_mut(entities[3]).unwrap() += 30;
+        let found: Vec<Option<&usize>> = entities.iter().map(|&entity| storage.get(entity)).collect();
+        assert_eq!(found, vec![Some(&0), None, Some(&20), Some(&33)]);
+        assert_eq!(storage.iter().map(|(_, &i)| i).collect::<Vec<_>>(), vec![0, 20, 33]);
+    }
+
+    #[test]
+    fn doors_stop_when_fully_open_or_closed() {
+        let mut node = SceneNode::new();
+        node.position = glm::vec3(1.0, 2.0, 3.0);
+        let door = DoorController::new(&node, 2, 2.0, 5.0);
+        let node_ptr = &mut **node as *mut SceneNode;
+        let mut doors = Storage::default();
+        doors.insert(node_ptr, door);
+
+        doors.get_mut(node_ptr).unwrap().command = 1.0;
+        move_doors(&mut doors, 0.1);
+        assert_eq!(node.position, glm::vec3(1.0, 2.0, 3.5));
+        move_doors(&mut doors, 1.0);
+        assert_eq!(node.position, glm::vec3(1.0, 2.0, 5.0));
+
+        doors.get_mut(node_ptr).unwrap().command = -0.5;
+        move_doors(&mut doors, 0.2);
+        assert_eq!(node.position, glm::vec3(1.0, 2.0, 4.5));
+        move_doors(&mut doors, 10.0);
+        assert_eq!(node.position, glm::vec3(1.0, 2.0, 3.0));
+        assert_eq!(doors.get(node_ptr).unwrap().opening, 0.0);
+    }
+
+    #[test]
+    fn spinners_keep_their_angle_within_a_turn() {
+        let mut node = SceneNode::new();
+        let node_ptr = &mut **node as *mut SceneNode;
+        let mut spinners = Storage::default();
+        spinners.insert(node_ptr, Spinner { axis: 1, speed: -3.0 });
+        spin(&spinners, 1.0);
+        assert!((node.rotation.y - (TAU - 3.0)).abs() < 1e-5, "{}", node.rotation.y);
+
+        spinners.get_mut(node_ptr).unwrap().speed = 10.0;
+        for _ in 0..100 {
+            spin(&spinners, 0.1);
+            assert!((0.0..TAU).contains(&node.rotation.y), "{}", node.rotation.y);
+        }
+        assert_eq!(node.rotation.x, 0.0);
+    }
+
+    #[test]
+    fn components_belong_to_the_nodes_found_by_name_and_tag() {
+        let text = r#"{
+            "nodes": [
+                { "name": "hangar", "children": [
+                    { "name": "door", "position": [0, 0, 4], "components": [ { "type": "door_controller", "axis": "x", "travel": 3, "speed": 1 } ] }
+                ] },
+                { "name": "fan_1", "tags": ["fan"], "components": [ { "type": "spinner", "axis": "y", "speed": 1 } ] },
+                { "name": "fan_2", "tags": ["fan"], "components": [ { "type": "spinner", "axis": "z", "speed": 2 } ] }
+            ]
+        }"#;
+        let description = SceneDescription::parse(text).unwrap();
+        let requests = description.request_meshes(&mut AssetManager::new(vec![]));
+        let scene = LoadedScene::build(description, &requests, &mut RecordingRenderer::default(), &mut RecordingRenderer::default()).unwrap();
+
+        let door = scene.find("hangar/door").unwrap();
+        assert_eq!(scene.world.doors.get(door).unwrap().closed, glm::vec3(0.0, 0.0, 4.0));
+        assert!(scene.world.doors.get(scene.find("hangar").unwrap()).is_none());
+
+        let fans = scene.tagged("fan");
+        assert_eq!(fans, vec![scene.find("fan_1").unwrap(), scene.find("fan_2").unwrap()]);
+        let axes: Vec<usize> = fans.iter().map(|&fan| scene.world.spinners.get(fan).unwrap().axis).collect();
+        assert_eq!(axes, vec![1, 2]);
+        assert_eq!(scene.world.entities().len(), 3);
+    }
+}
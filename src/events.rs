@@ -0,0 +1,63 @@
+use glutin::event::{DeviceEvent, ElementState, Event, KeyboardInput, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent};
+
+// The event loop runs on the main thread and owns the window, while the render thread owns the
+// OpenGL context. Window and input events are sent to the render thread over a channel, and
+// handled there in the order they happened at the start of every frame. The render thread asks
+// things of the window in turn by sending requests to the event loop as user events.
+
+// Scroll wheels reporting pixels instead of lines count this many pixels as one line
+const PIXELS_PER_SCROLL_LINE: f32 = 20.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WindowMessage {
+    Resized       { width: u32, height: u32 }, // In physical pixels
+    KeyDown       { keycode: Option<VirtualKeyCode>, scancode: u32 },
+    KeyUp         { keycode: Option<VirtualKeyCode>, scancode: u32 },
+    MouseButton   { button: MouseButton, state: ElementState },
+    MouseMotion   { dx: f32, dy: f32 },    // Raw movement, unaffected by the cursor hitting the screen edges
+    CursorMoved   { x: f32, y: f32 },      // In physical pixels from the top left of the window
+    Scroll        { lines: f32 },
+    GamepadAxis   { axis: u32, value: f32 }, // From -1 to 1
+    GamepadButton { button: u32, state: ElementState },
+    Focused(bool),
+    CloseRequested,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RenderRequest {
+    Exit,
+    GrabCursor(bool), // Confine and hide the cursor, or let it go
+}
+
+impl WindowMessage {
+    // The message to send on for an event, if the render thread cares about it
+    pub fn from_event(event: &Event<RenderRequest>) -> Option<WindowMessage> {
+        match event {
+            Event::WindowEvent { event, .. } => match event {
+                WindowEvent::Resized(size) => Some(WindowMessage::Resized { width: size.width, height: size.height }),
+                WindowEvent::KeyboardInput { input: KeyboardInput { state, virtual_keycode, scancode, .. }, .. } => {
+                    let (keycode, scancode) = (*virtual_keycode, *scancode);
+                    Some(match state {
+                        ElementState::Pressed  => WindowMessage::KeyDown { keycode, scancode },
+                        ElementState::Released => WindowMessage::KeyUp { keycode, scancode },
+                    })
+                }
+                WindowEvent::CursorMoved { position, .. } => Some(WindowMessage::CursorMoved { x: position.x as f32, y: position.y as f32 }),
+                WindowEvent::MouseInput { state, button, .. } => Some(WindowMessage::MouseButton { button: *button, state: *state }),
+                WindowEvent::MouseWheel { delta, .. } => Some(WindowMessage::Scroll {
+                    lines: match delta {
+                        MouseScrollDelta::LineDelta(_, y) => *y,
+                        MouseScrollDelta::PixelDelta(position) => position.y as f32 / PIXELS_PER_SCROLL_LINE,
+                    },
+                }),
+                WindowEvent::Focused(focused) => Some(WindowMessage::Focused(*focused)),
+                WindowEvent::CloseRequested => Some(WindowMessage::CloseRequested),
+                _ => None,
+            },
+            Event::DeviceEvent { event: DeviceEvent::MouseMotion { delta }, .. } => {
+                Some(WindowMessage::MouseMotion { dx: delta.0 as f32, dy: delta.1 as f32 })
+            }
+            _ => None,
+        }
+    }
+}
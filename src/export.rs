@@ -0,0 +1,367 @@
+extern crate nalgebra_glm as glm;
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use crate::json::Json;
+use crate::mesh::Mesh;
+use crate::scene_graph::SceneNode;
+
+// Writing geometry back out for other tools: single meshes as OBJ, with their colors as MTL
+// materials, and whole scenes as glTF 2.0, a .gltf file with the structure next to a .bin file
+// with the vertices and indices. A part of the scene can be baked into a single mesh to write it
+// as OBJ.
+
+// glTF constants
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+fn write_file(path: &Path, contents: &[u8]) -> Result<(), String> {
+    fs::write(path, contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+impl Mesh {
+    // Writes an .mtl file next to the OBJ as well, with a material for every vertex color. Each
+    // triangle gets the color of its first vertex.
+    pub fn write_obj(&self, path: &Path) -> Result<(), String> {
+        let name = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("mesh");
+        let mtl_path = path.with_extension("mtl");
+        let vertex_count = self.vertex_count();
+        let (has_normals, has_uvs) = (self.normals.len() == 3 * vertex_count, self.uvs.len() == 2 * vertex_count);
+        let color_of = |vertex: u32| -> [f32; 4] {
+            match self.colors.get(4 * vertex as usize..4 * vertex as usize + 4) {
+                Some(color) => [color[0], color[1], color[2], color[3]],
+                None => [1.0, 1.0, 1.0, 1.0],
+            }
+        };
+
+        // Triangles grouped by color, in order of first appearance
+        let mut colors: Vec<[f32; 4]> = vec![];
+        let mut triangles_of_color: Vec<Vec<&[u32]>> = vec![];
+        let mut color_index: HashMap<[u32; 4], usize> = HashMap::new();
+        for triangle in self.indices.chunks_exact(3) {
+            let color = color_of(triangle[0]);
+            let i = *color_index.entry(color.map(f32::to_bits)).or_insert_with(|| {
+                colors.push(color);
+                triangles_of_color.push(vec![]);
+                colors.len() - 1
+            });
+            triangles_of_color[i].push(triangle);
+        }
+
+        let mut mtl = String::new();
+        for (i, color) in colors.iter().enumerate() {
+            writeln!(mtl, "newmtl {}_{}\nKd {} {} {}\nd {}\n", name, i, color[0], color[1], color[2], color[3]).unwrap();
+        }
+
+        let mut obj = String::new();
+        let mtl_name = mtl_path.file_name().and_then(|file| file.to_str()).unwrap_or("mesh.mtl");
+        writeln!(obj, "# Written by gloom-rs\nmtllib {}\no {}", mtl_name, name).unwrap();
+        for p in self.vertices.chunks_exact(3) {
+            writeln!(obj, "v {} {} {}", p[0], p[1], p[2]).unwrap();
+        }
+        if has_uvs {
+            for uv in self.uvs.chunks_exact(2) {
+                writeln!(obj, "vt {} {}", uv[0], uv[1]).unwrap();
+            }
+        }
+        if has_normals {
+            for n in self.normals.chunks_exact(3) {
+                writeln!(obj, "vn {} {} {}", n[0], n[1], n[2]).unwrap();
+            }
+        }
+        for (i, triangles) in triangles_of_color.iter().enumerate() {
+            writeln!(obj, "usemtl {}_{}", name, i).unwrap();
+            for triangle in triangles {
+                obj.push('f');
+                for &index in triangle.iter() {
+                    // OBJ counts from 1
+                    let v = index + 1;
+                    match (has_uvs, has_normals) {
+                        (true, true)   => write!(obj, " {}/{}/{}", v, v, v),
+                        (true, false)  => write!(obj, " {}/{}", v, v),
+                        (false, true)  => write!(obj, " {}//{}", v, v),
+                        (false, false) => write!(obj, " {}", v),
+                    }.unwrap();
+                }
+                obj.push('\n');
+            }
+        }
+
+        write_file(&mtl_path, mtl.as_bytes())?;
+        write_file(path, obj.as_bytes())
+    }
+}
+
+// The most detailed mesh of what a node draws, if anything. The meshes are indexed by mesh id - 1.
+fn drawn_mesh<'a>(node: &SceneNode, meshes: &'a [Mesh]) -> Option<&'a Mesh> {
+    let mesh = node.vao_id.checked_sub(1).and_then(|i| meshes.get(i as usize))?;
+    if node.index_count > 0 && !mesh.indices.is_empty() { Some(mesh) } else { None }
+}
+
+// Everything drawn in a subtree as a single mesh, in the coordinates of its root, with the tints
+// of the nodes multiplied into the vertex colors. Normals and texture coordinates are only kept
+// if every mesh has them.
+pub fn bake(node: &SceneNode, meshes: &[Mesh]) -> Mesh {
+    let mut parts = vec![];
+    bake_subtree(node, meshes, &glm::identity(), &mut parts);
+
+    let has_normals = parts.iter().all(|part| part.normals.len() == 3 * part.vertex_count());
+    let has_uvs = parts.iter().all(|part| part.uvs.len() == 2 * part.vertex_count());
+    let (mut vertices, mut normals, mut colors, mut uvs, mut indices) = (vec![], vec![], vec![], vec![], vec![]);
+    for part in parts {
+        let offset = (vertices.len() / 3) as u32;
+        indices.extend(part.indices.iter().map(|index| index + offset));
+        vertices.extend(part.vertices);
+        colors.extend(part.colors);
+        if has_normals {
+            normals.extend(part.normals);
+        }
+        if has_uvs {
+            uvs.extend(part.uvs);
+        }
+    }
+    let mut baked = Mesh::new(vertices, normals, colors, indices);
+    baked.uvs = uvs;
+    baked
+}
+
+fn bake_subtree(node: &SceneNode, meshes: &[Mesh], transform: &glm::Mat4, parts: &mut Vec<Mesh>) {
+    if let Some(mesh) = drawn_mesh(node, meshes) {
+        let mut part = mesh.clone().transformed(transform);
+        let vertex_count = part.vertex_count();
+        part.colors.resize(4 * vertex_count, 1.0);
+        for color in part.colors.chunks_exact_mut(4) {
+            for (component, tint) in color.iter_mut().zip(node.material.tint.iter()) {
+                *component *= tint;
+            }
+        }
+        parts.push(part);
+    }
+    for &child in &node.children {
+        let child = unsafe { &*child };
+        bake_subtree(child, meshes, &(transform * child.local_transform()), parts);
+    }
+}
+
+struct GltfBuilder<'a> {
+    meshes       : &'a [Mesh], // Indexed by mesh id - 1
+    buffer       : Vec<u8>,
+    buffer_views : Vec<Json>,
+    accessors    : Vec<Json>,
+    gltf_meshes  : Vec<Json>,
+    materials    : Vec<Json>,
+    nodes        : Vec<Json>,
+    material_of_tint : HashMap<[u32; 4], usize>,
+    mesh_of_draw     : HashMap<(u32, [u32; 4]), usize>, // By mesh id and tint
+}
+
+impl<'a> GltfBuilder<'a> {
+    // Every component is four bytes, little endian, which keeps everything aligned
+    fn accessor(&mut self, data: &[u32], component_type: u32, kind: &str, target: u32, bounds: Option<(Json, Json)>) -> usize {
+        let components = match kind { "SCALAR" => 1, "VEC2" => 2, "VEC3" => 3, _ => 4 };
+        let offset = self.buffer.len();
+        self.buffer.extend(data.iter().flat_map(|value| value.to_le_bytes()));
+        self.buffer_views.push(Json::object(vec![
+            ("buffer", 0u32.into()),
+            ("byteOffset", offset.into()),
+            ("byteLength", (4 * data.len()).into()),
+            ("target", target.into()),
+        ]));
+
+        let mut accessor = vec![
+            ("bufferView", Json::from(self.buffer_views.len() - 1)),
+            ("componentType", component_type.into()),
+            ("count", (data.len() / components).into()),
+            ("type", kind.into()),
+        ];
+        if let Some((min, max)) = bounds {
+            accessor.push(("min", min));
+            accessor.push(("max", max));
+        }
+        self.accessors.push(Json::object(accessor));
+        self.accessors.len() - 1
+    }
+
+    fn material(&mut self, tint: &glm::Vec4) -> usize {
+        let materials = &mut self.materials;
+        *self.material_of_tint.entry([tint.x, tint.y, tint.z, tint.w].map(f32::to_bits)).or_insert_with(|| {
+            let mut material = vec![
+                ("pbrMetallicRoughness", Json::object(vec![
+                    ("baseColorFactor", vec![tint.x, tint.y, tint.z, tint.w].into()),
+                    ("metallicFactor", 0.0f32.into()),
+                    ("roughnessFactor", 1.0f32.into()),
+                ])),
+            ];
+            if tint.w < 1.0 {
+                material.push(("alphaMode", "BLEND".into()));
+            }
+            materials.push(Json::object(material));
+            materials.len() - 1
+        })
+    }
+
+    // Vertex colors are kept as they are, with the tint of the node as the material
+    fn add_mesh(&mut self, mesh: &Mesh, tint: &glm::Vec4) -> usize {
+        let bits = |values: &[f32]| -> Vec<u32> { values.iter().map(|value| value.to_bits()).collect() };
+        let vertex_count = mesh.vertex_count();
+        let bounds = (
+            Json::from(mesh.aabb.min.as_slice().to_vec()),
+            Json::from(mesh.aabb.max.as_slice().to_vec()),
+        );
+        let mut attributes = vec![("POSITION", Json::from(self.accessor(&bits(&mesh.vertices), FLOAT, "VEC3", ARRAY_BUFFER, Some(bounds))))];
+        if mesh.normals.len() == 3 * vertex_count {
+            attributes.push(("NORMAL", self.accessor(&bits(&mesh.normals), FLOAT, "VEC3", ARRAY_BUFFER, None).into()));
+        }
+        if mesh.colors.len() == 4 * vertex_count {
+            attributes.push(("COLOR_0", self.accessor(&bits(&mesh.colors), FLOAT, "VEC4", ARRAY_BUFFER, None).into()));
+        }
+        if mesh.uvs.len() == 2 * vertex_count {
+            attributes.push(("TEXCOORD_0", self.accessor(&bits(&mesh.uvs), FLOAT, "VEC2", ARRAY_BUFFER, None).into()));
+        }
+        let indices = self.accessor(&mesh.indices, UNSIGNED_INT, "SCALAR", ELEMENT_ARRAY_BUFFER, None);
+        let material = self.material(tint);
+
+        self.gltf_meshes.push(Json::object(vec![
+            ("primitives", Json::Array(vec![Json::object(vec![
+                ("attributes", Json::object(attributes)),
+                ("indices", indices.into()),
+                ("material", material.into()),
+            ])])),
+        ]));
+        self.gltf_meshes.len() - 1
+    }
+
+    fn add_hierarchical(&mut self, node: &SceneNode) -> usize {
+        let children: Vec<Json> = node.children.iter()
+            .map(|&child| self.add_hierarchical(unsafe { &*child }).into())
+            .collect();
+
+        let mut fields = vec![];
+        let transform = node.local_transform();
+        if transform != glm::identity::<f32, 4>() {
+            // Column major, like glm
+            fields.push(("matrix", Json::from(transform.as_slice().to_vec())));
+        }
+        if let Some(mesh) = drawn_mesh(node, self.meshes) {
+            let tint = node.material.tint;
+            let key = (node.vao_id, [tint.x, tint.y, tint.z, tint.w].map(f32::to_bits));
+            let index = match self.mesh_of_draw.get(&key) {
+                Some(&index) => index,
+                None => {
+                    let index = self.add_mesh(mesh, &tint);
+                    self.mesh_of_draw.insert(key, index);
+                    index
+                }
+            };
+            fields.push(("mesh", index.into()));
+        }
+        if !children.is_empty() {
+            fields.push(("children", Json::Array(children)));
+        }
+        self.nodes.push(Json::object(fields));
+        self.nodes.len() - 1
+    }
+}
+
+// The meshes are the ones uploaded to the renderer, indexed by mesh id - 1, like for picking.
+// The binary data goes in a .bin file with the same name.
+pub fn write_gltf(scene: &SceneNode, meshes: &[Mesh], path: &Path) -> Result<(), String> {
+    let mut builder = GltfBuilder {
+        meshes,
+        buffer           : vec![],
+        buffer_views     : vec![],
+        accessors        : vec![],
+        gltf_meshes      : vec![],
+        materials        : vec![],
+        nodes            : vec![],
+        material_of_tint : HashMap::new(),
+        mesh_of_draw     : HashMap::new(),
+    };
+    let root = builder.add_hierarchical(scene);
+
+    let bin_path = path.with_extension("bin");
+    let bin_name = bin_path.file_name().and_then(|file| file.to_str()).unwrap_or("scene.bin");
+    let mut gltf = vec![
+        ("asset", Json::object(vec![("version", "2.0".into()), ("generator", "gloom-rs".into())])),
+        ("scene", 0u32.into()),
+        ("scenes", Json::Array(vec![Json::object(vec![("nodes", vec![root].into())])])),
+        ("nodes", Json::Array(builder.nodes)),
+    ];
+    // glTF doesn't allow empty arrays, so a scene with nothing to draw has none of these
+    if !builder.gltf_meshes.is_empty() {
+        gltf.push(("meshes", Json::Array(builder.gltf_meshes)));
+        gltf.push(("materials", Json::Array(builder.materials)));
+        gltf.push(("accessors", Json::Array(builder.accessors)));
+        gltf.push(("bufferViews", Json::Array(builder.buffer_views)));
+        gltf.push(("buffers", Json::Array(vec![Json::object(vec![
+            ("byteLength", builder.buffer.len().into()),
+            ("uri", bin_name.into()),
+        ])])));
+        write_file(&bin_path, &builder.buffer)?;
+    }
+    write_file(path, Json::object(gltf).pretty().as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn obj_has_every_vertex_normal_and_triangle() {
+        let directory = std::env::temp_dir().join(format!("gloom-export-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("cube.obj");
+        let mut cube = Mesh::cube(2.0, 2, [1.0, 0.5, 0.0, 1.0]);
+        cube.write_obj(&path).unwrap();
+
+        let count = |prefix: &str| fs::read_to_string(&path).unwrap().lines().filter(|line| line.starts_with(prefix)).count();
+        assert_eq!(count("v "), cube.vertex_count());
+        assert_eq!(count("vn "), cube.vertex_count());
+        assert_eq!(count("f "), cube.triangle_count());
+        assert_eq!(count("usemtl "), 1);
+        let loaded = Mesh::load(&path).unwrap();
+        assert_eq!(loaded.triangle_count(), cube.triangle_count());
+        assert_eq!(loaded.aabb, cube.aabb);
+
+        // A material for every color
+        cube.colors[..4].copy_from_slice(&[0.0, 0.0, 1.0, 0.5]);
+        cube.write_obj(&path).unwrap();
+        assert_eq!(count("f "), cube.triangle_count());
+        assert_eq!(count("usemtl "), 2);
+        let mtl = fs::read_to_string(path.with_extension("mtl")).unwrap();
+        assert_eq!(mtl.matches("newmtl ").count(), 2);
+        assert!(mtl.contains("Kd 0 0 1\nd 0.5\n"), "{}", mtl);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn baking_moves_and_tints_every_part() {
+        let meshes = [Mesh::cube(1.0, 1, [1.0; 4]), Mesh::plane(1.0, 1.0, 1, 1, [0.5; 4])];
+        let mut root = SceneNode::new();
+        let mut cube = SceneNode::from_vao(1, meshes[0].index_count);
+        cube.position = glm::vec3(10.0, 0.0, 0.0);
+        cube.scale = glm::vec3(2.0, 2.0, 2.0);
+        cube.material.tint = glm::vec4(1.0, 0.0, 0.0, 1.0);
+        let mut plane = SceneNode::from_vao(2, meshes[1].index_count);
+        plane.position = glm::vec3(0.0, -1.0, 0.0);
+        let nothing = SceneNode::from_vao(3, 6); // Not uploaded, so not drawn
+        root.position = glm::vec3(100.0, 0.0, 0.0); // The root's own transformation isn't baked in
+        root.add_child(&cube);
+        cube.add_child(&plane);
+        root.add_child(&nothing);
+
+        let baked = bake(&root, &meshes);
+        assert_eq!(baked.vertex_count(), meshes[0].vertex_count() + meshes[1].vertex_count());
+        assert_eq!(baked.triangle_count(), meshes[0].triangle_count() + meshes[1].triangle_count());
+        assert_eq!(baked.aabb.min, glm::vec3(9.0, -2.0, -1.0));
+        assert_eq!(baked.aabb.max, glm::vec3(11.0, 1.0, 1.0));
+        assert_eq!(&baked.colors[..4], &[1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(&baked.colors[baked.colors.len() - 4..], &[0.5, 0.5, 0.5, 0.5]); // Tints are not inherited
+        assert!(baked.validate().is_ok());
+    }
+}
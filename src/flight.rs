@@ -0,0 +1,199 @@
+extern crate nalgebra_glm as glm;
+
+// A simplified helicopter flight model. The main rotor pushes the helicopter along its up axis, as
+// hard as the collective and the speed of the rotor have it, and a bit harder close to the ground.
+// Rather than working out the forces on the rotor disc, the cyclic tilts the helicopter straight
+// towards an attitude, and the pedals of the tail rotor turn it around at a rate, both only as far
+// as the rotor turns. The engine spools the rotor up to the throttle, or down, over a few seconds.
+//
+// Nothing in here knows about nodes or rigid bodies, see components::fly for what drives them.
+//
+//     let acceleration = flight::step(&model, &controls, &mut state, &velocity, height, &gravity, timestep);
+//     velocity += (acceleration + gravity) * timestep;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FlightModel {
+    pub max_thrust   : f32, // Lift with the collective all the way up, as a multiple of the weight
+    pub max_tilt     : f32, // How far over the cyclic tilts it, in radians
+    pub tilt_rate    : f32, // How quickly it settles into the tilt, per second
+    pub yaw_rate     : f32, // How fast the pedals turn it, in radians per second
+    pub spool_rate   : f32, // How much of its full speed the rotor gains or loses per second
+    pub drag         : f32, // The fraction of its velocity lost every second
+    pub rotor_radius : f32,
+    pub rotor_height : f32, // How high the rotor is above the origin of the node
+}
+
+impl Default for FlightModel {
+    // Fits the helicopter model
+    fn default() -> Self {
+        FlightModel {
+            max_thrust   : 1.5,
+            max_tilt     : 0.5,
+            tilt_rate    : 3.0,
+            yaw_rate     : 1.2,
+            spool_rate   : 0.25,
+            drag         : 0.15,
+            rotor_radius : 5.5,
+            rotor_height : 3.2,
+        }
+    }
+}
+
+// What the pilot does with the controls, from -1 to 1 apart from the throttle
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Controls {
+    pub collective : f32,       // Up to climb and down to sink, 0 hovers when level and clear of the ground
+    pub cyclic     : glm::Vec2, // Tilting it, x to the right and y forward
+    pub pedals     : f32,       // Turning it, to the right
+    pub throttle   : f32,       // How fast the engine drives the rotor, from 0 for off to 1 for full speed
+}
+
+impl Default for Controls {
+    // Hands off, with the engine running
+    fn default() -> Self {
+        Controls { collective: 0.0, cyclic: glm::zero(), pedals: 0.0, throttle: 1.0 }
+    }
+}
+
+// How the helicopter is turned: around the vertical first, then nose up, then over to the left.
+// Unlike the rotation angles of a node, these are about the helicopter's own axes, which is what
+// the controls work on. The nose points along -z.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Attitude {
+    pub yaw   : f32,
+    pub pitch : f32,
+    pub roll  : f32,
+}
+
+impl Attitude {
+    // From the rotation angles of a node, see SceneNode::local_transform
+    pub fn from_rotation(rotation: &glm::Vec3) -> Self {
+        let mut m: glm::Mat4 = glm::identity();
+        m = glm::rotate_x(&m, rotation.x);
+        m = glm::rotate_y(&m, rotation.y);
+        m = glm::rotate_z(&m, rotation.z);
+        Attitude {
+            yaw   : m[(0, 2)].atan2(m[(2, 2)]),
+            pitch : (-m[(1, 2)]).clamp(-1.0, 1.0).asin(),
+            roll  : m[(1, 0)].atan2(m[(1, 1)]),
+        }
+    }
+
+    // The rotation angles which turn a node the same way
+    pub fn rotation(&self) -> glm::Vec3 {
+        let m = self.matrix();
+        glm::vec3(
+            (-m[(1, 2)]).atan2(m[(2, 2)]),
+            m[(0, 2)].clamp(-1.0, 1.0).asin(),
+            (-m[(0, 1)]).atan2(m[(0, 0)]),
+        )
+    }
+
+    // Which way the rotor pushes
+    pub fn up(&self) -> glm::Vec3 {
+        (self.matrix() * glm::vec4(0.0, 1.0, 0.0, 0.0)).xyz()
+    }
+
+    fn matrix(&self) -> glm::Mat4 {
+        let mut m: glm::Mat4 = glm::identity();
+        m = glm::rotate_y(&m, self.yaw);
+        m = glm::rotate_x(&m, self.pitch);
+        glm::rotate_z(&m, self.roll)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FlightState {
+    pub attitude    : Attitude,
+    pub rotor_speed : f32, // The fraction of its full speed
+}
+
+// How much more lift the rotor gives at a height above the ground, as Cheeseman and Bennett have
+// it. Next to nothing once the rotor is more than its diameter up, and a third more right at the
+// ground, which is as close as the rotor gets.
+pub fn ground_effect(model: &FlightModel, height: f32) -> f32 {
+    let height = (height + model.rotor_height).max(model.rotor_radius / 2.0);
+    1.0 / (1.0 - (model.rotor_radius / (4.0 * height)).powi(2))
+}
+
+// Only the engine's part of a tick, for when something else is flying the helicopter
+pub fn spool(model: &FlightModel, controls: &Controls, state: &mut FlightState, timestep: f32) {
+    let change = model.spool_rate * timestep;
+    state.rotor_speed += (controls.throttle.clamp(0.0, 1.0) - state.rotor_speed).clamp(-change, change);
+}
+
+// One tick: the rotor spools towards the throttle, and the helicopter turns as the cyclic and the
+// pedals have it. Returns the acceleration the rotor and the drag give it, which gravity still has
+// to be added to. The height is that of the origin above the ground, infinite with none below.
+pub fn step(model: &FlightModel, controls: &Controls, state: &mut FlightState, velocity: &glm::Vec3, height: f32, gravity: &glm::Vec3, timestep: f32) -> glm::Vec3 {
+    spool(model, controls, state, timestep);
+
+    let authority = state.rotor_speed;
+    let settle = (1.0 - (-model.tilt_rate * timestep).exp()) * authority;
+    let attitude = &mut state.attitude;
+    attitude.pitch += (-controls.cyclic.y.clamp(-1.0, 1.0) * model.max_tilt - attitude.pitch) * settle;
+    attitude.roll += (-controls.cyclic.x.clamp(-1.0, 1.0) * model.max_tilt - attitude.roll) * settle;
+    attitude.yaw -= controls.pedals.clamp(-1.0, 1.0) * model.yaw_rate * authority * timestep;
+
+    // As a multiple of the weight: none with the collective all the way down, just enough to hover
+    // in the middle and max_thrust all the way up
+    let collective = controls.collective.clamp(-1.0, 1.0);
+    let thrust = if collective >= 0.0 { 1.0 + collective * (model.max_thrust - 1.0) } else { 1.0 + collective };
+    let lift = thrust * glm::length(gravity) * state.rotor_speed.powi(2) * ground_effect(model, height);
+    state.attitude.up() * lift - velocity * model.drag
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRAVITY: glm::Vec3 = glm::Vec3::new(0.0, -9.81, 0.0);
+    const TIMESTEP: f32 = 1.0 / 60.0;
+
+    fn level() -> FlightState {
+        FlightState { attitude: Attitude::default(), rotor_speed: 1.0 }
+    }
+
+    #[test]
+    fn hovers_with_the_collective_in_the_middle() {
+        let model = FlightModel::default();
+        let acceleration = step(&model, &Controls::default(), &mut level(), &glm::zero(), f32::INFINITY, &GRAVITY, TIMESTEP);
+        assert!(glm::distance(&acceleration, &-GRAVITY) < 1e-5, "{:?}", acceleration);
+    }
+
+    #[test]
+    fn ground_effect_only_helps_near_the_ground() {
+        let model = FlightModel::default();
+        assert_eq!(ground_effect(&model, f32::INFINITY), 1.0);
+        assert!(ground_effect(&model, 1000.0) - 1.0 < 1e-4);
+        assert!(ground_effect(&model, 0.0) > 1.1);
+        assert!(ground_effect(&model, 0.0) > ground_effect(&model, 5.0));
+    }
+
+    #[test]
+    fn rotor_spools_no_faster_than_the_spool_rate() {
+        let model = FlightModel::default();
+        let limit = model.spool_rate * TIMESTEP;
+        let mut state = level();
+        spool(&model, &Controls { throttle: 0.0, ..Controls::default() }, &mut state, TIMESTEP);
+        assert!((state.rotor_speed - (1.0 - limit)).abs() < 1e-6);
+
+        // Nor past the throttle
+        state.rotor_speed = 0.5 - limit / 2.0;
+        spool(&model, &Controls { throttle: 0.5, ..Controls::default() }, &mut state, TIMESTEP);
+        assert_eq!(state.rotor_speed, 0.5);
+        spool(&model, &Controls { throttle: 2.0, ..Controls::default() }, &mut state, TIMESTEP);
+        assert!((state.rotor_speed - (0.5 + limit)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn attitude_round_trips_through_rotation_angles() {
+        for &(yaw, pitch, roll) in &[(0.0, 0.0, 0.0), (0.3, -0.2, 0.1), (2.9, 0.4, -0.3), (-1.7, -0.45, 0.45)] {
+            let attitude = Attitude { yaw, pitch, roll };
+            let back = Attitude::from_rotation(&attitude.rotation());
+            for (a, b) in [(attitude.yaw, back.yaw), (attitude.pitch, back.pitch), (attitude.roll, back.roll)] {
+                assert!((a - b).abs() < 1e-5, "{:?} came back as {:?}", attitude, back);
+            }
+        }
+    }
+}
@@ -0,0 +1,220 @@
+extern crate nalgebra_glm as glm;
+
+use std::f32::consts::PI;
+use std::time::Duration;
+
+use crate::scene_graph::SceneNode;
+
+// The simulation moves forward in ticks of the same length whatever the frame rate, so that it
+// plays out the same every run. Frames bank the time they took, and run as many ticks as fit in
+// the bank. What's left over says how far the next tick has come, which the frame shows by
+// blending the last two ticks together.
+//
+//     clock.advance(frame_time);
+//     interpolation.restore();
+//     while let Some(time) = clock.next_tick() {
+//         interpolation.begin_tick();
+//         ... move things to where they are at time ...
+//         interpolation.end_tick();
+//     }
+//     interpolation.apply(clock.alpha());
+
+// How long a frame can count for at most. A frame which took longer, because of a breakpoint or
+// the window being dragged around, slows the simulation down rather than having it run so many
+// ticks that the next frame takes even longer.
+const MAX_FRAME_TIME: Duration = Duration::from_millis(250);
+
+pub struct GameClock {
+    pub timestep   : Duration,
+    pub time_scale : f32,  // How fast the simulation runs, 1 being real time
+    pub paused     : bool, // No time passes, but single steps still go through
+    accumulator    : Duration,
+    pending_steps  : u32,
+    ticks          : u64,
+}
+
+impl GameClock {
+    pub fn new(timestep: Duration) -> Self {
+        GameClock {
+            timestep,
+            time_scale    : 1.0,
+            paused        : false,
+            accumulator   : Duration::ZERO,
+            pending_steps : 0,
+            ticks         : 0,
+        }
+    }
+
+    // Bank the real time a frame took. Scaled in f64, which keeps every nanosecond at the usual
+    // scales, so that the bank holds the same however the time was split into frames.
+    pub fn advance(&mut self, frame_time: Duration) {
+        if !self.paused {
+            self.accumulator += frame_time.min(MAX_FRAME_TIME).mul_f64(self.time_scale as f64);
+        }
+    }
+
+    // Runs one tick the next time ticks are taken, even while paused
+    pub fn step(&mut self) {
+        self.pending_steps += 1;
+    }
+
+    // Take one tick out of the bank, if there's enough in it. Returns the simulation time at the
+    // end of the tick, in seconds.
+    pub fn next_tick(&mut self) -> Option<f32> {
+        if self.pending_steps > 0 {
+            self.pending_steps -= 1;
+        } else if self.accumulator >= self.timestep {
+            self.accumulator -= self.timestep;
+        } else {
+            return None;
+        }
+        self.ticks += 1;
+        Some(self.time())
+    }
+
+    // The simulation time, counting whole ticks only. Always the same after the same number of
+    // ticks, however the frames went.
+    pub fn time(&self) -> f32 {
+        (self.ticks as f64 * self.timestep.as_secs_f64()) as f32
+    }
+
+    // How far along the next tick the frame is, from 0 to 1
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator.as_secs_f64() / self.timestep.as_secs_f64()) as f32
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub position : glm::Vec3,
+    pub rotation : glm::Vec3,
+    pub scale    : glm::Vec3,
+}
+
+impl Transform {
+    pub fn of(node: &SceneNode) -> Self {
+        Transform { position: node.position, rotation: node.rotation, scale: node.scale }
+    }
+
+    pub fn apply_to(&self, node: &mut SceneNode) {
+        node.position = self.position;
+        node.rotation = self.rotation;
+        node.scale = self.scale;
+    }
+
+    // Angles go the short way around, so that a yaw going from just below pi to just above -pi
+    // doesn't spin all the way back for one frame
+    pub fn lerp(&self, other: &Transform, t: f32) -> Transform {
+        let angle = |from: f32, to: f32| {
+            let difference = (to - from + PI).rem_euclid(2.0 * PI) - PI;
+            from + difference * t
+        };
+        Transform {
+            position : glm::lerp(&self.position, &other.position, t),
+            rotation : glm::vec3(
+                angle(self.rotation.x, other.rotation.x),
+                angle(self.rotation.y, other.rotation.y),
+                angle(self.rotation.z, other.rotation.z),
+            ),
+            scale    : glm::lerp(&self.scale, &other.scale, t),
+        }
+    }
+}
+
+// Remembers where the simulated nodes were after the last two ticks. Between frames the nodes
+// are left where the frame showed them, so restore has to put them back before the next tick.
+pub struct Interpolation {
+    nodes    : Vec<*mut SceneNode>,
+    previous : Vec<Transform>,
+    current  : Vec<Transform>,
+}
+
+impl Interpolation {
+    pub fn new(nodes: Vec<*mut SceneNode>) -> Self {
+        let current: Vec<Transform> = nodes.iter().map(|&node| Transform::of(unsafe { &*node })).collect();
+        Interpolation { nodes, previous: current.clone(), current }
+    }
+
+    pub fn restore(&self) {
+        for (&node, transform) in self.nodes.iter().zip(&self.current) {
+            transform.apply_to(unsafe { &mut *node });
+        }
+    }
+
+    pub fn begin_tick(&mut self) {
+        self.previous.copy_from_slice(&self.current);
+    }
+
+    pub fn end_tick(&mut self) {
+        for (&node, transform) in self.nodes.iter().zip(self.current.iter_mut()) {
+            *transform = Transform::of(unsafe { &*node });
+        }
+    }
+
+    // Place the nodes alpha of the way from where they were after the tick before the last, to
+    // where they are after the last
+    pub fn apply(&self, alpha: f32) {
+        for ((&node, previous), current) in self.nodes.iter().zip(&self.previous).zip(&self.current) {
+            previous.lerp(current, alpha.clamp(0.0, 1.0)).apply_to(unsafe { &mut *node });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMESTEP: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+    fn ticks(clock: &mut GameClock) -> usize {
+        std::iter::from_fn(|| clock.next_tick()).count()
+    }
+
+    #[test]
+    fn same_ticks_however_the_frames_went() {
+        let mut one_frame = GameClock::new(TIMESTEP);
+        one_frame.advance(Duration::from_millis(100));
+        let mut ten_frames = GameClock::new(TIMESTEP);
+        let mut ten_frame_ticks = 0;
+        for _ in 0..10 {
+            ten_frames.advance(Duration::from_millis(10));
+            ten_frame_ticks += ticks(&mut ten_frames);
+        }
+        assert_eq!(ticks(&mut one_frame), 6);
+        assert_eq!(ten_frame_ticks, 6);
+        assert_eq!(one_frame.time(), ten_frames.time());
+        assert_eq!(one_frame.alpha(), ten_frames.alpha());
+    }
+
+    #[test]
+    fn long_frames_count_for_max_frame_time() {
+        let mut clock = GameClock::new(TIMESTEP);
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(ticks(&mut clock), 15); // 250ms of 16.7ms ticks
+    }
+
+    #[test]
+    fn paused_clock_only_takes_single_steps() {
+        let mut clock = GameClock::new(TIMESTEP);
+        clock.toggle_pause();
+        clock.advance(Duration::from_millis(100));
+        assert_eq!(ticks(&mut clock), 0);
+        clock.step();
+        clock.advance(Duration::from_millis(100));
+        assert_eq!(ticks(&mut clock), 1);
+        assert_eq!(clock.time(), TIMESTEP.as_secs_f32());
+    }
+
+    #[test]
+    fn angles_blend_the_short_way_around() {
+        let at = |yaw: f32| Transform { position: glm::zero(), rotation: glm::vec3(0.0, yaw, 0.0), scale: glm::vec3(1.0, 1.0, 1.0) };
+        let halfway = at(PI - 0.1).lerp(&at(-PI + 0.1), 0.5).rotation.y;
+        assert!((halfway - PI).abs() < 1e-5, "{}", halfway);
+        let quarter = at(-PI + 0.1).lerp(&at(PI - 0.1), 0.25).rotation.y;
+        assert!((quarter - (-PI + 0.05)).abs() < 1e-5, "{}", quarter);
+    }
+}
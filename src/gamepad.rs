@@ -0,0 +1,91 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Read;
+use std::sync::mpsc::Sender;
+use std::thread;
+
+use glutin::event::ElementState;
+
+use crate::events::WindowMessage;
+
+// Gamepads, which glutin doesn't report, read through the Linux joystick driver. The device is
+// read on a thread of its own, which sends what happens to the render thread along with the
+// window events. Where there is no such device, like on other platforms, there's no gamepad.
+
+// The first gamepad plugged in
+const DEVICE_PATH: &str = "/dev/input/js0";
+
+// The kinds of events, from linux/joystick.h
+const JS_EVENT_BUTTON : u8 = 0x01;
+const JS_EVENT_AXIS   : u8 = 0x02;
+const JS_EVENT_INIT   : u8 = 0x80; // Set on the events telling how things were when the device was opened
+
+// The message for one struct js_event { u32 time; i16 value; u8 type; u8 number; }, in the byte
+// order of the machine, if it's of a kind which is known
+pub fn parse_event(event: &[u8; 8]) -> Option<WindowMessage> {
+    let value = i16::from_ne_bytes([event[4], event[5]]);
+    let number = event[7] as u32;
+    match event[6] & !JS_EVENT_INIT {
+        JS_EVENT_AXIS => Some(WindowMessage::GamepadAxis { axis: number, value: value as f32 / i16::MAX as f32 }),
+        JS_EVENT_BUTTON => Some(WindowMessage::GamepadButton {
+            button : number,
+            state  : if value != 0 { ElementState::Pressed } else { ElementState::Released },
+        }),
+        _ => None,
+    }
+}
+
+// Start reading the gamepad, if one is plugged in
+pub fn read_gamepad(messages: Sender<WindowMessage>) -> Result<(), String> {
+    let mut device = File::open(DEVICE_PATH).map_err(|e| format!("Failed to open {}: {}", DEVICE_PATH, e))?;
+    thread::spawn(move || {
+        let (mut moved_axes, mut held_buttons) = (HashSet::new(), HashSet::new());
+        let mut event = [0; 8];
+        // Until the gamepad is unplugged, or nobody is listening anymore
+        while device.read_exact(&mut event).is_ok() {
+            let message = match parse_event(&event) {
+                Some(message) => message,
+                None => continue,
+            };
+            match message {
+                WindowMessage::GamepadAxis { axis, .. } => { moved_axes.insert(axis); }
+                WindowMessage::GamepadButton { button, state: ElementState::Pressed } => { held_buttons.insert(button); }
+                WindowMessage::GamepadButton { button, .. } => { held_buttons.remove(&button); }
+                _ => { }
+            }
+            if messages.send(message).is_err() {
+                return;
+            }
+        }
+        // Leave nothing held or pushed over once the gamepad is gone
+        println!("The gamepad was unplugged");
+        for axis in moved_axes {
+            let _ = messages.send(WindowMessage::GamepadAxis { axis, value: 0.0 });
+        }
+        for button in held_buttons {
+            let _ = messages.send(WindowMessage::GamepadButton { button, state: ElementState::Released });
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(value: i16, kind: u8, number: u8) -> [u8; 8] {
+        let value = value.to_ne_bytes();
+        [0, 0, 0, 0, value[0], value[1], kind, number]
+    }
+
+    #[test]
+    fn joystick_events_become_messages() {
+        assert_eq!(parse_event(&event(-32767, JS_EVENT_AXIS, 1)), Some(WindowMessage::GamepadAxis { axis: 1, value: -1.0 }));
+        assert_eq!(parse_event(&event(0, JS_EVENT_AXIS | JS_EVENT_INIT, 4)), Some(WindowMessage::GamepadAxis { axis: 4, value: 0.0 }));
+        assert_eq!(parse_event(&event(1, JS_EVENT_BUTTON, 3)),
+                   Some(WindowMessage::GamepadButton { button: 3, state: ElementState::Pressed }));
+        assert_eq!(parse_event(&event(0, JS_EVENT_BUTTON, 3)),
+                   Some(WindowMessage::GamepadButton { button: 3, state: ElementState::Released }));
+        assert_eq!(parse_event(&event(1, 0x04, 0)), None);
+    }
+}
@@ -0,0 +1,438 @@
+extern crate nalgebra_glm as glm;
+
+use std::{ mem, ptr, os::raw::c_void };
+
+use image::RgbaImage;
+
+use crate::mesh::Mesh;
+use crate::renderer::{BlendState, CullState, DrawCommand, Lighting, Material, RenderState, Renderer, MAX_LIGHTS};
+use crate::shader::Shader;
+
+// Get the size of an arbitrary array of numbers measured in bytes
+// Example usage:  byte_size_of_array(my_array)
+fn byte_size_of_array<T>(val: &[T]) -> isize {
+    std::mem::size_of_val(val) as isize
+}
+
+// Get the OpenGL-compatible pointer to an arbitrary array of numbers
+// Example usage:  pointer_to_array(my_array)
+fn pointer_to_array<T>(val: &[T]) -> *const c_void {
+    val.as_ptr() as *const c_void
+}
+
+// Get the size of the given type in bytes
+// Example usage:  size_of::<u64>()
+fn size_of<T>() -> i32 {
+    mem::size_of::<T>() as i32
+}
+
+// Get an offset in bytes for n units of type T, represented as a relative pointer
+// Example usage:  offset::<u64>(4)
+fn offset<T>(n: u32) -> *const c_void {
+    (n * mem::size_of::<T>() as u32) as *const T as *const c_void
+}
+
+// Upload a vertex attribute to a new buffer, bound to the given location of the current VAO.
+// A missing attribute gets no buffer and is left disabled, so the shader reads the constant
+// value of the attribute instead of reading past the end of an empty buffer. Returns 0 then.
+unsafe fn create_attribute_buffer(location: u32, components: i32, data: &[f32]) -> u32 {
+    if data.is_empty() {
+        gl::DisableVertexAttribArray(location);
+        return 0;
+    }
+
+    let mut vbo: u32 = 0;
+    gl::GenBuffers(1, &mut vbo);
+    gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+    gl::BufferData(
+        gl::ARRAY_BUFFER,
+        byte_size_of_array(data),
+        pointer_to_array(data),
+        gl::STATIC_DRAW
+    );
+
+    gl::EnableVertexAttribArray(location);
+    gl::VertexAttribPointer(
+        location,
+        components,
+        gl::FLOAT,
+        gl::FALSE,
+        components * size_of::<f32>(),
+        ptr::null()
+    );
+    vbo
+}
+
+// Bind the per instance model matrices to locations 3 to 6 of the current VAO. A mat4 attribute
+// takes up four locations, one for each column.
+unsafe fn bind_instance_buffer(instance_vbo: u32) {
+    gl::BindBuffer(gl::ARRAY_BUFFER, instance_vbo);
+    for column in 0..4 {
+        let location = 3 + column;
+        gl::EnableVertexAttribArray(location);
+        gl::VertexAttribPointer(
+            location,
+            4,
+            gl::FLOAT,
+            gl::FALSE,
+            size_of::<glm::Mat4>(),
+            offset::<glm::Vec4>(column)
+        );
+        gl::VertexAttribDivisor(location, 1);
+    }
+}
+
+// A mesh living on the GPU
+struct GlMesh {
+    vao         : u32,
+    buffers     : Vec<u32>, // Owned by the VAO, except for the shared instance buffer
+    translucent : bool,     // Whether any of the vertex colors are
+}
+
+unsafe fn create_vao(vertices: &[f32], vertices_color: &[f32], indices: &[u32], normals: &[f32], instance_vbo: u32) -> (u32, Vec<u32>) {
+    let mut vao: u32 = 0;
+    gl::GenVertexArrays(1, &mut vao);
+    gl::BindVertexArray(vao);
+
+    let mut buffers: Vec<u32> = [
+        create_attribute_buffer(0, 3, vertices),
+        create_attribute_buffer(1, 4, vertices_color),
+        create_attribute_buffer(2, 3, normals),
+    ].iter().copied().filter(|&vbo| vbo != 0).collect();
+    bind_instance_buffer(instance_vbo);
+
+    let mut ebo: u32 = 0;
+    gl::GenBuffers(1, &mut ebo);
+    gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo);
+    gl::BufferData(
+        gl::ELEMENT_ARRAY_BUFFER,
+        byte_size_of_array(indices),
+        pointer_to_array(indices),
+        gl::STATIC_DRAW,
+    );
+    buffers.push(ebo);
+
+    (vao, buffers)
+}
+
+unsafe fn draw_vao(vao: u32, index_count: i32){
+    gl::BindVertexArray(vao);
+    gl::DrawElements(gl::TRIANGLES, index_count, gl::UNSIGNED_INT, std::ptr::null())
+}
+
+unsafe fn apply_state(state: &RenderState) {
+    if state.depth.test {
+        gl::Enable(gl::DEPTH_TEST);
+        gl::DepthFunc(gl::LESS);
+    } else {
+        gl::Disable(gl::DEPTH_TEST);
+    }
+    gl::DepthMask(if state.depth.write { gl::TRUE } else { gl::FALSE });
+
+    match state.blend {
+        BlendState::Opaque => gl::Disable(gl::BLEND),
+        BlendState::AlphaBlend => {
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        }
+    }
+
+    match state.cull {
+        CullState::Disabled => gl::Disable(gl::CULL_FACE),
+        CullState::Back => {
+            gl::Enable(gl::CULL_FACE);
+            gl::CullFace(gl::BACK);
+        }
+        CullState::Front => {
+            gl::Enable(gl::CULL_FACE);
+            gl::CullFace(gl::FRONT);
+        }
+    }
+}
+
+// Where simple.frag takes its lights, in one of the programs using it
+struct LightingUniforms {
+    ambient    : i32,
+    count      : i32,
+    directions : i32,
+    colors     : i32,
+}
+
+impl LightingUniforms {
+    unsafe fn locate(shader: &Shader) -> Self {
+        LightingUniforms {
+            ambient    : shader.get_uniform_location("ambient"),
+            count      : shader.get_uniform_location("lightCount"),
+            directions : shader.get_uniform_location("lightDirections"),
+            colors     : shader.get_uniform_location("lightColors"),
+        }
+    }
+
+    // The program has to be active
+    unsafe fn set(&self, lighting: &Lighting) {
+        let lights = &lighting.lights[..lighting.lights.len().min(MAX_LIGHTS)];
+        let directions: Vec<glm::Vec3> = lights.iter().map(|light| glm::normalize(&light.direction)).collect();
+        let colors: Vec<glm::Vec3> = lights.iter().map(|light| light.color).collect();
+        gl::Uniform3fv(self.ambient, 1, lighting.ambient.as_ptr());
+        gl::Uniform1i(self.count, lights.len() as i32);
+        if !lights.is_empty() {
+            gl::Uniform3fv(self.directions, lights.len() as i32, directions[0].as_ptr());
+            gl::Uniform3fv(self.colors, lights.len() as i32, colors[0].as_ptr());
+        }
+    }
+}
+
+// The OpenGL implementation of Renderer. Single draws use simple.vert, while instanced draws use
+// instanced.vert, which reads the model matrix from a per instance attribute instead of a
+// uniform. Both are paired with simple.frag. It must only be used on the thread owning the
+// OpenGL context.
+pub struct GlRenderer {
+    shader                : Shader,
+    u_model_loc           : i32,
+    u_view_loc            : i32,
+    u_tint_loc            : i32,
+    u_lighting            : LightingUniforms,
+    instanced_shader      : Shader,
+    u_instanced_view_loc  : i32,
+    u_instanced_tint_loc  : i32,
+    u_instanced_lighting  : LightingUniforms,
+    instance_vbo          : u32, // Shared by all VAOs, refilled for every instanced draw
+    meshes                : Vec<Option<GlMesh>>, // Indexed by mesh id - 1, None once deleted
+    state                 : RenderState,
+    view_projection       : glm::Mat4,
+}
+
+impl GlRenderer {
+    pub unsafe fn new(shader: Shader, instanced_shader: Shader) -> Self {
+        let mut instance_vbo: u32 = 0;
+        gl::GenBuffers(1, &mut instance_vbo);
+
+        let mut renderer = GlRenderer {
+            u_model_loc           : shader.get_uniform_location("model"),
+            u_view_loc            : shader.get_uniform_location("modelViewProj"),
+            u_tint_loc            : shader.get_uniform_location("tint"),
+            u_lighting            : LightingUniforms::locate(&shader),
+            shader,
+            u_instanced_view_loc  : instanced_shader.get_uniform_location("modelViewProj"),
+            u_instanced_tint_loc  : instanced_shader.get_uniform_location("tint"),
+            u_instanced_lighting  : LightingUniforms::locate(&instanced_shader),
+            instanced_shader,
+            instance_vbo,
+            meshes                : vec![],
+            state                 : RenderState::default(),
+            view_projection       : glm::identity(),
+        };
+        apply_state(&renderer.state);
+        // The constant the colors of meshes without any are, (0, 0, 0, 1) unless set. White, as
+        // in the software renderer.
+        gl::VertexAttrib4f(1, 1.0, 1.0, 1.0, 1.0);
+        renderer.set_lighting(&Lighting::default());
+        renderer
+    }
+
+    // What has been drawn to the window so far, top row first
+    pub fn read_pixels(&self, width: u32, height: u32) -> RgbaImage {
+        let mut pixels = vec![0u8; 4 * (width * height) as usize];
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::ReadPixels(0, 0, width as i32, height as i32, gl::RGBA, gl::UNSIGNED_BYTE, pixels.as_mut_ptr() as *mut c_void);
+        }
+        // Whatever ended up in the alpha channel of the window isn't what anyone sees
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel[3] = 255;
+        }
+        // OpenGL counts rows from the bottom up
+        let image = RgbaImage::from_raw(width, height, pixels).unwrap();
+        image::imageops::flip_vertical(&image)
+    }
+
+    fn vao(&self, mesh_id: u32) -> Option<u32> {
+        mesh_id.checked_sub(1).and_then(|i| self.meshes.get(i as usize)).and_then(|mesh| mesh.as_ref()).map(|mesh| mesh.vao)
+    }
+}
+
+impl Renderer for GlRenderer {
+    fn upload_mesh(&mut self, mesh: &Mesh) -> (u32, i32) {
+        let (vao, buffers) = unsafe { create_vao(&mesh.vertices, &mesh.colors, &mesh.indices, &mesh.normals, self.instance_vbo) };
+        self.meshes.push(Some(GlMesh { vao, buffers, translucent: mesh.has_translucent_colors() }));
+        (self.meshes.len() as u32, mesh.index_count)
+    }
+
+    fn delete_mesh(&mut self, mesh_id: u32) {
+        let gl_mesh = mesh_id.checked_sub(1).and_then(|i| self.meshes.get_mut(i as usize)).and_then(|mesh| mesh.take());
+        if let Some(gl_mesh) = gl_mesh {
+            unsafe {
+                gl::DeleteVertexArrays(1, &gl_mesh.vao);
+                gl::DeleteBuffers(gl_mesh.buffers.len() as i32, gl_mesh.buffers.as_ptr());
+            }
+        }
+    }
+
+    fn has_translucent_vertices(&self, mesh_id: u32) -> bool {
+        mesh_id.checked_sub(1).and_then(|i| self.meshes.get(i as usize)).and_then(|mesh| mesh.as_ref()).is_some_and(|mesh| mesh.translucent)
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        unsafe { gl::Viewport(0, 0, width as i32, height as i32); }
+    }
+
+    fn begin_frame(&mut self, clear_color: &glm::Vec4, view_projection: &glm::Mat4) {
+        self.view_projection = *view_projection;
+        // glClear respects the depth mask, so it has to be restored before clearing
+        self.set_state(&RenderState::default());
+        unsafe {
+            gl::ClearColor(clear_color.x, clear_color.y, clear_color.z, clear_color.w);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+            self.instanced_shader.activate();
+            gl::UniformMatrix4fv(self.u_instanced_view_loc, 1, gl::FALSE, self.view_projection.as_ptr());
+            self.shader.activate();
+            gl::UniformMatrix4fv(self.u_view_loc, 1, gl::FALSE, self.view_projection.as_ptr());
+        }
+    }
+
+    fn set_state(&mut self, state: &RenderState) {
+        if self.state != *state {
+            self.state = *state;
+            unsafe { apply_state(state); }
+        }
+    }
+
+    fn set_lighting(&mut self, lighting: &Lighting) {
+        unsafe {
+            self.instanced_shader.activate();
+            self.u_instanced_lighting.set(lighting);
+            self.shader.activate();
+            self.u_lighting.set(lighting);
+        }
+    }
+
+    fn submit(&mut self, command: &DrawCommand) {
+        let vao = match self.vao(command.mesh_id) {
+            Some(vao) => vao,
+            None => return,
+        };
+        unsafe {
+            self.shader.activate();
+            gl::UniformMatrix4fv(self.u_model_loc, 1, gl::FALSE, command.transform.as_ptr());
+            gl::Uniform4fv(self.u_tint_loc, 1, command.material.tint.as_ptr());
+            draw_vao(vao, command.index_count);
+        }
+    }
+
+    fn submit_instanced(&mut self, mesh_id: u32, index_count: i32, material: &Material, transforms: &[glm::Mat4]) {
+        let vao = match self.vao(mesh_id) {
+            Some(vao) if !transforms.is_empty() => vao,
+            _ => return,
+        };
+        unsafe {
+            self.instanced_shader.activate();
+            gl::Uniform4fv(self.u_instanced_tint_loc, 1, material.tint.as_ptr());
+
+            // Orphan the previous contents rather than waiting for draws still using them
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.instance_vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                byte_size_of_array(transforms),
+                pointer_to_array(transforms),
+                gl::STREAM_DRAW
+            );
+
+            gl::BindVertexArray(vao);
+            gl::DrawElementsInstanced(gl::TRIANGLES, index_count, gl::UNSIGNED_INT, ptr::null(), transforms.len() as i32);
+        }
+    }
+
+    fn end_frame(&mut self) { }
+}
+
+// Picking on the GPU, by drawing every pickable node into an offscreen buffer in a color
+// encoding its index, and reading back the pixel under the cursor. Exact for whatever is actually
+// drawn, levels of detail included, at the cost of a round trip to the GPU. Draws with id.frag.
+pub struct IdBuffer {
+    shader       : Shader,
+    u_model_loc  : i32,
+    u_view_loc   : i32,
+    u_id_loc     : i32,
+    framebuffer  : u32,
+    color_buffer : u32,
+    depth_buffer : u32,
+}
+
+impl IdBuffer {
+    pub unsafe fn new(shader: Shader, width: u32, height: u32) -> Self {
+        let mut framebuffer: u32 = 0;
+        let mut renderbuffers = [0u32; 2];
+        gl::GenFramebuffers(1, &mut framebuffer);
+        gl::GenRenderbuffers(2, renderbuffers.as_mut_ptr());
+
+        let id_buffer = IdBuffer {
+            u_model_loc  : shader.get_uniform_location("model"),
+            u_view_loc   : shader.get_uniform_location("modelViewProj"),
+            u_id_loc     : shader.get_uniform_location("id"),
+            shader,
+            framebuffer,
+            color_buffer : renderbuffers[0],
+            depth_buffer : renderbuffers[1],
+        };
+        id_buffer.resize(width, height);
+        id_buffer
+    }
+
+    pub fn resize(&self, width: u32, height: u32) {
+        unsafe {
+            gl::BindRenderbuffer(gl::RENDERBUFFER, self.color_buffer);
+            gl::RenderbufferStorage(gl::RENDERBUFFER, gl::RGBA8, width as i32, height as i32);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, self.depth_buffer);
+            gl::RenderbufferStorage(gl::RENDERBUFFER, gl::DEPTH_COMPONENT24, width as i32, height as i32);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer);
+            gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::RENDERBUFFER, self.color_buffer);
+            gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, gl::RENDERBUFFER, self.depth_buffer);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+    }
+
+    // The index of the draw covering the given pixel, with the origin at the top left like window
+    // coordinates. Only that one pixel is drawn to, by way of the scissor test.
+    pub fn pick(&self, renderer: &GlRenderer, draws: &[DrawCommand], view_projection: &glm::Mat4, x: u32, y: u32) -> Option<usize> {
+        let mut viewport = [0i32; 4];
+        let mut pixel = [0u8; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+            if x as i32 >= viewport[2] || y as i32 >= viewport[3] {
+                return None;
+            }
+            let y = viewport[3] - 1 - y as i32; // OpenGL has its origin at the bottom left
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer);
+            apply_state(&RenderState { blend: BlendState::Opaque, cull: CullState::Disabled, ..RenderState::default() });
+            gl::Enable(gl::SCISSOR_TEST);
+            gl::Scissor(x as i32, y, 1, 1);
+            gl::ClearColor(0.0, 0.0, 0.0, 0.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+
+            self.shader.activate();
+            gl::UniformMatrix4fv(self.u_view_loc, 1, gl::FALSE, view_projection.as_ptr());
+            for (i, draw) in draws.iter().enumerate() {
+                if let Some(vao) = renderer.vao(draw.mesh_id) {
+                    // 0 is left for the background
+                    gl::Uniform1ui(self.u_id_loc, i as u32 + 1);
+                    gl::UniformMatrix4fv(self.u_model_loc, 1, gl::FALSE, draw.transform.as_ptr());
+                    draw_vao(vao, draw.index_count);
+                }
+            }
+
+            gl::ReadPixels(x as i32, y, 1, 1, gl::RGBA, gl::UNSIGNED_BYTE, pixel.as_mut_ptr() as *mut c_void);
+
+            // Leave everything the way the renderer expects it
+            gl::Disable(gl::SCISSOR_TEST);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            apply_state(&renderer.state);
+        }
+
+        let id = pixel[0] as usize | (pixel[1] as usize) << 8 | (pixel[2] as usize) << 16;
+        id.checked_sub(1)
+    }
+}
@@ -0,0 +1,325 @@
+use std::collections::{HashMap, HashSet};
+
+use glutin::event::{ElementState, MouseButton, VirtualKeyCode};
+
+// Maps keys, buttons, mouse movement and gamepad axes to named actions and axes, read from a
+// config file (see resources/input.cfg for the format). Raw input is fed into an InputState as it
+// arrives, and once per frame an InputFrame is taken from it, which tells which actions are held
+// and which were pressed or released since the last frame.
+
+// The bindings used when no config file can be read
+const DEFAULT_BINDINGS: &str = include_str!("../resources/input.cfg");
+
+// Gamepad axes closer to the center than this count as centered
+const GAMEPAD_DEADZONE: f32 = 0.15;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(VirtualKeyCode),
+    Scancode(u32),         // A physical key, the same on every keyboard layout
+    Mouse(MouseButton),
+    MouseX,
+    MouseY,
+    ScrollY,
+    GamepadAxis(u32),
+    GamepadButton(u32),
+}
+
+pub struct InputMap {
+    actions : HashMap<String, Vec<Binding>>,
+    axes    : HashMap<String, Vec<(Binding, f32)>>, // Along with their scale
+}
+
+impl InputMap {
+    pub fn load(path: &str) -> Result<InputMap, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        InputMap::parse(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn default_bindings() -> InputMap {
+        InputMap::parse(DEFAULT_BINDINGS).expect("The default input bindings are invalid")
+    }
+
+    pub fn parse(text: &str) -> Result<InputMap, String> {
+        let mut map = InputMap { actions: HashMap::new(), axes: HashMap::new() };
+
+        for (line_number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |message: &str| format!("line {}: {}", line_number + 1, message);
+
+            let (declaration, bindings) = line.split_once('=').ok_or_else(|| error("expected '='"))?;
+            let mut declaration = declaration.split_whitespace();
+            let (kind, name) = match (declaration.next(), declaration.next(), declaration.next()) {
+                (Some(kind), Some(name), None) => (kind, name.to_string()),
+                _ => return Err(error("expected 'action <name>' or 'axis <name>' before '='")),
+            };
+
+            let mut parsed = vec![];
+            for binding in bindings.split(',').map(str::trim).filter(|b| !b.is_empty()) {
+                let (binding, scale) = match binding.split_once('*') {
+                    Some((binding, scale)) => {
+                        let scale = scale.trim().parse::<f32>().map_err(|_| error(&format!("invalid scale '{}'", scale.trim())))?;
+                        (binding.trim(), scale)
+                    }
+                    None => (binding, 1.0),
+                };
+                let binding = parse_binding(binding).ok_or_else(|| error(&format!("unknown binding '{}'", binding)))?;
+                parsed.push((binding, scale));
+            }
+
+            match kind {
+                "action" => { map.actions.insert(name, parsed.into_iter().map(|(binding, _)| binding).collect()); }
+                "axis"   => { map.axes.insert(name, parsed); }
+                other    => return Err(error(&format!("unknown kind '{}', expected action or axis", other))),
+            }
+        }
+        Ok(map)
+    }
+}
+
+fn parse_binding(text: &str) -> Option<Binding> {
+    // Bindings with an argument, like Scancode(17)
+    if let Some((kind, argument)) = text.strip_suffix(')').and_then(|t| t.split_once('(')) {
+        let argument = argument.trim();
+        return match kind.trim() {
+            "Scancode"      => argument.parse().ok().map(Binding::Scancode),
+            "GamepadAxis"   => argument.parse().ok().map(Binding::GamepadAxis),
+            "GamepadButton" => argument.parse().ok().map(Binding::GamepadButton),
+            "Mouse" => match argument {
+                "Left"   => Some(Binding::Mouse(MouseButton::Left)),
+                "Right"  => Some(Binding::Mouse(MouseButton::Right)),
+                "Middle" => Some(Binding::Mouse(MouseButton::Middle)),
+                other    => other.parse().ok().map(|n| Binding::Mouse(MouseButton::Other(n))),
+            },
+            _ => None,
+        };
+    }
+    match text {
+        "MouseX"  => Some(Binding::MouseX),
+        "MouseY"  => Some(Binding::MouseY),
+        "ScrollY" => Some(Binding::ScrollY),
+        key       => parse_key(key).map(Binding::Key),
+    }
+}
+
+macro_rules! key_names {
+    ($name:expr, $($key:ident),* $(,)?) => {
+        match $name {
+            $(stringify!($key) => Some(VirtualKeyCode::$key),)*
+            _ => None,
+        }
+    };
+}
+
+fn parse_key(name: &str) -> Option<VirtualKeyCode> {
+    key_names!(name,
+        Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9, Key0,
+        A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+        Escape, F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+        Insert, Home, Delete, End, PageDown, PageUp,
+        Left, Up, Right, Down,
+        Back, Return, Space, Tab,
+        Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8, Numpad9,
+        NumpadAdd, NumpadSubtract, NumpadMultiply, NumpadDivide, NumpadDecimal, NumpadEnter,
+        LShift, RShift, LControl, RControl, LAlt, RAlt,
+        Apostrophe, Backslash, Comma, Equals, Grave, LBracket, RBracket, Minus, Period, Semicolon, Slash,
+    )
+}
+
+// What the actions and axes were during one frame
+#[derive(Default)]
+pub struct InputFrame {
+    held     : HashSet<String>,
+    pressed  : HashSet<String>,
+    released : HashSet<String>,
+    axes     : HashMap<String, f32>,
+}
+
+impl InputFrame {
+    pub fn held(&self, action: &str) -> bool {
+        self.held.contains(action)
+    }
+
+    // Also true when the action was both pressed and released within the same frame
+    pub fn pressed(&self, action: &str) -> bool {
+        self.pressed.contains(action)
+    }
+
+    #[allow(dead_code)]
+    pub fn released(&self, action: &str) -> bool {
+        self.released.contains(action)
+    }
+
+    pub fn axis(&self, name: &str) -> f32 {
+        self.axes.get(name).copied().unwrap_or(0.0)
+    }
+}
+
+pub struct InputState {
+    map              : InputMap,
+    held             : HashSet<Binding>,
+    pressed          : HashSet<Binding>, // Since the last frame
+    released         : HashSet<Binding>, // Since the last frame
+    motion           : HashMap<Binding, f32>, // Mouse movement and scrolling since the last frame
+    gamepad_axes     : HashMap<u32, f32>,
+    previously_held  : HashSet<String>, // Actions held during the last frame
+}
+
+impl InputState {
+    pub fn new(map: InputMap) -> Self {
+        InputState {
+            map,
+            held            : HashSet::new(),
+            pressed         : HashSet::new(),
+            released        : HashSet::new(),
+            motion          : HashMap::new(),
+            gamepad_axes    : HashMap::new(),
+            previously_held : HashSet::new(),
+        }
+    }
+
+    fn set_binding(&mut self, binding: Binding, state: ElementState) {
+        match state {
+            ElementState::Pressed => {
+                // Key repeat sends presses while held, which are not new presses
+                if self.held.insert(binding) {
+                    self.pressed.insert(binding);
+                }
+            }
+            ElementState::Released => {
+                if self.held.remove(&binding) {
+                    self.released.insert(binding);
+                }
+            }
+        }
+    }
+
+    pub fn handle_key(&mut self, keycode: Option<VirtualKeyCode>, scancode: u32, state: ElementState) {
+        if let Some(keycode) = keycode {
+            self.set_binding(Binding::Key(keycode), state);
+        }
+        self.set_binding(Binding::Scancode(scancode), state);
+    }
+
+    pub fn handle_mouse_button(&mut self, button: MouseButton, state: ElementState) {
+        self.set_binding(Binding::Mouse(button), state);
+    }
+
+    pub fn handle_mouse_motion(&mut self, dx: f32, dy: f32) {
+        *self.motion.entry(Binding::MouseX).or_insert(0.0) += dx;
+        *self.motion.entry(Binding::MouseY).or_insert(0.0) += dy;
+    }
+
+    pub fn handle_scroll(&mut self, dy: f32) {
+        *self.motion.entry(Binding::ScrollY).or_insert(0.0) += dy;
+    }
+
+    // Where an axis of the gamepad is, from -1 to 1
+    pub fn handle_gamepad_axis(&mut self, axis: u32, value: f32) {
+        let value = if value.abs() < GAMEPAD_DEADZONE { 0.0 } else { value.clamp(-1.0, 1.0) };
+        self.gamepad_axes.insert(axis, value);
+    }
+
+    pub fn handle_gamepad_button(&mut self, button: u32, state: ElementState) {
+        self.set_binding(Binding::GamepadButton(button), state);
+    }
+
+    // Release everything, for when the window loses focus and won't hear about releases
+    pub fn release_all(&mut self) {
+        let held: Vec<Binding> = self.held.iter().copied().collect();
+        for binding in held {
+            self.set_binding(binding, ElementState::Released);
+        }
+        self.gamepad_axes.clear();
+    }
+
+    fn binding_value(&self, binding: &Binding) -> f32 {
+        match binding {
+            Binding::MouseX | Binding::MouseY | Binding::ScrollY => self.motion.get(binding).copied().unwrap_or(0.0),
+            Binding::GamepadAxis(axis) => self.gamepad_axes.get(axis).copied().unwrap_or(0.0),
+            other => if self.held.contains(other) { 1.0 } else { 0.0 },
+        }
+    }
+
+    // Sum up everything that happened since the last call, and start over for the next frame
+    pub fn take_frame(&mut self) -> InputFrame {
+        let mut frame = InputFrame::default();
+
+        for (action, bindings) in &self.map.actions {
+            let held = bindings.iter().any(|b| self.held.contains(b));
+            let was_held = self.previously_held.contains(action);
+            if held {
+                frame.held.insert(action.clone());
+            }
+            if (held && !was_held) || bindings.iter().any(|b| self.pressed.contains(b)) {
+                frame.pressed.insert(action.clone());
+            }
+            if (!held && was_held) || bindings.iter().any(|b| self.released.contains(b)) {
+                frame.released.insert(action.clone());
+            }
+        }
+        for (axis, bindings) in &self.map.axes {
+            let value = bindings.iter().map(|(binding, scale)| self.binding_value(binding) * scale).sum();
+            frame.axes.insert(axis.clone(), value);
+        }
+
+        self.previously_held = frame.held.clone();
+        self.pressed.clear();
+        self.released.clear();
+        self.motion.clear();
+        frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_bindings_move_by_position_rather_than_by_name() {
+        let mut input = InputState::new(InputMap::default_bindings());
+        // Where W is on QWERTY, labeled Z on AZERTY
+        input.handle_key(Some(VirtualKeyCode::Z), 17, ElementState::Pressed);
+        input.handle_key(Some(VirtualKeyCode::Q), 30, ElementState::Pressed);
+        let frame = input.take_frame();
+        assert_eq!(frame.axis("move_forward"), 1.0);
+        assert_eq!(frame.axis("move_right"), -1.0);
+        assert_eq!(frame.axis("cyclic_forward"), 1.0);
+        assert!(!frame.pressed("quit"));
+
+        // Where Z is on QWERTY, labeled W on AZERTY
+        input.handle_key(Some(VirtualKeyCode::W), 44, ElementState::Pressed);
+        assert_eq!(input.take_frame().axis("pedals"), -1.0);
+    }
+
+    #[test]
+    fn gamepad_axes_move_named_axes_outside_of_the_deadzone() {
+        let mut input = InputState::new(InputMap::default_bindings());
+        input.handle_gamepad_axis(1, -0.5);
+        input.handle_gamepad_axis(3, 0.1);
+        let frame = input.take_frame();
+        assert_eq!(frame.axis("move_forward"), 0.5);
+        assert_eq!(frame.axis("cyclic_forward"), 0.5);
+        assert_eq!(frame.axis("look_right"), 0.0);
+
+        // Axes stay where they were until they move again, unlike the mouse
+        input.handle_gamepad_axis(3, 2.0);
+        assert_eq!(input.take_frame().axis("look_right"), 2.0);
+        assert_eq!(input.take_frame().axis("move_forward"), 0.5);
+
+        input.handle_gamepad_button(3, ElementState::Pressed);
+        assert!(input.take_frame().pressed("fly"));
+        input.release_all();
+        assert_eq!(input.take_frame().axis("move_forward"), 0.0);
+    }
+
+    #[test]
+    fn unknown_bindings_are_errors() {
+        assert!(InputMap::parse("axis move = Joystick(0)").err().unwrap().contains("unknown binding"));
+        assert!(InputMap::parse("axis move = GamepadAxis(left)").err().unwrap().contains("unknown binding"));
+        assert!(InputMap::parse("axis move = W * fast").err().unwrap().contains("invalid scale"));
+    }
+}
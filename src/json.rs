@@ -0,0 +1,434 @@
+use std::fmt::Write;
+
+// Just enough JSON for the files we read and write, without pulling in a crate for it. Objects
+// keep their fields in order, so that what gets written out reads the way it was put together.
+
+// How deep arrays and objects may nest, as parsing them recurses and must not run out of stack
+const MAX_DEPTH: usize = 128;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn object(fields: Vec<(&str, Json)>) -> Json {
+        Json::Object(fields.into_iter().map(|(name, value)| (name.to_string(), value)).collect())
+    }
+
+    pub fn parse(text: &str) -> Result<Json, String> {
+        let mut parser = Parser { text, offset: 0, depth: 0 };
+        parser.skip_whitespace();
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.offset < text.len() {
+            return Err(parser.error("Trailing characters after the value"));
+        }
+        Ok(value)
+    }
+
+    // A field of an object, None for anything else
+    pub fn get(&self, name: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(field, _)| field == name).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    // Two spaces of indentation, with arrays of nothing but numbers and such kept on one line
+    pub fn pretty(&self) -> String {
+        let mut out = String::new();
+        self.write(&mut out, 0);
+        out.push('\n');
+        out
+    }
+
+    fn is_scalar(&self) -> bool {
+        !matches!(self, Json::Array(_) | Json::Object(_))
+    }
+
+    fn write(&self, out: &mut String, depth: usize) {
+        let indent = |out: &mut String, depth: usize| out.push_str(&"  ".repeat(depth));
+        match self {
+            Json::Null => out.push_str("null"),
+            Json::Bool(value) => out.push_str(if *value { "true" } else { "false" }),
+            // JSON has no infinities or NaN
+            Json::Number(value) if !value.is_finite() => out.push_str("null"),
+            Json::Number(value) => write!(out, "{}", value).unwrap(),
+            Json::String(value) => write_string(out, value),
+            Json::Array(items) if items.is_empty() => out.push_str("[]"),
+            Json::Array(items) if items.iter().all(Json::is_scalar) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    item.write(out, depth);
+                }
+                out.push(']');
+            }
+            Json::Array(items) => {
+                out.push_str("[\n");
+                for (i, item) in items.iter().enumerate() {
+                    indent(out, depth + 1);
+                    item.write(out, depth + 1);
+                    out.push_str(if i + 1 < items.len() { ",\n" } else { "\n" });
+                }
+                indent(out, depth);
+                out.push(']');
+            }
+            Json::Object(fields) if fields.is_empty() => out.push_str("{}"),
+            Json::Object(fields) => {
+                out.push_str("{\n");
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    indent(out, depth + 1);
+                    write_string(out, name);
+                    out.push_str(": ");
+                    value.write(out, depth + 1);
+                    out.push_str(if i + 1 < fields.len() { ",\n" } else { "\n" });
+                }
+                indent(out, depth);
+                out.push('}');
+            }
+        }
+    }
+}
+
+fn write_string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+struct Parser<'a> {
+    text   : &'a str,
+    offset : usize, // In bytes
+    depth  : usize, // How many arrays and objects the parser is inside
+}
+
+impl<'a> Parser<'a> {
+    // Says where, as a line and a column counting from 1
+    fn error(&self, message: &str) -> String {
+        let before = &self.text[..self.offset];
+        let line = before.matches('\n').count() + 1;
+        let column = before.chars().rev().take_while(|&c| c != '\n').count() + 1;
+        format!("{} at line {}, column {}", message, line, column)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.offset..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.offset += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(' ' | '\t' | '\n' | '\r') = self.peek() {
+            self.offset += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.peek() {
+            Some(c) if c == expected => { self.offset += 1; Ok(()) }
+            _ => Err(self.error(&format!("Expected '{}'", expected))),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str, value: Json) -> Result<Json, String> {
+        if self.text[self.offset..].starts_with(keyword) {
+            self.offset += keyword.len();
+            Ok(value)
+        } else {
+            Err(self.error("Expected a value"))
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        match self.peek() {
+            Some('{' | '[') if self.depth == MAX_DEPTH => Err(self.error("Nested too deeply")),
+            Some('{') => self.nested(Self::object),
+            Some('[') => self.nested(Self::array),
+            Some('"') => Ok(Json::String(self.string()?)),
+            Some('t') => self.keyword("true", Json::Bool(true)),
+            Some('f') => self.keyword("false", Json::Bool(false)),
+            Some('n') => self.keyword("null", Json::Null),
+            Some('-' | '0'..='9') => self.number(),
+            Some(_) => Err(self.error("Expected a value")),
+            None => Err(self.error("Unexpected end of the text")),
+        }
+    }
+
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<Json, String>) -> Result<Json, String> {
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+        self.expect('{')?;
+        let mut fields = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.offset += 1;
+            return Ok(Json::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err(self.error("Expected the name of a field"));
+            }
+            let name = self.string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            self.skip_whitespace();
+            fields.push((name, self.value()?));
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => { }
+                Some('}') => return Ok(Json::Object(fields)),
+                _ => return Err(self.error("Expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        self.expect('[')?;
+        let mut items = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.offset += 1;
+            return Ok(Json::Array(items));
+        }
+        loop {
+            self.skip_whitespace();
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => { }
+                Some(']') => return Ok(Json::Array(items)),
+                _ => return Err(self.error("Expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut value = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(value),
+                Some('\\') => {
+                    let escaped = match self.next() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => self.unicode_escape()?,
+                        _ => return Err(self.error("Invalid escape")),
+                    };
+                    value.push(escaped);
+                }
+                Some(c) if (c as u32) < 0x20 => return Err(self.error("Control character in a string")),
+                Some(c) => value.push(c),
+                None => return Err(self.error("Unterminated string")),
+            }
+        }
+    }
+
+    // After the \u, which may be the first half of a surrogate pair
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let high = self.hex4()?;
+        let code = if (0xd800..0xdc00).contains(&high) {
+            if !self.text[self.offset..].starts_with("\\u") {
+                return Err(self.error("Unpaired surrogate"));
+            }
+            self.offset += 2;
+            let low = self.hex4()?;
+            if !(0xdc00..0xe000).contains(&low) {
+                return Err(self.error("Unpaired surrogate"));
+            }
+            0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or_else(|| self.error("Invalid unicode escape"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let digits = self.text.get(self.offset..self.offset + 4).ok_or_else(|| self.error("Invalid unicode escape"))?;
+        let code = u32::from_str_radix(digits, 16).map_err(|_| self.error("Invalid unicode escape"))?;
+        self.offset += 4;
+        Ok(code)
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.offset;
+        while let Some('-' | '+' | '.' | 'e' | 'E' | '0'..='9') = self.peek() {
+            self.offset += 1;
+        }
+        let text = &self.text[start..self.offset];
+        // Rust accepts a few things JSON doesn't, like "1." and leading zeros, which does no harm
+        text.parse().map(Json::Number).map_err(|_| {
+            self.offset = start;
+            self.error(&format!("Invalid number {}", text))
+        })
+    }
+}
+
+impl From<bool> for Json {
+    fn from(value: bool) -> Json {
+        Json::Bool(value)
+    }
+}
+
+impl From<f32> for Json {
+    // Through the shortest text that reads back as the same f32, so that 0.1 is written as 0.1
+    // and not as 0.10000000149011612
+    fn from(value: f32) -> Json {
+        Json::Number(value.to_string().parse().unwrap_or(value as f64))
+    }
+}
+
+impl From<f64> for Json {
+    fn from(value: f64) -> Json {
+        Json::Number(value)
+    }
+}
+
+impl From<u32> for Json {
+    fn from(value: u32) -> Json {
+        Json::Number(value as f64)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Json {
+        Json::Number(value as f64)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(value: &str) -> Json {
+        Json::String(value.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(value: String) -> Json {
+        Json::String(value)
+    }
+}
+
+impl<T: Into<Json>> From<Vec<T>> for Json {
+    fn from(values: Vec<T>) -> Json {
+        Json::Array(values.into_iter().map(Into::into).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pretty_text_parses_back_the_same() {
+        let value = Json::object(vec![
+            ("name", "helicopter".into()),
+            ("visible", true.into()),
+            ("nothing", Json::Null),
+            ("position", vec![1.5f32, -2.0, 0.1].into()),
+            ("empty", Json::Array(vec![])),
+            ("children", Json::Array(vec![Json::object(vec![("name", "rotor".into())]), Json::object(vec![])])),
+        ]);
+        let text = value.pretty();
+        assert!(text.contains("\"position\": [1.5, -2, 0.1]"), "{}", text);
+        assert_eq!(Json::parse(&text), Ok(value));
+    }
+
+    #[test]
+    fn strings_are_escaped_and_unescaped() {
+        let odd = "quote \" backslash \\ newline \n tab \t bell \u{7} ø 🚁";
+        let text = Json::from(odd).pretty();
+        assert_eq!(text, "\"quote \\\" backslash \\\\ newline \\n tab \\t bell \\u0007 ø 🚁\"\n");
+        assert_eq!(Json::parse(&text), Ok(Json::from(odd)));
+        assert_eq!(Json::parse(r#""\/\b\f\u00e9\ud83d\ude81""#), Ok(Json::from("/\u{8}\u{c}é🚁")));
+    }
+
+    #[test]
+    fn numbers() {
+        for (text, number) in [("0", 0.0), ("-12", -12.0), ("3.25", 3.25), ("1e3", 1000.0), ("-2.5E-2", -0.025)] {
+            assert_eq!(Json::parse(text), Ok(Json::Number(number)), "{}", text);
+        }
+        assert_eq!(Json::from(0.1f32), Json::Number(0.1));
+        assert_eq!(Json::Number(f64::NAN).pretty(), "null\n");
+    }
+
+    #[test]
+    fn errors_say_where() {
+        assert_eq!(Json::parse("{\n  \"a\": tru\n}"), Err("Expected a value at line 2, column 8".to_string()));
+        assert_eq!(Json::parse("[1, 2"), Err("Expected ',' or ']' at line 1, column 6".to_string()));
+        assert_eq!(Json::parse("1 2"), Err("Trailing characters after the value at line 1, column 3".to_string()));
+        for text in ["", "{1: 2}", "\"open", "\"\\x\"", "\"\\ud83d\"", "-", "[1,]", "{\"a\" 1}"] {
+            assert!(Json::parse(text).is_err(), "{:?} parsed", text);
+        }
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error_rather_than_a_crash() {
+        let nested = |depth: usize| "[".repeat(depth) + &"]".repeat(depth);
+        assert!(Json::parse(&nested(MAX_DEPTH)).is_ok());
+        assert!(Json::parse(&nested(MAX_DEPTH + 1)).unwrap_err().starts_with("Nested too deeply"));
+        assert!(Json::parse(&"{\"a\":".repeat(100_000)).unwrap_err().starts_with("Nested too deeply"));
+    }
+}
@@ -8,220 +8,238 @@
 #![allow(unused_variables)]
 */
 extern crate nalgebra_glm as glm;
-use std::{ mem, ptr, os::raw::c_void };
+use std::path::{Path, PathBuf};
+use std::ptr;
 use std::thread;
-use std::sync::{Mutex, Arc, RwLock};
+use std::sync::mpsc;
+use std::time::Duration;
 
 mod shader;
 mod util;
 mod mesh;
+mod mesh_cache;
+mod mesh_tools;
+mod primitives;
+mod json;
+mod export;
 mod scene_graph;
 use scene_graph::SceneNode;
 mod toolbox;
-
-
-use std::ffi::CString;
-
-use glutin::event::{Event, WindowEvent, DeviceEvent, KeyboardInput, ElementState::{Pressed, Released}, VirtualKeyCode::{self, *}};
+mod bounds;
+mod simplify;
+mod camera;
+mod rasterizer;
+mod renderer;
+mod gl_renderer;
+mod input;
+mod events;
+mod picking;
+mod terrain_generator;
+mod chunked_terrain;
+mod assets;
+mod scene_file;
+mod cli;
+mod game_loop;
+mod components;
+mod physics;
+mod flight;
+mod gamepad;
+
+use glutin::event::{Event, ElementState};
 use glutin::event_loop::ControlFlow;
-
-use crate::mesh::{Helicopter, Mesh};
-use crate::toolbox::Heading;
-
-// initial window size
-const INITIAL_SCREEN_W: u32 = 800;
-const INITIAL_SCREEN_H: u32 = 600;
-
-// Get the size of an arbitrary array of numbers measured in bytes
-// Example usage:  byte_size_of_array(my_array)
-fn byte_size_of_array<T>(val: &[T]) -> isize {
-    std::mem::size_of_val(&val[..]) as isize
+use glutin::window::{CursorGrabMode, Fullscreen};
+
+use crate::assets::{AssetManager, Handle};
+use crate::camera::{Camera, CameraController, CameraInput};
+use crate::cli::Options;
+use crate::game_loop::{GameClock, Interpolation};
+use crate::flight::Controls;
+use crate::events::{RenderRequest, WindowMessage};
+use crate::input::{InputMap, InputState};
+use crate::picking::Ray;
+use crate::scene_file::{LoadedScene, MeshRequest, SceneDescription, Surface};
+use crate::shader::ShaderSource;
+use crate::renderer::{DrawCommand, RecordingRenderer, RenderQueue, Renderer};
+
+const NIGHT_SKY: [f32; 4] = [0.035, 0.046, 0.078, 1.0];
+
+// Relative to one of the asset roots: the working directory, or failing that the crate
+const INPUT_BINDINGS_PATH: &str = "resources/input.cfg";
+
+// The scene to show when none is given on the command line
+const DEFAULT_SCENE: &str = include_str!("../resources/scenes/lunar.json");
+
+const SCENE_EXPORT_PATH: &str = "scene.gltf";
+const OBJECT_EXPORT_PATH: &str = "object.obj";
+const SCENE_SAVE_PATH: &str = "saved_scene.json";
+
+// How long a tick of the simulation is, 60 of them per second
+const SIMULATION_TIMESTEP: Duration = Duration::from_nanos(1_000_000_000 / 60);
+// How fast the simulation runs in slow motion
+const SLOW_MOTION_SCALE: f32 = 0.25;
+
+// How much time passes between frames when rendering headless, one tick each
+const HEADLESS_FRAME_TIME: Duration = SIMULATION_TIMESTEP;
+
+// What clicking on something selects, the object it's a part of
+const SELECTABLE_TAG: &str = "helicopter";
+
+// Doors move at command times their own speed, but only those within the selected object, if
+// there is one
+fn command_doors(scene: &mut LoadedScene, command: f32, selected: Option<*mut SceneNode>) {
+    for (node, door) in scene.world.doors.iter_mut() {
+        let within = selected.is_none_or(|object| unsafe { &*object }.contains(node));
+        door.command = if within { command } else { 0.0 };
+    }
 }
 
-// Get the OpenGL-compatible pointer to an arbitrary array of numbers
-// Example usage:  pointer_to_array(my_array)
-fn pointer_to_array<T>(val: &[T]) -> *const c_void {
-    &val[0] as *const T as *const c_void
+// Only the helicopter being flown answers to the flight controls, the others are left hovering
+// with their hands off. The throttle stays where it was.
+fn command_aircraft(scene: &mut LoadedScene, controls: &Controls, flown: Option<*mut SceneNode>) {
+    for (node, aircraft) in scene.world.aircraft.iter_mut() {
+        let held = if flown.is_some_and(|flown| std::ptr::eq(flown, node)) { *controls } else { Controls::default() };
+        aircraft.controls = Controls { throttle: aircraft.controls.throttle, ..held };
+    }
 }
 
-// Get the size of the given type in bytes
-// Example usage:  size_of::<u64>()
-fn size_of<T>() -> i32 {
-    mem::size_of::<T>() as i32
+// Which selectable object a node is a part of
+fn object_of(scene: &LoadedScene, node: *const SceneNode) -> Option<*mut SceneNode> {
+    scene.tagged(SELECTABLE_TAG).into_iter().find(|&object| unsafe { &*object }.contains(node))
 }
 
-// Get an offset in bytes for n units of type T, represented as a relative pointer
-// Example usage:  offset::<u64>(4)
-fn offset<T>(n: u32) -> *const c_void {
-    (n * mem::size_of::<T>() as u32) as *const T as *const c_void
+// Waits for the sources to load, on the render thread as that's where the context is
+unsafe fn link_shader(sources: &[&Handle<ShaderSource>]) -> shader::Shader {
+    sources.iter().fold(shader::ShaderBuilder::new(), |builder, source| {
+        let source = source.wait().unwrap_or_else(|e| panic!("{}", e));
+        builder.attach_source(&source)
+    }).link()
 }
 
-// Helper functions to simplify handling of vao's
-unsafe fn create_vao_from_mesh(mesh: &mesh::Mesh) -> (u32, i32){
-    let vao = create_vao(&mesh.vertices, &mesh.colors, &mesh.indices, &mesh.normals);
-    (vao, mesh.index_count)
-}
+fn render_frame(renderer: &mut dyn Renderer, queue: &mut RenderQueue, scene: &SceneNode, view_projection_matrix: &glm::Mat4) {
+    queue.begin(view_projection_matrix);
+    queue.push_scene(scene, &glm::identity());
 
-unsafe fn draw_vao(vao: u32, index_count: i32){
-    gl::BindVertexArray(vao);
-    gl::DrawElements(gl::TRIANGLES, index_count, gl::UNSIGNED_INT, std::ptr::null())
+    renderer.begin_frame(&glm::make_vec4(&NIGHT_SKY), view_projection_matrix);
+    queue.flush(renderer);
+    renderer.end_frame();
 }
 
-fn apply_heading(helicopter_body: &mut SceneNode, time: f32) {
-    let heading: Heading = toolbox::simple_heading_animation(time);
-    helicopter_body.position.x = heading.x;
-    helicopter_body.position.z = heading.z;
+fn save_image(image: &image::RgbaImage, path: &str) {
+    match image.save(path) {
+        Ok(()) => println!("Frame saved to {}.", path),
+        Err(e) => println!("Failed to save the frame to {}: {}", path, e),
+    }
+}
 
-    helicopter_body.rotation.z = heading.roll;
-    helicopter_body.rotation.x = heading.pitch;
-    helicopter_body.rotation.y = heading.yaw;
+// Render with the software renderer only, without a window or an OpenGL context, from the first
+// camera of the scene. Every frame is one tick of the simulation, and the terrain is generated
+// before it's needed rather than streamed in, so that the frames come out the same every run.
+fn run_headless(options: &Options, description: SceneDescription, meshes: &[MeshRequest]) {
+    let mut software_renderer = rasterizer::Rasterizer::new(options.width, options.height);
+    // Stands in for the GL renderer, so that the mesh ids are handed out as usual
+    let mut recorder = RecordingRenderer::default();
+    let mut scene = LoadedScene::build(description, meshes, &mut software_renderer, &mut recorder)
+        .unwrap_or_else(|e| panic!("{}", e));
+    software_renderer.set_lighting(&scene.description.lighting);
+    let (mut camera, mut camera_controller) = scene.description.cameras[0]
+        .instantiate(&scene, options.width as f32 / options.height as f32)
+        .unwrap_or_else(|e| panic!("{}", e));
+    let mut render_queue = RenderQueue::default();
+    let mut clock = GameClock::new(SIMULATION_TIMESTEP);
+
+    let frames = options.frames.unwrap_or(1);
+    let before = std::time::Instant::now();
+    for frame in 0..frames {
+        if let (Surface::Streamed(chunks), Some(ground)) = (&mut scene.surface, scene.ground) {
+            chunks.update_blocking(&camera.position, unsafe { &mut *ground }, &mut [&mut software_renderer, &mut recorder]);
+        }
+        // The first frame shows the scene as it starts out, on the ground generated for it
+        if frame == 0 {
+            scene.world.start(clock.time(), clock.timestep.as_secs_f32(), scene.surface.ground());
+        } else {
+            clock.advance(HEADLESS_FRAME_TIME);
+        }
+        while let Some(time) = clock.next_tick() {
+            scene.world.update(time, clock.timestep.as_secs_f32(), scene.surface.ground());
+        }
+        scene.root.update_world(&glm::identity());
+        camera_controller.update(&mut camera, &CameraInput::default(), HEADLESS_FRAME_TIME.as_secs_f32());
+        let view_projection_matrix = camera.view_projection_matrix();
+        scene.root.update_lods(&view_projection_matrix, &glm::identity());
+
+        render_frame(&mut software_renderer, &mut render_queue, &scene.root, &view_projection_matrix);
+        if let Some(path) = options.capture_path(frame, frame + 1 == frames) {
+            save_image(&software_renderer.to_image(), &path);
+        }
+    }
+    let seconds = before.elapsed().as_secs_f32();
+    println!("Rendered {} frames in {:.3}s, {:.3}ms per frame.", frames, seconds, seconds * 1e3 / frames as f32);
 }
 
-unsafe fn draw_scene(
-    node: &scene_graph::SceneNode,
-    view_projection_matrix: &glm::Mat4,
-    transformation_so_far: &glm::Mat4,
-    u_model_loc: i32,
-    u_view_loc: i32,
-    ) {
-    let t = glm::translation(&node.position);
-
-    let mut r: glm::Mat4 = glm::identity();
-    r = glm::rotate_x(&r, node.rotation.x);
-    r = glm::rotate_y(&r, node.rotation.y);
-    r = glm::rotate_z(&r, node.rotation.z);
-
-    let to_pivot     = glm::translation(&node.reference_point);
-    let from_pivot   = glm::translation(&(-node.reference_point));
-    let local = t * to_pivot * r * from_pivot;
-    let model = transformation_so_far * local;
-
-    gl::UniformMatrix4fv(u_model_loc, 1, gl::FALSE, model.as_ptr());
-    gl::UniformMatrix4fv(u_view_loc, 1, gl::FALSE, view_projection_matrix.as_ptr());
-
-    if node.index_count > 0 {
-        draw_vao(node.vao_id, node.index_count);
+fn main() {
+    let options = Options::parse(std::env::args().skip(1)).unwrap_or_else(|e| {
+        println!("{}\n\n{}", e, cli::USAGE);
+        std::process::exit(2);
+    });
+    if options.help {
+        print!("{}", cli::USAGE);
+        return;
     }
-    for &child in &node.children {
-        draw_scene(
-            &*child, 
-            view_projection_matrix, 
-            &model, 
-            u_model_loc, 
-            u_view_loc
-        );
+
+    let mut assets = AssetManager::new(vec![
+        PathBuf::from("."),
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")),
+    ]);
+    let scene_description = match &options.scene {
+        Some(path) => assets.resolve(path).and_then(|path| SceneDescription::load(&path)),
+        None => SceneDescription::parse(DEFAULT_SCENE).map_err(|e| format!("The default scene is invalid: {}", e)),
+    }.unwrap_or_else(|e| {
+        println!("{}", e);
+        std::process::exit(1);
+    });
+
+    // Start loading right away, the window and the OpenGL context take a while anyway
+    let scene_meshes = scene_description.request_meshes(&mut assets);
+    if options.headless {
+        run_headless(&options, scene_description, &scene_meshes);
+        return;
     }
-}
 
-unsafe fn create_vao(vertices: &Vec<f32>, vertices_color: &Vec<f32>, indices: &Vec<u32>, normals: &Vec<f32>) -> u32 {
-    let mut vao: u32 = 0;
-    gl::GenVertexArrays(1, &mut vao);  
-    gl::BindVertexArray(vao);
-
-    let mut vbo_pos: u32 = 0;
-    gl::GenBuffers(1, &mut vbo_pos);
-    gl::BindBuffer(gl::ARRAY_BUFFER, vbo_pos);
-
-    gl::BufferData(
-        gl::ARRAY_BUFFER, 
-        byte_size_of_array(vertices), 
-        pointer_to_array(vertices),
-        gl::STATIC_DRAW
-    );
-
-    let pos_stride = 3 * size_of::<f32>();
-
-    gl::EnableVertexAttribArray(0);
-    gl::VertexAttribPointer(
-        0,
-        3,
-        gl::FLOAT,
-        gl::FALSE,
-        pos_stride,
-        ptr::null() 
-    );
-    
-    let mut vbo_colors: u32 = 0;
-    gl::GenBuffers(1, &mut vbo_colors);
-    gl::BindBuffer(gl::ARRAY_BUFFER, vbo_colors);
-    
-    gl::BufferData(
-        gl::ARRAY_BUFFER,
-        byte_size_of_array(vertices_color), 
-        pointer_to_array(vertices_color),
-        gl::STATIC_DRAW
-    );
-    
-    let color_stride = 4 * size_of::<f32>();
-    gl::EnableVertexAttribArray(1);
-    gl::VertexAttribPointer(
-        1, 
-        4,
-        gl::FLOAT,
-        gl::FALSE,
-        color_stride,
-        ptr::null() 
-    );
-
-    let mut vbo_normals: u32 = 0;
-    gl::GenBuffers(1, &mut vbo_normals);
-    gl::BindBuffer(gl::ARRAY_BUFFER, vbo_normals);
-    
-    gl::BufferData(
-        gl::ARRAY_BUFFER,
-        byte_size_of_array(normals), 
-        pointer_to_array(normals),
-        gl::STATIC_DRAW
-    );
-    
-    let normals_stride = 3 * size_of::<f32>();
-    gl::EnableVertexAttribArray(2);
-    gl::VertexAttribPointer(
-        2, 
-        3,
-        gl::FLOAT,
-        gl::FALSE,
-        normals_stride,
-        ptr::null() 
-    );
-
-    let mut ebo: u32 = 0;
-    gl::GenBuffers(1, &mut ebo);
-    gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo);
-    gl::BufferData(
-        gl::ELEMENT_ARRAY_BUFFER,
-        byte_size_of_array(indices),
-        pointer_to_array(indices),
-        gl::STATIC_DRAW,
-    );
-    
-    vao
-}
+    let shader_path = |name: &str| Path::new(&options.shader_dir).join(name).to_string_lossy().into_owned();
+    let simple_frag = assets.shader(&shader_path("simple.frag"));
+    let simple_vert = assets.shader(&shader_path("simple.vert"));
+    let instanced_vert = assets.shader(&shader_path("instanced.vert"));
+    let id_frag = assets.shader(&shader_path("id.frag"));
 
-fn main() {
     // Set up the necessary objects to deal with windows and event handling
-    let el = glutin::event_loop::EventLoop::new();
+    let el = glutin::event_loop::EventLoopBuilder::<RenderRequest>::with_user_event().build();
     let wb = glutin::window::WindowBuilder::new()
         .with_title("Gloom-rs")
         .with_resizable(true)
-        .with_inner_size(glutin::dpi::LogicalSize::new(INITIAL_SCREEN_W, INITIAL_SCREEN_H));
+        .with_inner_size(glutin::dpi::LogicalSize::new(options.width, options.height))
+        .with_fullscreen(if options.fullscreen { Some(Fullscreen::Borderless(None)) } else { None });
     let cb = glutin::ContextBuilder::new()
-        .with_vsync(true);
+        .with_vsync(options.vsync)
+        .with_multisampling(options.msaa_samples);
     let windowed_context = cb.build_windowed(wb, &el).unwrap();
-    // Set up a shared vector for keeping track of currently pressed keys
-    let arc_pressed_keys = Arc::new(Mutex::new(Vec::<VirtualKeyCode>::with_capacity(10)));
-    // Make a reference of this vector to send to the render thread
-    let pressed_keys = Arc::clone(&arc_pressed_keys);
+    // The window stays with the event loop, so the render thread can ask it to grab the cursor.
+    // The render thread drops the context before it asks the event loop to exit, and with it the window.
+    let (raw_context, window) = unsafe { windowed_context.split() };
 
-    // Set up shared tuple for tracking mouse movement between frames
-    let arc_mouse_delta = Arc::new(Mutex::new((0f32, 0f32)));
-    // Make a reference of this tuple to send to the render thread
-    let mouse_delta = Arc::clone(&arc_mouse_delta);
+    let input_map = assets.resolve(INPUT_BINDINGS_PATH).and_then(|path| InputMap::load(&path.to_string_lossy())).unwrap_or_else(|e| {
+        println!("{}, using the default input bindings", e);
+        InputMap::default_bindings()
+    });
+
+    // Window events go to the render thread through this channel, and requests come back through the proxy
+    let (message_sender, messages) = mpsc::channel::<WindowMessage>();
+    let requests = el.create_proxy();
+    let watchdog_requests = el.create_proxy();
 
-    // Set up shared tuple for tracking changes to the window size
-    let arc_window_size = Arc::new(Mutex::new((INITIAL_SCREEN_W, INITIAL_SCREEN_H, false)));
-    // Make a reference of this tuple to send to the render thread
-    let window_size = Arc::clone(&arc_window_size);
+    // Gamepads aren't window events, so they are read on a thread of their own and sent on along with them
+    if let Err(e) = gamepad::read_gamepad(message_sender.clone()) {
+        println!("{}, playing without a gamepad", e);
+    }
 
     // Spawn a separate thread for rendering, so event handling doesn't block rendering
     let render_thread = thread::spawn(move || {
@@ -229,21 +247,20 @@ fn main() {
         // This has to be done inside of the rendering thread, because
         // an active OpenGL context cannot safely traverse a thread boundary
         let context = unsafe {
-            let c = windowed_context.make_current().unwrap();
+            let c = raw_context.make_current().unwrap();
             gl::load_with(|symbol| c.get_proc_address(symbol) as *const _);
             c
         };
 
-        let mut window_aspect_ratio = INITIAL_SCREEN_W as f32 / INITIAL_SCREEN_H as f32;
+        let window_aspect_ratio = options.width as f32 / options.height as f32;
 
         // Set up openGL
         unsafe {
-            gl::Enable(gl::DEPTH_TEST);
-            gl::DepthFunc(gl::LESS);
-            gl::Enable(gl::CULL_FACE);
-            gl::Disable(gl::MULTISAMPLE);
-            gl::Enable(gl::BLEND);
-            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            if options.msaa_samples > 0 {
+                gl::Enable(gl::MULTISAMPLE);
+            } else {
+                gl::Disable(gl::MULTISAMPLE);
+            }
             gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
             gl::DebugMessageCallback(Some(util::debug_callback), ptr::null());
 
@@ -253,189 +270,289 @@ fn main() {
             println!("GLSL\t: {}", util::get_gl_string(gl::SHADING_LANGUAGE_VERSION));
         }
 
-        let lunarsurface: Mesh = mesh::Terrain::load("./resources/lunarsurface.obj");
-        let helicopter: Helicopter = mesh::Helicopter::load("./resources/helicopter.obj");
-        let door: Mesh = helicopter.door;
-        let body: Mesh = helicopter.body;
-        let main_rotor: Mesh = helicopter.main_rotor;
-        let tail_rotor: Mesh = helicopter.tail_rotor;
-
-        let (lunarsurface_vao, lunarsurface_indexcount) = unsafe{create_vao_from_mesh(&lunarsurface)};
-        let (body_vao, body_indexcount) = unsafe{create_vao_from_mesh(&body)};
-        let (door_vao, door_indexcount) = unsafe{create_vao_from_mesh(&door)};
-        let (main_rotor_vao, main_rotor_indexcount) = unsafe{create_vao_from_mesh(&main_rotor)};
-        let (tail_rotor_vao, tail_rotor_indexcount) = unsafe{create_vao_from_mesh(&tail_rotor)};
-
-        let mut scene = SceneNode::new();
-        let lunarsurface_scene = SceneNode::from_vao(lunarsurface_vao, lunarsurface_indexcount);
-        scene.add_child(&lunarsurface_scene);
-
-        // We iterate 5 times and create helicopters. The helicopters are stored as children
-        let mut i = 0;
-        while i < 5 {
-            let mut body_scene = SceneNode::from_vao(body_vao, body_indexcount);
-            let door_scene = SceneNode::from_vao(door_vao, door_indexcount);
-            let main_rotor_scene = SceneNode::from_vao(main_rotor_vao, main_rotor_indexcount);
-            let mut tail_rotor_scene = SceneNode::from_vao(tail_rotor_vao, tail_rotor_indexcount);
-    
-            // Set the tail reference point. This prevents the tail rotor from spinning around the body chassis of the helicopter
-            tail_rotor_scene.reference_point = nalgebra_glm::Vec3::new(0.35, 2.3, 10.4);
-    
-            body_scene.add_child(&door_scene);
-            body_scene.add_child(&main_rotor_scene);
-            body_scene.add_child(&tail_rotor_scene);
-            
-            scene.add_child(&body_scene);
-
-            i = i + 1;
-        } // After the loop, we essentially have 5 helicopters in the exact same spot
-
-        scene.print();
-
-        let simple_shader = unsafe {
-            shader::ShaderBuilder::new()
-                .attach_file("./shaders/simple.frag")
-                .attach_file("./shaders/simple.vert")
-                .link()
-        };
-        
-        let u_view_loc = unsafe {
-            let name = CString::new("modelViewProj").unwrap();
-            gl::GetUniformLocation(simple_shader.program_id, name.as_ptr())
-        };
-
-        let u_model_loc = unsafe {
-            let name = CString::new("model").unwrap();
-            gl::GetUniformLocation(simple_shader.program_id, name.as_ptr())
-        };
+        let simple_shader = unsafe { link_shader(&[&simple_frag, &simple_vert]) };
+        let instanced_shader = unsafe { link_shader(&[&simple_frag, &instanced_vert]) };
+        let id_shader = unsafe { link_shader(&[&id_frag, &simple_vert]) };
+
+        // The GL renderer draws to the window, while the software renderer is kept around to
+        // render reference images of the same scene on the CPU
+        let mut renderer = unsafe { gl_renderer::GlRenderer::new(simple_shader, instanced_shader) };
+        let mut software_renderer = rasterizer::Rasterizer::new(options.width, options.height);
+        let mut render_queue = RenderQueue::default();
+        let id_buffer = unsafe { gl_renderer::IdBuffer::new(id_shader, options.width, options.height) };
+
+        let mut scene = LoadedScene::build(scene_description, &scene_meshes, &mut renderer, &mut software_renderer)
+            .unwrap_or_else(|e| panic!("{}", e));
+        drop(scene_meshes);
+        renderer.set_lighting(&scene.description.lighting);
+        software_renderer.set_lighting(&scene.description.lighting);
+        scene.root.print();
+
+        // Put everything where it is at the start, before the first frame shows it
+        let mut clock = GameClock::new(SIMULATION_TIMESTEP);
+        scene.world.start(clock.time(), clock.timestep.as_secs_f32(), scene.surface.ground());
+        let mut interpolation = Interpolation::new(scene.world.entities());
+        let mut previous_frame_time = std::time::Instant::now();
+
+        // Cycled through with C, every one of them keeping where it was in the meantime
+        let mut cameras: Vec<(Camera, Box<dyn CameraController>)> = scene.description.cameras.iter()
+            .map(|camera| camera.instantiate(&scene, window_aspect_ratio))
+            .collect::<Result<_, _>>()
+            .unwrap_or_else(|e| panic!("{}", e));
+        let mut active_camera = 0;
+
+        let mut input = InputState::new(input_map);
+        let mut cursor_grabbed = false;
+        let mut window_size = (options.width, options.height);
+        let mut cursor_position = (0.0, 0.0);
+        let mut use_gpu_picking = false;
+        let mut selected_object: Option<*mut SceneNode> = None;
+        let mut flown_object: Option<*mut SceneNode> = None;
+        let mut frame: u64 = 0;
 
-        // Variables for the camera projection
-        let first_frame_time = std::time::Instant::now();
-        let mut previous_frame_time = first_frame_time;
-        let mut x = 50.0;
-        let mut y = -3.0;
-        let mut z = 50.0;
-        let mut theta_x: f32 = 0.2;
-        let mut theta_y: f32 = 2.3;
-
-        let mut helicopter_doors_slider_value = 0.0;
-        
         // The main rendering loop
         loop {
-            // Compute time passed since the previous frame and since the start of the program
+            // Compute time passed since the previous frame. The camera goes by it directly, the
+            // simulation in ticks of its own.
             let now = std::time::Instant::now();
-            let elapsed = now.duration_since(first_frame_time).as_secs_f32();
-            let delta_time = now.duration_since(previous_frame_time).as_secs_f32();
+            let frame_time = now.duration_since(previous_frame_time);
+            let delta_time = frame_time.as_secs_f32();
             previous_frame_time = now;
 
-            let helicopter_count = scene.children.len();
-            for j in 1..helicopter_count {
-                let helicopter = scene.get_child(j);
-
-                // Controll doors
-                helicopter.get_child(0).position.z = helicopter_doors_slider_value;
-
-                // Spin rotors
-                helicopter.get_child(1).rotation.y = elapsed * 10.0;
-                helicopter.get_child(2).rotation.x = elapsed * 10.0;
+            // Handle the window events which happened since the previous frame, in order
+            let mut close_requested = false;
+            for message in messages.try_iter() {
+                match message {
+                    // Minimising the window makes it 0x0, which there is nothing to draw into
+                    WindowMessage::Resized { width: 0, .. } | WindowMessage::Resized { height: 0, .. } => {}
+                    WindowMessage::Resized { width, height } => {
+                        context.resize(glutin::dpi::PhysicalSize::new(width, height));
+                        for (camera, _) in cameras.iter_mut() {
+                            camera.aspect_ratio = width as f32 / height as f32;
+                        }
+                        println!("Window was resized to {}x{}", width, height);
+                        renderer.resize(width, height);
+                        software_renderer.resize(width, height);
+                        id_buffer.resize(width, height);
+                        window_size = (width, height);
+                    }
+                    WindowMessage::KeyDown { keycode, scancode }   => input.handle_key(keycode, scancode, ElementState::Pressed),
+                    WindowMessage::KeyUp { keycode, scancode }     => input.handle_key(keycode, scancode, ElementState::Released),
+                    WindowMessage::MouseButton { button, state }   => input.handle_mouse_button(button, state),
+                    WindowMessage::MouseMotion { dx, dy }          => input.handle_mouse_motion(dx, dy),
+                    WindowMessage::CursorMoved { x, y }            => { cursor_position = (x, y); }
+                    WindowMessage::Scroll { lines }                => input.handle_scroll(lines),
+                    WindowMessage::GamepadAxis { axis, value }     => input.handle_gamepad_axis(axis, value),
+                    WindowMessage::GamepadButton { button, state } => input.handle_gamepad_button(button, state),
+                    // Releases happening while the window is in the background are never reported
+                    WindowMessage::Focused(false) => input.release_all(),
+                    WindowMessage::Focused(true)  => { }
+                    WindowMessage::CloseRequested => { close_requested = true; }
+                }
+            }
+            let frame_input = input.take_frame();
 
+            if close_requested || frame_input.pressed("quit") {
+                break;
+            }
+            let last_frame = options.frames.is_some_and(|frames| frame + 1 >= frames);
+            if frame_input.pressed("grab_cursor") {
+                cursor_grabbed = !cursor_grabbed;
+                let _ = requests.send_event(RenderRequest::GrabCursor(cursor_grabbed));
+            }
+            if frame_input.pressed("toggle_camera") {
+                active_camera = (active_camera + 1) % cameras.len();
+                let (camera, controller) = &mut cameras[active_camera];
+                controller.activate(camera);
+                println!("Camera {}", scene.description.cameras[active_camera].name);
+            }
+            let software_render_requested = frame_input.pressed("software_render");
 
+            if frame_input.pressed("pause") {
+                clock.toggle_pause();
+                println!("{}", if clock.paused { "Paused" } else { "Unpaused" });
+            }
+            if frame_input.pressed("step") {
+                clock.step();
+            }
+            if frame_input.pressed("slow_motion") {
+                clock.time_scale = if clock.time_scale == 1.0 { SLOW_MOTION_SCALE } else { 1.0 };
+                println!("Simulation running at {}x", clock.time_scale);
+            }
 
-                apply_heading(helicopter, elapsed + (j as f32 - 1.0) * 0.85);
+            if frame_input.pressed("toggle_gpu_picking") {
+                use_gpu_picking = !use_gpu_picking;
+                println!("Picking on the {}", if use_gpu_picking { "GPU" } else { "CPU" });
             }
 
-            // Handle resize events
-            if let Ok(mut new_size) = window_size.lock() {
-                if new_size.2 {
-                    context.resize(glutin::dpi::PhysicalSize::new(new_size.0, new_size.1));
-                    window_aspect_ratio = new_size.0 as f32 / new_size.1 as f32;
-                    (*new_size).2 = false;
-                    println!("Window was resized to {}x{}", new_size.0, new_size.1);
-                    unsafe { gl::Viewport(0, 0, new_size.0 as i32, new_size.1 as i32); }
+            // The doors of the selected object, or all of them if none is selected
+            let door_command = if frame_input.held("open_doors") { 1.0 } else { 0.0 }
+                             - if frame_input.held("close_doors") { 1.0 } else { 0.0 };
+            command_doors(&mut scene, door_command, selected_object);
+
+            // Taking the controls of the selected helicopter takes it off its path for good, letting
+            // go of them leaves it hovering where it is
+            if frame_input.pressed("fly") {
+                flown_object = match (flown_object, selected_object) {
+                    (None, Some(object)) if scene.world.aircraft.get(object).is_some() => {
+                        scene.world.followers.remove(object);
+                        println!("Flying {}", scene.path_of(object).as_deref().unwrap_or("an object without a name"));
+                        Some(object)
+                    }
+                    (None, _) => {
+                        println!("Select a helicopter to fly first");
+                        None
+                    }
+                    (Some(_), _) => {
+                        println!("Let go of the controls");
+                        None
+                    }
+                };
+            }
+            if let Some(aircraft) = flown_object.and_then(|object| scene.world.aircraft.get_mut(object)) {
+                if frame_input.pressed("engine") {
+                    aircraft.controls.throttle = if aircraft.controls.throttle > 0.0 { 0.0 } else { 1.0 };
+                    println!("Engine {}", if aircraft.controls.throttle > 0.0 { "started" } else { "stopped" });
                 }
             }
-
-            // Handle keyboard input
-            if let Ok(keys) = pressed_keys.lock() {
-                for key in keys.iter() {
-                    match key {
-                        // The `VirtualKeyCode` enum is defined here:
-                        //    https://docs.rs/winit/0.25.0/winit/event/enum.VirtualKeyCode.html
-
-                        VirtualKeyCode::A => {x += delta_time * 7.5;}
-                        VirtualKeyCode::D => {x -= delta_time * 7.5;}
-                        VirtualKeyCode::W => {y -= delta_time * 7.5;}
-                        VirtualKeyCode::S => {y += delta_time * 7.5;}
-
-                        VirtualKeyCode::Space =>  {z += delta_time * 7.5;}
-                        VirtualKeyCode::LShift => {z -= delta_time * 7.5;}
-
-                        VirtualKeyCode::Up =>     {theta_x -= delta_time;}
-                        VirtualKeyCode::Down =>   {theta_x += delta_time;}
-                        VirtualKeyCode::Right =>  {theta_y += delta_time;}
-                        VirtualKeyCode::Left =>   {theta_y -= delta_time;}
-
-                        VirtualKeyCode::J => {
-                            if helicopter_doors_slider_value < 2.0 {
-                                helicopter_doors_slider_value += delta_time * 5.0;
-                            }
-                        }
-                        VirtualKeyCode::K => {
-                            if helicopter_doors_slider_value > 0.0 {
-                                helicopter_doors_slider_value -= delta_time * 5.0;
-                            }
+            let flight_controls = Controls {
+                collective : frame_input.axis("collective"),
+                cyclic     : glm::vec2(frame_input.axis("cyclic_right"), frame_input.axis("cyclic_forward")),
+                pedals     : frame_input.axis("pedals"),
+                ..Controls::default()
+            };
+            command_aircraft(&mut scene, &flight_controls, flown_object);
+
+            let (camera, camera_controller) = &mut cameras[active_camera];
+            if let (Surface::Streamed(chunks), Some(ground)) = (&mut scene.surface, scene.ground) {
+                chunks.update(&camera.position, unsafe { &mut *ground }, &mut [&mut renderer, &mut software_renderer]);
+            }
+            clock.advance(frame_time);
+            interpolation.restore();
+            while let Some(time) = clock.next_tick() {
+                interpolation.begin_tick();
+                scene.world.update(time, clock.timestep.as_secs_f32(), scene.surface.ground());
+                interpolation.end_tick();
+            }
+            interpolation.apply(clock.alpha());
+            scene.root.update_world(&glm::identity());
+
+            // The movement keys fly the helicopter while there is one being flown
+            let camera_input = CameraInput {
+                movement: if flown_object.is_some() { glm::zero() } else { glm::vec3(
+                    frame_input.axis("move_right"),
+                    frame_input.axis("move_up"),
+                    frame_input.axis("move_forward"),
+                ) },
+                look: glm::vec2(
+                    frame_input.axis("look_right") * delta_time + frame_input.axis("mouse_look_right"),
+                    frame_input.axis("look_up") * delta_time + frame_input.axis("mouse_look_up"),
+                ),
+            };
+
+            // The camera is deliberately left out of the fixed ticks: it isn't part of the
+            // simulation, so runs play out the same wherever it looks, and going by the frame
+            // keeps looking around as responsive as the frame rate allows. Controllers which
+            // follow a node see it where the frame shows it, after the interpolation.
+            camera_controller.update(camera, &camera_input, delta_time);
+            let view_projection_matrix = camera.view_projection_matrix();
+
+            scene.root.update_lods(&view_projection_matrix, &glm::identity());
+
+            // Select the helicopter under the cursor, to follow it and open its doors
+            if frame_input.pressed("select") {
+                let picked = if use_gpu_picking {
+                    let draws = picking::pickable_draws(&mut scene.root);
+                    let commands: Vec<DrawCommand> = draws.iter().map(|(_, command)| *command).collect();
+                    id_buffer.pick(&renderer, &commands, &view_projection_matrix, cursor_position.0 as u32, cursor_position.1 as u32)
+                        .map(|i| draws[i].0)
+                } else {
+                    // The software renderer keeps a copy of every uploaded mesh, which is handy here
+                    let ray = Ray::from_screen(cursor_position.0, cursor_position.1, window_size.0, window_size.1, &view_projection_matrix);
+                    picking::pick(&mut scene.root, &ray, software_renderer.meshes()).map(|hit| {
+                        let point = ray.at(hit.distance);
+                        println!("Hit triangle {} at [{:.2}, {:.2}, {:.2}], {:.2} units away, barycentric [{:.2}, {:.2}, {:.2}]",
+                            hit.triangle, point.x, point.y, point.z, hit.distance,
+                            hit.barycentric.x, hit.barycentric.y, hit.barycentric.z);
+                        hit.node
+                    })
+                };
+
+                selected_object = picked.and_then(|node| object_of(&scene, node));
+                match selected_object {
+                    Some(object) => {
+                        println!("Selected {}", scene.path_of(object).as_deref().unwrap_or("an object without a name"));
+                        for (_, controller) in cameras.iter_mut() {
+                            controller.focus_on(unsafe { &*object });
                         }
-
-                        // default handler:
-                        _ => { }
                     }
+                    None => println!("Selected nothing"),
                 }
             }
-            // Handle mouse movement. delta contains the x and y movement of the mouse since last frame in pixels
-            if let Ok(mut delta) = mouse_delta.lock() {
+            render_frame(&mut renderer, &mut render_queue, &scene.root, &view_projection_matrix);
+            if let Some(path) = options.capture_path(frame, last_frame) {
+                save_image(&renderer.read_pixels(window_size.0, window_size.1), &path);
+            }
 
-                // == // Optionally access the accumulated mouse movement between
-                // == // frames here with `delta.0` and `delta.1`
+            // Render the same frame on the CPU, as a reference to compare the GL output with
+            if software_render_requested {
+                let before = std::time::Instant::now();
+                render_frame(&mut software_renderer, &mut render_queue, &scene.root, &view_projection_matrix);
+                match software_renderer.to_image().save("software_render.png") {
+                    Ok(()) => println!("Software render saved to software_render.png in {:.3}ms.",
+                        before.elapsed().as_micros() as f32 / 1e3),
+                    Err(e) => println!("Failed to save software render: {}", e),
+                }
+            }
 
-                *delta = (0.0, 0.0); // reset when done
+            // Write the scene as it is right now out for other tools, with the meshes kept on the CPU
+            if frame_input.pressed("export_scene") {
+                match export::write_gltf(&scene.root, software_renderer.meshes(), Path::new(SCENE_EXPORT_PATH)) {
+                    Ok(()) => println!("Scene exported to {}.", SCENE_EXPORT_PATH),
+                    Err(e) => println!("Failed to export the scene: {}", e),
+                }
             }
 
-            // == // Please compute camera transforms here (exercise 2 & 3)
-            unsafe {
-                // Clear the color and depth buffers
-                gl::ClearColor(0.035, 0.046, 0.078, 1.0); // night sky
-                gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
-
-                simple_shader.activate();
-                
-                let mut view_projection_matrix = glm::Mat4::identity();
-                let translation = glm::translation(&glm::vec3(x, y, z));
-                let rotate_x_axis = glm::rotate_x(&view_projection_matrix, theta_x);
-                let rotate_y_axis = glm::rotate_y(&view_projection_matrix, theta_y);
-                let projection: glm::Mat4 = glm::perspective(window_aspect_ratio, 0.6, 1.0, 1000.0);
-
-                view_projection_matrix = projection * rotate_x_axis* rotate_y_axis * translation * view_projection_matrix;
-
-                let translation_so_far = glm::Mat4::identity();
-                //gl::DepthMask(gl::FALSE); // Disable while drawing, then enable again
-                draw_scene(&scene, &view_projection_matrix, &translation_so_far, u_model_loc, u_view_loc);
-                //gl::DepthMask(gl::TRUE);
+            // The same for the selected object alone, or everything without one, as a single mesh
+            if frame_input.pressed("export_object") {
+                let object: &SceneNode = match selected_object {
+                    Some(object) => unsafe { &*object },
+                    None => &scene.root,
+                };
+                match export::bake(object, software_renderer.meshes()).write_obj(Path::new(OBJECT_EXPORT_PATH)) {
+                    Ok(()) => println!("Object exported to {}.", OBJECT_EXPORT_PATH),
+                    Err(e) => println!("Failed to export the object: {}", e),
+                }
             }
+
+            // Write the scene as it is right now, in a file which can be given on the command line
+            if frame_input.pressed("save_scene") {
+                let cameras: Vec<Camera> = cameras.iter().map(|(camera, _)| *camera).collect();
+                match scene.describe(&cameras).save(Path::new(SCENE_SAVE_PATH)) {
+                    Ok(()) => println!("Scene saved to {}.", SCENE_SAVE_PATH),
+                    Err(e) => println!("Failed to save the scene: {}", e),
+                }
+            }
+
             // Display the new color buffer on the display
             context.swap_buffers().unwrap(); // we use "double buffering" to avoid artifacts
+            if last_frame {
+                break;
+            }
+            frame += 1;
         }
+
+        // The context has to go before the window does
+        drop(context);
+        let _ = requests.send_event(RenderRequest::Exit);
     });
 
     // == // From here on down there are only internals.
-    // Keep track of the health of the rendering thread
-    let render_thread_healthy = Arc::new(RwLock::new(true));
-    let render_thread_watchdog = Arc::clone(&render_thread_healthy);
+    // Keep track of the health of the rendering thread, and terminate the program if it panics
     thread::spawn(move || {
-        if !render_thread.join().is_ok() {
-            if let Ok(mut health) = render_thread_watchdog.write() {
-                println!("Render thread panicked!");
-                *health = false;
-            }
+        if render_thread.join().is_err() {
+            println!("Render thread panicked!");
+            let _ = watchdog_requests.send_event(RenderRequest::Exit);
         }
     });
 
@@ -443,57 +560,29 @@ fn main() {
     el.run(move |event, _, control_flow| {
         *control_flow = ControlFlow::Wait;
 
-        // Terminate program if render thread panics
-        if let Ok(health) = render_thread_healthy.read() {
-            if *health == false {
-                *control_flow = ControlFlow::Exit;
-            }
-        }
-
         match event {
-            Event::WindowEvent { event: WindowEvent::Resized(physical_size), .. } => {
-                println!("New window size received: {}x{}", physical_size.width, physical_size.height);
-                if let Ok(mut new_size) = arc_window_size.lock() {
-                    *new_size = (physical_size.width, physical_size.height, true);
-                }
-            }
-            Event::WindowEvent { event: WindowEvent::CloseRequested, .. } => {
+            Event::UserEvent(RenderRequest::Exit) => {
                 *control_flow = ControlFlow::Exit;
             }
-            // Keep track of currently pressed keys to send to the rendering thread
-            Event::WindowEvent { event: WindowEvent::KeyboardInput {
-                    input: KeyboardInput { state: key_state, virtual_keycode: Some(keycode), .. }, .. }, .. } => {
-
-                if let Ok(mut keys) = arc_pressed_keys.lock() {
-                    match key_state {
-                        Released => {
-                            if keys.contains(&keycode) {
-                                let i = keys.iter().position(|&k| k == keycode).unwrap();
-                                keys.remove(i);
-                            }
-                        },
-                        Pressed => {
-                            if !keys.contains(&keycode) {
-                                keys.push(keycode);
-                            }
-                        }
-                    }
-                }
-
-                // Handle Escape and Q keys separately
-                match keycode {
-                    Escape => { *control_flow = ControlFlow::Exit; }
-                    Q      => { *control_flow = ControlFlow::Exit; }
-                    _      => { }
+            Event::UserEvent(RenderRequest::GrabCursor(grab)) => {
+                // Confining the cursor isn't supported everywhere, in which case locking it may be
+                let grabbed = if grab {
+                    window.set_cursor_grab(CursorGrabMode::Confined)
+                        .or_else(|_| window.set_cursor_grab(CursorGrabMode::Locked))
+                } else {
+                    window.set_cursor_grab(CursorGrabMode::None)
+                };
+                if let Err(e) = grabbed {
+                    println!("Failed to grab the cursor: {}", e);
                 }
+                window.set_cursor_visible(!grab);
             }
-            Event::DeviceEvent { event: DeviceEvent::MouseMotion { delta }, .. } => {
-                // Accumulate mouse movement
-                if let Ok(mut position) = arc_mouse_delta.lock() {
-                    *position = (position.0 + delta.0 as f32, position.1 + delta.1 as f32);
+            // Everything else the rendering thread cares about is passed on to it
+            event => {
+                if let Some(message) = WindowMessage::from_event(&event) {
+                    let _ = message_sender.send(message);
                 }
             }
-            _ => { }
         }
     });
 }
@@ -1,5 +1,21 @@
+extern crate nalgebra_glm as glm;
+use std::path::Path;
 use tobj;
 
+use crate::bounds::{Aabb, BoundingSphere};
+use crate::picking::Ray;
+
+fn obj_load_options() -> tobj::LoadOptions {
+    tobj::LoadOptions {
+        triangulate: true,
+        single_index: true,
+        ..Default::default()
+    }
+}
+
+// Models without normals get smooth ones, creased where faces meet at more than this many radians
+const SMOOTHING_ANGLE: f32 = std::f32::consts::FRAC_PI_3;
+
 // internal helper
 fn generate_color_vec(color: [f32; 4], num: usize) -> Vec<f32> {
     color.iter().cloned().cycle().take(num*4).collect()
@@ -7,61 +23,258 @@ fn generate_color_vec(color: [f32; 4], num: usize) -> Vec<f32> {
 
 // Mesh
 
+#[derive(Clone)]
 pub struct Mesh {
     pub vertices    : Vec<f32>,
     pub normals     : Vec<f32>,
     pub colors      : Vec<f32>,
+    pub uvs         : Vec<f32>, // Two per vertex, or none
     pub indices     : Vec<u32>,
     pub index_count : i32,
+    pub aabb            : Aabb,           // In the mesh's own coordinates
+    #[allow(dead_code)]
+    pub bounding_sphere : BoundingSphere,
 }
 
 impl Mesh {
-    pub fn from(mesh: tobj::Mesh, color: [f32; 4]) -> Self {
-        let num_verts = mesh.positions.len() / 3;
-        let index_count = mesh.indices.len() as i32;
+    pub fn new(vertices: Vec<f32>, normals: Vec<f32>, colors: Vec<f32>, indices: Vec<u32>) -> Self {
+        let index_count = indices.len() as i32;
+        let empty = Aabb { min: glm::zero(), max: glm::zero() };
+        let aabb = Aabb::from_positions(&vertices).unwrap_or(empty);
+        let bounding_sphere = BoundingSphere::from_positions(&vertices).unwrap_or_else(|| empty.bounding_sphere());
         Mesh {
-            vertices: mesh.positions,
-            normals: mesh.normals,
-            indices: mesh.indices,
-            colors: generate_color_vec(color, num_verts),
+            vertices,
+            normals,
+            colors,
+            uvs: vec![],
+            indices,
             index_count,
+            aabb,
+            bounding_sphere,
         }
     }
+
+    pub fn from(mesh: tobj::Mesh, color: [f32; 4]) -> Self {
+        let num_verts = mesh.positions.len() / 3;
+        let colors = generate_color_vec(color, num_verts);
+        let mut result = Mesh::new(mesh.positions, mesh.normals, colors, mesh.indices);
+        result.uvs = mesh.texcoords;
+        if result.normals.is_empty() {
+            result.compute_normals(SMOOTHING_ANGLE);
+        }
+        result
+    }
+
+    // An OBJ file with a single mesh in it, in white
+    pub fn load(path: &Path) -> Result<Mesh, String> {
+        let (models, _materials) = tobj::load_obj(path, &obj_load_options()).map_err(|e| e.to_string())?;
+        match models.len() {
+            1 => { }
+            // You could try merging the vertices and indices of the separate meshes into a single
+            // mesh. I'll leave that as an optional exercise. ;)
+            n => return Err(format!("Expected a model with a single mesh, found {}", n)),
+        }
+        let model = models.into_iter().next().unwrap();
+        let mesh = Mesh::from(model.mesh, [1.0, 1.0, 1.0, 1.0]);
+        mesh.validate()?;
+        Ok(mesh)
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    // Whether any vertex is colored less than opaque
+    pub fn has_translucent_colors(&self) -> bool {
+        self.colors.chunks_exact(4).any(|color| color[3] < 1.0)
+    }
 }
 
 // Lunar terrain
 
-pub struct Terrain;
+// Roughly how many triangles end up in every cell of a terrain's grid
+const TRIANGLES_PER_CELL: f32 = 4.0;
+
+// How far outside of a triangle, as a fraction of it, a point may be and still count as on it.
+// Keeps points on the edges between triangles from falling through the cracks.
+const EDGE_TOLERANCE: f32 = 1e-5;
+
+// The ground things stand on, which can be asked how high it is anywhere
+pub trait Ground {
+    // The height of the ground at a point, or None where there is no ground
+    fn height_at(&self, x: f32, z: f32) -> Option<f32>;
+
+    // The upwards facing normal of the ground at a point
+    fn normal_at(&self, x: f32, z: f32) -> Option<glm::Vec3>;
+}
+
+// A heightfield over the X-Z plane, made from a mesh without overhangs. The triangles are sorted
+// into a uniform grid of cells as seen from above, so that queries only look at the few triangles
+// around them. Everything is in the coordinates of the mesh.
+pub struct Terrain {
+    positions : Vec<glm::Vec3>,
+    normals   : Vec<glm::Vec3>, // Empty if the mesh has none
+    triangles : Vec<[u32; 3]>,
+    bounds    : Aabb,
+    cell_size : f32,
+    columns   : usize,          // Along the X axis
+    rows      : usize,          // Along the Z axis
+    cells     : Vec<Vec<u32>>,  // The triangles overlapping every cell, row by row
+}
+
 impl Terrain {
-    pub fn load(path: &str) -> Mesh {
-        println!("Loading terrain model...");
-        let before = std::time::Instant::now();
-        let (models, _materials)
-            = tobj::load_obj(path,
-                &tobj::LoadOptions{
-                    triangulate: true,
-                    single_index: true,
-                    ..Default::default()
+    pub fn new(mesh: &Mesh) -> Terrain {
+        let to_vec3 = |p: &[f32]| glm::vec3(p[0], p[1], p[2]);
+        let positions: Vec<glm::Vec3> = mesh.vertices.chunks_exact(3).map(to_vec3).collect();
+        let normals: Vec<glm::Vec3> = if mesh.normals.len() == mesh.vertices.len() {
+            mesh.normals.chunks_exact(3).map(to_vec3).collect()
+        } else {
+            vec![]
+        };
+        let triangles: Vec<[u32; 3]> = mesh.indices.chunks_exact(3).map(|t| [t[0], t[1], t[2]]).collect();
+
+        let bounds = mesh.aabb;
+        let size = bounds.max - bounds.min;
+        let area = (size.x * size.z).max(f32::EPSILON);
+        let cell_size = (area * TRIANGLES_PER_CELL / triangles.len().max(1) as f32).sqrt();
+        let columns = ((size.x / cell_size).ceil() as usize).max(1);
+        let rows = ((size.z / cell_size).ceil() as usize).max(1);
+
+        let mut terrain = Terrain { positions, normals, triangles, bounds, cell_size, columns, rows, cells: vec![vec![]; columns * rows] };
+
+        // Every triangle goes into all the cells its footprint's bounding rectangle overlaps
+        for (i, triangle) in terrain.triangles.iter().enumerate() {
+            let corners = triangle.map(|v| terrain.positions[v as usize]);
+            let min = glm::min2(&glm::min2(&corners[0], &corners[1]), &corners[2]);
+            let max = glm::max2(&glm::max2(&corners[0], &corners[1]), &corners[2]);
+            let (first_column, first_row) = terrain.clamped_cell(min.x, min.z);
+            let (last_column, last_row) = terrain.clamped_cell(max.x, max.z);
+            for row in first_row..=last_row {
+                for column in first_column..=last_column {
+                    terrain.cells[row * columns + column].push(i as u32);
                 }
-            ).expect("Failed to load terrain model");
-        let after = std::time::Instant::now();
-        println!("Done in {:.3}ms.", after.duration_since(before).as_micros() as f32 / 1e3);
-
-        if models.len() > 1 || models.len() == 0 {
-            panic!("Please use a model with a single mesh!")
-            // You could try merging the vertices and indices
-            // of the separate meshes into a single mesh.
-            // I'll leave that as an optional exercise. ;)
+            }
         }
+        terrain
+    }
 
-        let terrain = models[0].to_owned();
-        println!("Loaded {} with {} points and {} triangles.",
-            terrain.name,
-            terrain.mesh.positions.len() /3,
-            terrain.mesh.indices.len() / 3,
-        );
+    // The cell containing a point, or the closest one if it is outside of the grid
+    fn clamped_cell(&self, x: f32, z: f32) -> (usize, usize) {
+        let column = ((x - self.bounds.min.x) / self.cell_size).max(0.0) as usize;
+        let row = ((z - self.bounds.min.z) / self.cell_size).max(0.0) as usize;
+        (column.min(self.columns - 1), row.min(self.rows - 1))
+    }
 
-        Mesh::from(terrain.mesh, [1.0, 1.0, 1.0, 1.0])
+    fn cell_triangles(&self, column: usize, row: usize) -> &[u32] {
+        &self.cells[row * self.columns + column]
+    }
+
+    // The triangle right above or below a point, along with the barycentric coordinates of the
+    // point on it. Where several are, the highest one.
+    fn triangle_at(&self, x: f32, z: f32) -> Option<(usize, glm::Vec3)> {
+        if x < self.bounds.min.x || x > self.bounds.max.x || z < self.bounds.min.z || z > self.bounds.max.z {
+            return None;
+        }
+        let (column, row) = self.clamped_cell(x, z);
+
+        let mut highest: Option<(usize, glm::Vec3, f32)> = None;
+        for &i in self.cell_triangles(column, row) {
+            let [a, b, c] = self.triangles[i as usize].map(|v| self.positions[v as usize]);
+            let determinant = (b.z - c.z) * (a.x - c.x) + (c.x - b.x) * (a.z - c.z);
+            if determinant.abs() < f32::EPSILON {
+                continue; // Standing on its edge, seen from above
+            }
+            let w0 = ((b.z - c.z) * (x - c.x) + (c.x - b.x) * (z - c.z)) / determinant;
+            let w1 = ((c.z - a.z) * (x - c.x) + (a.x - c.x) * (z - c.z)) / determinant;
+            let w2 = 1.0 - w0 - w1;
+            if w0 < -EDGE_TOLERANCE || w1 < -EDGE_TOLERANCE || w2 < -EDGE_TOLERANCE {
+                continue;
+            }
+            let y = a.y * w0 + b.y * w1 + c.y * w2;
+            if highest.is_none_or(|(_, _, highest_y)| y > highest_y) {
+                highest = Some((i as usize, glm::vec3(w0, w1, w2), y));
+            }
+        }
+        highest.map(|(i, barycentric, _)| (i, barycentric))
+    }
+
+    // The distance along the ray to where it first hits the ground. Walks through the cells the
+    // ray passes over in order, following Amanatides & Woo, so it can stop at the first hit.
+    #[allow(dead_code)]
+    pub fn intersect_ray(&self, ray: &Ray) -> Option<f32> {
+        let entry = ray.intersect_aabb(&self.bounds)?;
+        let start = ray.at(entry);
+        let (mut column, mut row) = self.clamped_cell(start.x, start.z);
+
+        // How far along the ray the next cell boundary is, and how far apart they are, per axis
+        let axis = |direction: f32, start: f32, min: f32, cell: usize| -> (f32, f32) {
+            if direction == 0.0 {
+                return (f32::INFINITY, f32::INFINITY);
+            }
+            let boundary = min + (cell + if direction > 0.0 { 1 } else { 0 }) as f32 * self.cell_size;
+            (entry + (boundary - start) / direction, self.cell_size / direction.abs())
+        };
+        let (mut next_x, delta_x) = axis(ray.direction.x, start.x, self.bounds.min.x, column);
+        let (mut next_z, delta_z) = axis(ray.direction.z, start.z, self.bounds.min.z, row);
+
+        let mut closest = f32::INFINITY;
+        loop {
+            for &i in self.cell_triangles(column, row) {
+                let [a, b, c] = self.triangles[i as usize].map(|v| self.positions[v as usize]);
+                if let Some((distance, _, _)) = ray.intersect_triangle(&a, &b, &c) {
+                    closest = closest.min(distance);
+                }
+            }
+
+            // Triangles reach into several cells, so a hit further than this cell may yet be
+            // beaten by one in the next
+            let cell_exit = next_x.min(next_z);
+            if closest <= cell_exit && closest.is_finite() {
+                return Some(closest);
+            }
+            if cell_exit.is_infinite() {
+                break; // Straight up or down, so this was the only cell
+            }
+            if next_x < next_z {
+                match (ray.direction.x > 0.0, column) {
+                    (true, c) if c + 1 < self.columns => column += 1,
+                    (false, c) if c > 0 => column -= 1,
+                    _ => break,
+                }
+                next_x += delta_x;
+            } else {
+                match (ray.direction.z > 0.0, row) {
+                    (true, r) if r + 1 < self.rows => row += 1,
+                    (false, r) if r > 0 => row -= 1,
+                    _ => break,
+                }
+                next_z += delta_z;
+            }
+        }
+        if closest.is_finite() { Some(closest) } else { None }
+    }
+}
+
+impl Ground for Terrain {
+    // None outside of the terrain
+    fn height_at(&self, x: f32, z: f32) -> Option<f32> {
+        let (i, weights) = self.triangle_at(x, z)?;
+        let [a, b, c] = self.triangles[i].map(|v| self.positions[v as usize].y);
+        Some(a * weights.x + b * weights.y + c * weights.z)
+    }
+
+    // Interpolated between the vertex normals when the mesh has them, otherwise the normal of the
+    // triangle
+    fn normal_at(&self, x: f32, z: f32) -> Option<glm::Vec3> {
+        let (i, weights) = self.triangle_at(x, z)?;
+        let [a, b, c] = self.triangles[i].map(|v| v as usize);
+        let normal = if self.normals.is_empty() {
+            glm::cross(&(self.positions[b] - self.positions[a]), &(self.positions[c] - self.positions[a]))
+        } else {
+            self.normals[a] * weights.x + self.normals[b] * weights.y + self.normals[c] * weights.z
+        };
+        let normal = glm::normalize(&normal);
+        Some(if normal.y < 0.0 { -normal } else { normal })
     }
 }
 
@@ -76,49 +289,135 @@ pub struct Helicopter {
 }
 
 // You can use square brackets to access the components of the helicopter, if you want to use loops!
+// They're in the order the parts are below the body in the scenes: door, main rotor, tail rotor.
 use std::ops::Index;
 impl Index<usize> for Helicopter {
     type Output = Mesh;
-    fn index<'a>(&'a self, i: usize) -> &'a Mesh {
+    fn index(&self, i: usize) -> &Mesh {
         match i {
             0 => &self.body,
-            1 => &self.main_rotor,
-            2 => &self.tail_rotor,
-            3 => &self.door,
+            1 => &self.door,
+            2 => &self.main_rotor,
+            3 => &self.tail_rotor,
             _ => panic!("Invalid index, try [0,3]"),
         }
     }
 }
 
 impl Helicopter {
-    pub fn load(path: &str) -> Self {
-        println!("Loading helicopter model...");
-        let before = std::time::Instant::now();
-        let (models, _materials)
-            = tobj::load_obj(path,
-                &tobj::LoadOptions{
-                    triangulate: true,
-                    single_index: true,
-                    ..Default::default()
-                }
-            ).expect("Failed to load helicopter model");
-        let after = std::time::Instant::now();
-        println!("Done in {:.3}ms!", after.duration_since(before).as_micros() as f32 / 1e3);
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let (models, _materials) = tobj::load_obj(path, &obj_load_options()).map_err(|e| e.to_string())?;
+        let part = |name: &str, color: [f32; 4]| {
+            let model = models.iter().find(|m| m.name == name).ok_or_else(|| format!("Incorrect model file, no {}", name))?;
+            let mesh = Mesh::from(model.mesh.clone(), color);
+            mesh.validate().map_err(|e| format!("{}: {}", name, e))?;
+            Ok::<Mesh, String>(mesh)
+        };
+
+        Ok(Helicopter {
+            body:       part("Body_body",             [0.3, 0.3, 0.3, 1.0])?,
+            door:       part("Door_door",             [0.1, 0.1, 0.3, 1.0])?,
+            main_rotor: part("Main_Rotor_main_rotor", [0.3, 0.1, 0.1, 1.0])?,
+            tail_rotor: part("Tail_Rotor_tail_rotor", [0.1, 0.3, 0.1, 1.0])?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        for model in &models {
-            println!("Loaded {} with {} points and {} triangles.", model.name, model.mesh.positions.len() / 3, model.mesh.indices.len() / 3);
+    fn ray(origin: glm::Vec3, direction: glm::Vec3) -> Ray {
+        Ray { origin, direction: glm::normalize(&direction) }
+    }
+
+    // Where a ray reaches y = 0
+    fn distance_to_ground(ray: &Ray) -> f32 {
+        -ray.origin.y / ray.direction.y
+    }
+
+    // A 2 by 2 grid of quads over x and z from 0 to 2, bent so that neighbouring triangles
+    // aren't in the same plane. Every quad is split from its corner nearest the origin.
+    fn bent_terrain(normals: Vec<f32>) -> Terrain {
+        let heights = [0.0, 1.0, 2.0, 1.0, 3.0, 2.0, 2.0, 2.0, 4.0];
+        let vertices = (0..9).flat_map(|i| [(i % 3) as f32, heights[i], (i / 3) as f32]).collect();
+        let indices = [0, 1, 3, 4].iter().flat_map(|&v| [v, v + 3, v + 4, v, v + 4, v + 1]).collect();
+        Terrain::new(&Mesh::new(vertices, normals, vec![], indices))
+    }
+
+    fn assert_close(actual: glm::Vec3, expected: glm::Vec3) {
+        assert!(glm::distance(&actual, &expected) < 1e-5, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn sloped_ground_is_interpolated_over_its_triangles() {
+        let terrain = bent_terrain(vec![]);
+        let cases = [
+            (0.25, 0.75, 1.25, glm::vec3(-2.0, 1.0, -1.0)), // Where y = 2x + z
+            (0.75, 0.25, 1.25, glm::vec3(-1.0, 1.0, -2.0)), // Where y = x + 2z
+            (1.5,  0.75, 2.0,  glm::vec3( 1.0, 1.0, -2.0)), // Where y = 2 - x + 2z
+            (1.25, 1.75, 2.75, glm::vec3(-2.0, 1.0,  1.0)), // Where y = 2 + 2x - z
+        ];
+        for (x, z, height, normal) in cases {
+            assert!((terrain.height_at(x, z).unwrap() - height).abs() < 1e-5, "at {}, {}", x, z);
+            assert_close(terrain.normal_at(x, z).unwrap(), glm::normalize(&normal));
+        }
+    }
+
+    #[test]
+    fn points_on_shared_edges_are_on_the_ground() {
+        let terrain = bent_terrain(vec![]);
+        // Between the two triangles of a quad, and between two quads
+        for (x, z, height, normals) in [
+            (0.5, 0.5, 1.5, [glm::vec3(-2.0, 1.0, -1.0), glm::vec3(-1.0, 1.0, -2.0)]),
+            (1.0, 0.5, 2.0, [glm::vec3(-1.0, 1.0, -2.0), glm::vec3( 1.0, 1.0, -2.0)]),
+        ] {
+            assert!((terrain.height_at(x, z).unwrap() - height).abs() < 1e-5, "at {}, {}", x, z);
+            let normal = terrain.normal_at(x, z).unwrap();
+            assert!(normals.iter().any(|n| glm::distance(&normal, &glm::normalize(n)) < 1e-5), "{} at {}, {}", normal, x, z);
         }
 
-        let body_model = models.iter().find(|m| m.name == "Body_body").expect("Incorrect model file!").to_owned();
-        let door_model = models.iter().find(|m| m.name == "Door_door").expect("Incorrect model file!").to_owned();
-        let main_rotor_model = models.iter().find(|m| m.name == "Main_Rotor_main_rotor").expect("Incorrect model file!").to_owned();
-        let tail_rotor_model = models.iter().find(|m| m.name == "Tail_Rotor_tail_rotor").expect("Incorrect model file!").to_owned();
+        // With vertex normals, both triangles agree along the edge
+        let mut normals = [0.0, 1.0, 0.0].repeat(9);
+        normals[3..6].copy_from_slice(&[0.6, 0.8, 0.0]);
+        normals[12..15].copy_from_slice(&[0.0, 0.8, -0.6]);
+        let smooth = bent_terrain(normals);
+        assert_close(smooth.normal_at(1.0, 0.5).unwrap(), glm::normalize(&glm::vec3(0.6, 1.6, -0.6)));
+    }
+
+    #[test]
+    fn there_is_no_ground_outside_of_the_grid() {
+        let terrain = bent_terrain(vec![]);
+        for (x, z) in [(-0.01, 1.0), (1.0, 2.01), (2.5, -0.5), (-100.0, 100.0)] {
+            assert_eq!(terrain.height_at(x, z), None, "at {}, {}", x, z);
+            assert_eq!(terrain.normal_at(x, z), None, "at {}, {}", x, z);
+        }
+    }
+
+    #[test]
+    fn rays_hit_a_flat_terrain_from_either_side() {
+        let terrain = Terrain::new(&Mesh::plane(10.0, 10.0, 8, 8, [1.0; 4]));
+        for ray in [
+            ray(glm::vec3(1.3, 5.0, -2.1), glm::vec3(0.3, -1.0, 0.2)),
+            ray(glm::vec3(0.0, 5.0, 0.0), glm::vec3(0.0, -1.0, 0.0)),
+            ray(glm::vec3(-2.0, -3.0, 1.0), glm::vec3(0.5, 1.0, 0.0)), // From below
+            ray(glm::vec3(-12.0, 1.0, -12.0), glm::vec3(1.0, -0.07, 1.0)), // Across many cells, from outside
+        ] {
+            let distance = terrain.intersect_ray(&ray).unwrap_or_else(|| panic!("{:?} missed", ray));
+            assert!((distance - distance_to_ground(&ray)).abs() < 1e-4, "{:?} hit at {}", ray, distance);
+        }
+    }
 
-        Helicopter {
-            body:       Mesh::from(body_model.mesh,         [0.3, 0.3, 0.3, 1.0]),
-            door:       Mesh::from(door_model.mesh,         [0.1, 0.1, 0.3, 1.0]),
-            main_rotor: Mesh::from(main_rotor_model.mesh,   [0.3, 0.1, 0.1, 1.0]),
-            tail_rotor: Mesh::from(tail_rotor_model.mesh,   [0.1, 0.3, 0.1, 1.0]),
+    #[test]
+    fn rays_miss_a_flat_terrain_when_they_should() {
+        let terrain = Terrain::new(&Mesh::plane(10.0, 10.0, 8, 8, [1.0; 4]));
+        for ray in [
+            ray(glm::vec3(0.0, 5.0, 0.0), glm::vec3(0.0, 1.0, 0.0)),     // Away from it
+            ray(glm::vec3(20.0, 5.0, 0.0), glm::vec3(0.0, -1.0, 0.0)),   // Beside it
+            ray(glm::vec3(-20.0, 1.0, 0.0), glm::vec3(1.0, 0.0, 0.0)),   // Over it
+            ray(glm::vec3(-20.0, 2.0, 0.0), glm::vec3(1.0, -0.01, 0.0)), // Coming down past its far edge
+        ] {
+            assert_eq!(terrain.intersect_ray(&ray), None, "{:?}", ray);
         }
     }
 }
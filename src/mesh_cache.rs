@@ -0,0 +1,309 @@
+extern crate nalgebra_glm as glm;
+
+use std::convert::TryInto;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::bounds::{Aabb, BoundingSphere};
+use crate::mesh::{Helicopter, Mesh};
+
+// A binary format for meshes, so that big OBJ files only have to be parsed once. Parsed OBJs are
+// cached next to them, and parsed again when the OBJ is newer than the cache. Helicopters get a
+// cache file for every part.
+//
+// Everything is little endian:
+//   magic "GLMS", version                          4 bytes, u32
+//   modification time of the source, or 0          u64, nanoseconds since the epoch
+//   vertex count, index count, layout              u32 each, the layout flags saying which
+//                                                  attributes there are besides positions
+//   positions, normals, colors, uvs                f32, 3, 3, 4 and 2 per vertex
+//   indices                                        u32
+//   bounding box min and max, sphere center and    f32 x 10
+//   radius
+//   checksum of everything before it               u64, FNV-1a
+
+const MAGIC: &[u8; 4] = b"GLMS";
+const VERSION: u32 = 2; // 2: OBJs without normals get computed ones
+
+const HAS_NORMALS: u32 = 1;
+const HAS_COLORS: u32 = 2;
+const HAS_UVS: u32 = 4;
+
+// Appended to the name of the source file
+const CACHE_EXTENSION: &str = "meshcache";
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
+
+fn encode(mesh: &Mesh, source_modified: u64) -> Vec<u8> {
+    let vertex_count = mesh.vertices.len() / 3;
+    let mut layout = 0;
+    for (attribute, flag) in [(&mesh.normals, HAS_NORMALS), (&mesh.colors, HAS_COLORS), (&mesh.uvs, HAS_UVS)] {
+        if !attribute.is_empty() {
+            layout |= flag;
+        }
+    }
+
+    let mut bytes = Vec::with_capacity(64 + 4 * (mesh.vertices.len() + mesh.normals.len() + mesh.colors.len() + mesh.uvs.len() + mesh.indices.len()));
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    bytes.extend_from_slice(&source_modified.to_le_bytes());
+    for value in [vertex_count as u32, mesh.indices.len() as u32, layout] {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    for attribute in [&mesh.vertices, &mesh.normals, &mesh.colors, &mesh.uvs] {
+        bytes.extend(attribute.iter().flat_map(|value| value.to_le_bytes()));
+    }
+    bytes.extend(mesh.indices.iter().flat_map(|index| index.to_le_bytes()));
+    let sphere = &mesh.bounding_sphere;
+    let bounds = [mesh.aabb.min.as_slice(), mesh.aabb.max.as_slice(), sphere.center.as_slice(), &[sphere.radius]].concat();
+    bytes.extend(bounds.iter().flat_map(|value| value.to_le_bytes()));
+    let checksum = fnv1a(&bytes);
+    bytes.extend_from_slice(&checksum.to_le_bytes());
+    bytes
+}
+
+struct Reader<'a> {
+    bytes  : &'a [u8],
+    offset : usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+        let end = self.offset.checked_add(count).filter(|&end| end <= self.bytes.len()).ok_or("Truncated mesh")?;
+        let taken = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn u32s(&mut self, count: usize) -> Result<Vec<u32>, String> {
+        let bytes = self.take(count.checked_mul(4).ok_or("Truncated mesh")?)?;
+        Ok(bytes.chunks_exact(4).map(|b| u32::from_le_bytes(b.try_into().unwrap())).collect())
+    }
+
+    fn f32s(&mut self, count: usize) -> Result<Vec<f32>, String> {
+        Ok(self.u32s(count)?.into_iter().map(f32::from_bits).collect())
+    }
+}
+
+// The mesh, and the modification time of its source
+fn decode(bytes: &[u8]) -> Result<(Mesh, u64), String> {
+    if bytes.len() < 8 {
+        return Err("Truncated mesh".to_string());
+    }
+    let (contents, checksum) = bytes.split_at(bytes.len() - 8);
+    if fnv1a(contents) != u64::from_le_bytes(checksum.try_into().unwrap()) {
+        return Err("Checksum mismatch".to_string());
+    }
+
+    let mut reader = Reader { bytes: contents, offset: 0 };
+    if reader.take(4)? != MAGIC {
+        return Err("Not a mesh file".to_string());
+    }
+    let version = reader.u32()?;
+    if version != VERSION {
+        return Err(format!("Unsupported mesh version {}", version));
+    }
+    let source_modified = reader.u64()?;
+    let vertex_count = reader.u32()? as usize;
+    let index_count = reader.u32()? as usize;
+    let layout = reader.u32()?;
+
+    let vertices = reader.f32s(3 * vertex_count)?;
+    let mut attribute = |flag: u32, per_vertex: usize| {
+        if layout & flag != 0 { reader.f32s(per_vertex * vertex_count) } else { Ok(vec![]) }
+    };
+    let normals = attribute(HAS_NORMALS, 3)?;
+    let colors = attribute(HAS_COLORS, 4)?;
+    let uvs = attribute(HAS_UVS, 2)?;
+    let indices = reader.u32s(index_count)?;
+    if indices.iter().any(|&index| index as usize >= vertex_count) {
+        return Err("Index out of bounds".to_string());
+    }
+    let bounds = reader.f32s(10)?;
+    if reader.offset != contents.len() {
+        return Err("Trailing bytes after mesh".to_string());
+    }
+
+    // The bounds are stored rather than computed again, which is most of the point on big meshes
+    let mesh = Mesh {
+        vertices,
+        normals,
+        colors,
+        uvs,
+        index_count     : indices.len() as i32,
+        indices,
+        aabb            : Aabb { min: glm::make_vec3(&bounds[0..3]), max: glm::make_vec3(&bounds[3..6]) },
+        bounding_sphere : BoundingSphere { center: glm::make_vec3(&bounds[6..9]), radius: bounds[9] },
+    };
+    Ok((mesh, source_modified))
+}
+
+fn modified_nanos(path: &Path) -> Result<u64, String> {
+    let modified = fs::metadata(path).and_then(|metadata| metadata.modified()).map_err(|e| e.to_string())?;
+    Ok(modified.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_nanos() as u64))
+}
+
+// Like model.obj.meshcache, or model.obj.door.meshcache for a part of the model
+fn cache_path(source: &Path, part: Option<&str>) -> PathBuf {
+    let mut name = source.file_name().unwrap_or_default().to_os_string();
+    for suffix in part.into_iter().chain([CACHE_EXTENSION]) {
+        name.push(".");
+        name.push(suffix);
+    }
+    source.with_file_name(name)
+}
+
+// The cached mesh, unless there is no cache or it's broken or older than the source
+fn read_cache(cache: &Path, source_modified: u64) -> Option<Mesh> {
+    let bytes = fs::read(cache).ok()?;
+    match decode(&bytes) {
+        Ok((mesh, cached_modified)) if cached_modified == source_modified => Some(mesh),
+        Ok(_) => None, // Stale
+        Err(e) => {
+            println!("Ignoring broken mesh cache {}: {}", cache.display(), e);
+            None
+        }
+    }
+}
+
+// Failing to write the cache, say in a read-only directory, only makes the next start slower
+fn write_cache(cache: &Path, mesh: &Mesh, source_modified: u64) {
+    // Written next to it and renamed, so that nobody reads half a cache
+    let partial = cache.with_extension(format!("{}.partial", CACHE_EXTENSION));
+    let written = fs::write(&partial, encode(mesh, source_modified)).and_then(|_| fs::rename(&partial, cache));
+    if let Err(e) = written {
+        println!("Couldn't write mesh cache {}: {}", cache.display(), e);
+        let _ = fs::remove_file(&partial);
+    }
+}
+
+impl Mesh {
+    // Like Mesh::load, but through a cache next to the OBJ file
+    pub fn load_cached(path: &Path) -> Result<Mesh, String> {
+        let source_modified = modified_nanos(path)?;
+        let cache = cache_path(path, None);
+        if let Some(mesh) = read_cache(&cache, source_modified) {
+            return Ok(mesh);
+        }
+        let mesh = Mesh::load(path)?;
+        write_cache(&cache, &mesh, source_modified);
+        Ok(mesh)
+    }
+}
+
+// In the order of Helicopter's Index
+const HELICOPTER_PARTS: [&str; 4] = ["body", "door", "main_rotor", "tail_rotor"];
+
+impl Helicopter {
+    // Like Helicopter::load, but through a cache next to the OBJ file for every part. All of the
+    // parts are loaded again if any of them isn't cached.
+    pub fn load_cached(path: &Path) -> Result<Helicopter, String> {
+        let source_modified = modified_nanos(path)?;
+        let caches = HELICOPTER_PARTS.map(|part| cache_path(path, Some(part)));
+        let cached: Option<Vec<Mesh>> = caches.iter().map(|cache| read_cache(cache, source_modified)).collect();
+        let cached: Option<[Mesh; 4]> = cached.and_then(|parts| parts.try_into().ok());
+        if let Some([body, door, main_rotor, tail_rotor]) = cached {
+            return Ok(Helicopter { body, door, main_rotor, tail_rotor });
+        }
+        let helicopter = Helicopter::load(path)?;
+        for (i, cache) in caches.iter().enumerate() {
+            write_cache(cache, &helicopter[i], source_modified);
+        }
+        Ok(helicopter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_same(a: &Mesh, b: &Mesh) {
+        assert_eq!(a.vertices, b.vertices);
+        assert_eq!(a.normals, b.normals);
+        assert_eq!(a.colors, b.colors);
+        assert_eq!(a.uvs, b.uvs);
+        assert_eq!(a.indices, b.indices);
+        assert_eq!(a.index_count, b.index_count);
+        assert_eq!((a.aabb.min, a.aabb.max), (b.aabb.min, b.aabb.max));
+        assert_eq!((a.bounding_sphere.center, a.bounding_sphere.radius), (b.bounding_sphere.center, b.bounding_sphere.radius));
+    }
+
+    // With the checksum put right again after changing the contents
+    fn resealed(mut contents: Vec<u8>) -> Vec<u8> {
+        let checksum = fnv1a(&contents);
+        contents.extend_from_slice(&checksum.to_le_bytes());
+        contents
+    }
+
+    #[test]
+    fn round_trips() {
+        let mesh = Mesh::cube(2.0, 2, [0.2, 0.4, 0.6, 0.8]);
+        let (decoded, modified) = decode(&encode(&mesh, 1234)).unwrap();
+        assert_same(&mesh, &decoded);
+        assert_eq!(modified, 1234);
+
+        // Without the optional attributes
+        let bare = Mesh::new(vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0], vec![], vec![], vec![0, 1, 2]);
+        let (decoded, _) = decode(&encode(&bare, 0)).unwrap();
+        assert_same(&bare, &decoded);
+    }
+
+    #[test]
+    fn rejects_a_bad_checksum() {
+        let mut bytes = encode(&Mesh::cube(1.0, 1, [1.0; 4]), 0);
+        bytes[20] ^= 1;
+        assert_eq!(decode(&bytes).err().unwrap(), "Checksum mismatch");
+    }
+
+    #[test]
+    fn rejects_a_truncated_file() {
+        let bytes = encode(&Mesh::cube(1.0, 1, [1.0; 4]), 0);
+        assert!(decode(&bytes[..bytes.len() - 12]).is_err());
+        assert_eq!(decode(&bytes[..4]).err().unwrap(), "Truncated mesh");
+        // Even with a checksum which fits
+        let contents = bytes[..bytes.len() - 8 - 12].to_vec();
+        assert_eq!(decode(&resealed(contents)).err().unwrap(), "Truncated mesh");
+    }
+
+    #[test]
+    fn rejects_a_wrong_version() {
+        let bytes = encode(&Mesh::cube(1.0, 1, [1.0; 4]), 0);
+        let mut contents = bytes[..bytes.len() - 8].to_vec();
+        contents[4..8].copy_from_slice(&(VERSION + 1).to_le_bytes());
+        assert_eq!(decode(&resealed(contents)).err().unwrap(), format!("Unsupported mesh version {}", VERSION + 1));
+    }
+
+    #[test]
+    fn caches_every_part_of_a_helicopter() {
+        let directory = std::env::temp_dir().join(format!("gloom-mesh-cache-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("helicopter.obj");
+        let parts = ["Body_body", "Door_door", "Main_Rotor_main_rotor", "Tail_Rotor_tail_rotor"];
+        let obj: String = parts.iter().enumerate().map(|(i, name)| {
+            let x = i as f32;
+            format!("o {}\nv {} 0 0\nv {} 0 0\nv {} 1 0\nf {} {} {}\n", name, x, x + 1.0, x, 3 * i + 1, 3 * i + 2, 3 * i + 3)
+        }).collect();
+        fs::write(&path, obj).unwrap();
+
+        let loaded = Helicopter::load_cached(&path).unwrap();
+        for part in HELICOPTER_PARTS {
+            assert!(cache_path(&path, Some(part)).exists(), "no cache for the {}", part);
+        }
+        let cached = Helicopter::load_cached(&path).unwrap();
+        for i in 0..4 {
+            assert_same(&loaded[i], &cached[i]);
+        }
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,290 @@
+extern crate nalgebra_glm as glm;
+
+use std::collections::HashMap;
+
+use crate::mesh::Mesh;
+
+// Repairs and derived attributes for meshes: normals, tangents, welding duplicate vertices,
+// dropping degenerate triangles, and checking that everything adds up.
+
+impl Mesh {
+    fn position(&self, vertex: u32) -> glm::Vec3 {
+        let i = 3 * vertex as usize;
+        glm::vec3(self.vertices[i], self.vertices[i + 1], self.vertices[i + 2])
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len() / 3
+    }
+
+    // Each of the attributes, along with how many components it has per vertex. Empty ones are
+    // left out.
+    fn attributes(&self) -> Vec<(&Vec<f32>, usize)> {
+        vec![(&self.vertices, 3), (&self.normals, 3), (&self.colors, 4), (&self.uvs, 2)].into_iter()
+            .filter(|(attribute, _)| !attribute.is_empty())
+            .collect()
+    }
+
+    // The mesh with new vertices, each one copied from an old one, and new indices
+    fn rebuilt(&self, old_vertices: &[u32], indices: Vec<u32>) -> Mesh {
+        let copy = |attribute: &Vec<f32>, components: usize| -> Vec<f32> {
+            if attribute.is_empty() {
+                return vec![];
+            }
+            old_vertices.iter().flat_map(|&v| {
+                let start = components * v as usize;
+                attribute[start..start + components].iter().copied()
+            }).collect()
+        };
+        let mut mesh = Mesh::new(copy(&self.vertices, 3), copy(&self.normals, 3), copy(&self.colors, 4), indices);
+        mesh.uvs = copy(&self.uvs, 2);
+        mesh
+    }
+
+    // Normals averaged over the triangles around each vertex, weighted by the angle of their
+    // corner there, but only over those facing within smoothing_angle radians of each other.
+    // 0 gives flat shading, and PI smooths everything. Vertices sharing a position are smoothed
+    // together even when the file split them, and vertices on a crease are split in two.
+    pub fn compute_normals(&mut self, smoothing_angle: f32) {
+        let triangles: Vec<[u32; 3]> = self.indices.chunks_exact(3).map(|t| [t[0], t[1], t[2]]).collect();
+        let face_normals: Vec<glm::Vec3> = triangles.iter().map(|&[a, b, c]| {
+            let normal = glm::cross(&(self.position(b) - self.position(a)), &(self.position(c) - self.position(a)));
+            if glm::length(&normal) > 0.0 { glm::normalize(&normal) } else { glm::zero() }
+        }).collect();
+
+        // The corners of every triangle, grouped by the position of their vertex
+        let mut corners_at: HashMap<[u32; 3], Vec<(usize, f32)>> = HashMap::new();
+        let position_key = |v: u32| self.position(v).map(|x| x.to_bits());
+        for (t, &[a, b, c]) in triangles.iter().enumerate() {
+            for (vertex, previous, next) in [(a, c, b), (b, a, c), (c, b, a)] {
+                let to_previous = self.position(previous) - self.position(vertex);
+                let to_next = self.position(next) - self.position(vertex);
+                let angle = glm::angle(&to_previous, &to_next);
+                let weight = if angle.is_finite() { angle } else { 0.0 };
+                let key = position_key(vertex);
+                corners_at.entry([key.x, key.y, key.z]).or_default().push((t, weight));
+            }
+        }
+
+        let threshold = smoothing_angle.cos();
+        let mut new_vertex: HashMap<(u32, [u32; 3]), u32> = HashMap::new();
+        let mut old_vertices: Vec<u32> = vec![];
+        let mut normals: Vec<f32> = vec![];
+        let mut indices: Vec<u32> = Vec::with_capacity(self.indices.len());
+        for (t, triangle) in triangles.iter().enumerate() {
+            for &vertex in triangle {
+                let key = position_key(vertex);
+                let mut normal: glm::Vec3 = corners_at[&[key.x, key.y, key.z]].iter()
+                    .filter(|&&(other, _)| other == t || glm::dot(&face_normals[t], &face_normals[other]) >= threshold)
+                    .map(|&(other, weight)| face_normals[other] * weight)
+                    .sum();
+                normal = if glm::length(&normal) > 0.0 {
+                    glm::normalize(&normal)
+                } else if glm::length(&face_normals[t]) > 0.0 {
+                    face_normals[t]
+                } else {
+                    glm::vec3(0.0, 1.0, 0.0) // Nothing to go by
+                };
+
+                let bits = normal.map(|x| x.to_bits());
+                let index = *new_vertex.entry((vertex, [bits.x, bits.y, bits.z])).or_insert_with(|| {
+                    old_vertices.push(vertex);
+                    normals.extend_from_slice(normal.as_slice());
+                    old_vertices.len() as u32 - 1
+                });
+                indices.push(index);
+            }
+        }
+
+        *self = self.rebuilt(&old_vertices, indices);
+        self.normals = normals;
+    }
+
+    // Tangents following the conventions of MikkTSpace: four per vertex, the direction of
+    // increasing u made perpendicular to the normal, and in w the sign to multiply
+    // cross(normal, tangent) with to get the bitangent. Needs normals and uvs. Unlike MikkTSpace
+    // this doesn't split vertices whose triangles disagree on the sign, which only matters for
+    // mirrored uvs.
+    #[allow(dead_code)]
+    pub fn compute_tangents(&self) -> Result<Vec<f32>, String> {
+        let vertex_count = self.vertex_count();
+        if self.normals.len() != 3 * vertex_count || self.uvs.len() != 2 * vertex_count {
+            return Err("Computing tangents needs normals and uvs".to_string());
+        }
+        let uv = |v: u32| glm::vec2(self.uvs[2 * v as usize], self.uvs[2 * v as usize + 1]);
+
+        let mut tangents = vec![glm::Vec3::zeros(); vertex_count];
+        let mut bitangents = vec![glm::Vec3::zeros(); vertex_count];
+        for triangle in self.indices.chunks_exact(3) {
+            let [a, b, c] = [triangle[0], triangle[1], triangle[2]];
+            let (edge1, edge2) = (self.position(b) - self.position(a), self.position(c) - self.position(a));
+            let (duv1, duv2) = (uv(b) - uv(a), uv(c) - uv(a));
+            let determinant = duv1.x * duv2.y - duv2.x * duv1.y;
+            if determinant.abs() < f32::EPSILON {
+                continue; // The uvs are degenerate here
+            }
+            let tangent = (edge1 * duv2.y - edge2 * duv1.y) / determinant;
+            let bitangent = (edge2 * duv1.x - edge1 * duv2.x) / determinant;
+            for (vertex, previous, next) in [(a, c, b), (b, a, c), (c, b, a)] {
+                let angle = glm::angle(&(self.position(previous) - self.position(vertex)), &(self.position(next) - self.position(vertex)));
+                let weight = if angle.is_finite() { angle } else { 0.0 };
+                tangents[vertex as usize] += tangent * weight;
+                bitangents[vertex as usize] += bitangent * weight;
+            }
+        }
+
+        let mut result = Vec::with_capacity(4 * vertex_count);
+        for v in 0..vertex_count {
+            let normal = glm::make_vec3(&self.normals[3 * v..3 * v + 3]);
+            // Gram-Schmidt, falling back on any direction perpendicular to the normal
+            let mut tangent = tangents[v] - normal * glm::dot(&normal, &tangents[v]);
+            if glm::length(&tangent) < f32::EPSILON {
+                let axis = if normal.x.abs() < 0.9 { glm::vec3(1.0, 0.0, 0.0) } else { glm::vec3(0.0, 1.0, 0.0) };
+                tangent = glm::cross(&normal, &axis);
+            }
+            let tangent = glm::normalize(&tangent);
+            let sign = if glm::dot(&glm::cross(&normal, &tangent), &bitangents[v]) < 0.0 { -1.0 } else { 1.0 };
+            result.extend_from_slice(&[tangent.x, tangent.y, tangent.z, sign]);
+        }
+        Ok(result)
+    }
+
+    // Merge vertices whose attributes all round to the same multiples of epsilon. Returns how many
+    // vertices went away.
+    #[allow(dead_code)]
+    pub fn weld(&mut self, epsilon: f32) -> usize {
+        let vertex_count = self.vertex_count();
+        let mut welded: HashMap<Vec<i64>, u32> = HashMap::new();
+        let mut old_vertices: Vec<u32> = vec![];
+        let remap: Vec<u32> = (0..vertex_count).map(|v| {
+            let key: Vec<i64> = self.attributes().iter()
+                .flat_map(|&(attribute, components)| attribute[components * v..components * (v + 1)].iter())
+                .map(|&x| (x / epsilon).round() as i64)
+                .collect();
+            *welded.entry(key).or_insert_with(|| {
+                old_vertices.push(v as u32);
+                old_vertices.len() as u32 - 1
+            })
+        }).collect();
+
+        let indices = self.indices.iter().map(|&i| remap[i as usize]).collect();
+        *self = self.rebuilt(&old_vertices, indices);
+        vertex_count - old_vertices.len()
+    }
+
+    // Drop triangles using a vertex twice or with no area to speak of. Returns how many went
+    // away. The vertices are left alone, even if nothing uses them anymore.
+    #[allow(dead_code)]
+    pub fn remove_degenerate_triangles(&mut self) -> usize {
+        let triangle_count = self.triangle_count();
+        let indices: Vec<u32> = self.indices.chunks_exact(3)
+            .filter(|t| {
+                if t[0] == t[1] || t[1] == t[2] || t[2] == t[0] {
+                    return false;
+                }
+                let (edge1, edge2) = (self.position(t[1]) - self.position(t[0]), self.position(t[2]) - self.position(t[0]));
+                // The sine of the angle between the edges, so that it doesn't depend on scale
+                glm::length(&glm::cross(&edge1, &edge2)) > f32::EPSILON * glm::length(&edge1) * glm::length(&edge2)
+            })
+            .flatten()
+            .copied()
+            .collect();
+        self.index_count = indices.len() as i32;
+        self.indices = indices;
+        triangle_count - self.triangle_count()
+    }
+
+    // Check that the attributes have one entry per vertex, or none, and that the indices make up
+    // whole triangles of vertices which exist
+    pub fn validate(&self) -> Result<(), String> {
+        if self.vertices.len() % 3 != 0 {
+            return Err(format!("{} position components isn't a whole number of vertices", self.vertices.len()));
+        }
+        let vertex_count = self.vertex_count();
+        for (name, attribute, components) in [("normals", &self.normals, 3), ("colors", &self.colors, 4), ("uvs", &self.uvs, 2)] {
+            if !attribute.is_empty() && attribute.len() != components * vertex_count {
+                return Err(format!("{} {} components for {} vertices", attribute.len(), name, vertex_count));
+            }
+        }
+        if self.indices.len() % 3 != 0 {
+            return Err(format!("{} indices isn't a whole number of triangles", self.indices.len()));
+        }
+        if self.index_count as usize != self.indices.len() {
+            return Err(format!("The index count is {}, but there are {} indices", self.index_count, self.indices.len()));
+        }
+        if let Some(index) = self.indices.iter().find(|&&i| i as usize >= vertex_count) {
+            return Err(format!("Index {} is out of bounds for {} vertices", index, vertex_count));
+        }
+        if self.vertices.iter().any(|x| !x.is_finite()) {
+            return Err("Positions aren't all finite".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every corner of every triangle a vertex of its own, as some exporters write them
+    fn unindexed(mesh: &Mesh) -> Mesh {
+        let indices = (0..mesh.indices.len() as u32).collect();
+        mesh.rebuilt(&mesh.indices, indices)
+    }
+
+    #[test]
+    fn welding_merges_vertices_with_the_same_attributes() {
+        let cube = Mesh::cube(1.0, 1, [1.0; 4]);
+        let mut positions_only = unindexed(&cube);
+        positions_only.normals.clear();
+        positions_only.uvs.clear();
+        assert_eq!(positions_only.weld(1e-5), 28);
+        assert_eq!(positions_only.vertex_count(), 8);
+        assert_eq!(positions_only.triangle_count(), 12);
+        assert!(positions_only.validate().is_ok());
+
+        // Corners of different faces have different normals, so they stay apart
+        let mut with_normals = unindexed(&cube);
+        assert_eq!(with_normals.weld(1e-5), 36 - 24);
+        assert_eq!(with_normals.aabb, cube.aabb);
+    }
+
+    #[test]
+    fn degenerate_triangles_are_dropped() {
+        let vertices = vec![
+            0.0, 0.0, 0.0,
+            1.0, 0.0, 0.0,
+            0.0, 1.0, 0.0,
+            2.0, 0.0, 0.0,
+            1e-4, 0.0, 0.0,
+            0.0, 1e-4, 0.0,
+        ];
+        let indices = vec![
+            0, 1, 2, // Fine
+            0, 1, 1, // A vertex twice
+            0, 1, 3, // In a line
+            0, 4, 5, // Small, but not degenerate
+        ];
+        let mut mesh = Mesh::new(vertices, vec![], vec![], indices);
+        assert_eq!(mesh.remove_degenerate_triangles(), 2);
+        assert_eq!(mesh.indices, vec![0, 1, 2, 0, 4, 5]);
+        assert_eq!(mesh.vertex_count(), 6);
+        assert!(mesh.validate().is_ok());
+    }
+
+    #[test]
+    fn tangents_point_along_u() {
+        let plane = Mesh::plane(2.0, 2.0, 2, 2, [1.0; 4]);
+        let tangents = plane.compute_tangents().unwrap();
+        assert_eq!(tangents.len(), 4 * plane.vertex_count());
+        // u grows along x and v along z, so the bitangent is the opposite of cross(normal, tangent)
+        for tangent in tangents.chunks_exact(4) {
+            assert!(glm::distance(&glm::make_vec3(&tangent[..3]), &glm::vec3(1.0, 0.0, 0.0)) < 1e-6, "{:?}", tangent);
+            assert_eq!(tangent[3], -1.0);
+        }
+
+        let mut without_uvs = plane;
+        without_uvs.uvs.clear();
+        assert!(without_uvs.compute_tangents().is_err());
+    }
+}
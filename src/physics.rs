@@ -0,0 +1,585 @@
+extern crate nalgebra_glm as glm;
+
+use std::sync::Arc;
+
+use crate::bounds::Aabb;
+use crate::mesh::{Ground, Mesh};
+use crate::scene_graph::SceneNode;
+
+// Rigid bodies pushed around by forces, bumping into each other and resting on the ground. Kept
+// simple: collisions change how fast bodies move, but not how fast they turn, and bodies turn by
+// changing the rotation angles of their node directly. Bodies have to be children of the root,
+// which scene files make sure of, so that the transforms of their nodes are in world coordinates.
+//
+// Every step goes:
+//   1. Forces, gravity and drag change the velocities, which move the nodes
+//   2. Broadphase: sweep and prune over the world bounds of the bodies finds the pairs which
+//      might touch
+//   3. Narrowphase: the shapes of those pairs are tested for contact
+//   4. Contacts push the bodies apart, both their velocities and their positions
+//   5. The same for bodies sinking into the ground
+
+pub const GRAVITY: glm::Vec3 = glm::Vec3::new(0.0, -9.81, 0.0);
+
+// How many times the contacts are gone through every step. More settles stacks better.
+const SOLVER_ITERATIONS: usize = 4;
+// How far bodies may sink into each other before they are pushed apart, which keeps resting
+// contacts from jittering. Anything deeper is undone right away, as undoing only part of it would
+// leave resting bodies sunk deeper than this by what gravity pulls them in every step.
+const PENETRATION_SLOP: f32 = 0.01;
+
+// Triangles in the coordinates of the mesh they were made from
+pub struct TriangleMesh {
+    pub triangles : Vec<[glm::Vec3; 3]>,
+    pub bounds    : Aabb,
+}
+
+impl TriangleMesh {
+    pub fn new(mesh: &Mesh) -> Self {
+        let position = |i: u32| glm::vec3(mesh.vertices[i as usize * 3], mesh.vertices[i as usize * 3 + 1], mesh.vertices[i as usize * 3 + 2]);
+        TriangleMesh {
+            triangles : mesh.indices.chunks_exact(3).map(|t| [position(t[0]), position(t[1]), position(t[2])]).collect(),
+            bounds    : mesh.aabb,
+        }
+    }
+}
+
+// In the coordinates of the node
+#[derive(Clone)]
+pub enum Shape {
+    Sphere { center: glm::Vec3, radius: f32 },
+    Box    { center: glm::Vec3, half_extents: glm::Vec3 },
+    Mesh   (Arc<TriangleMesh>), // Collides with other meshes as its bounding box
+}
+
+#[derive(Clone)]
+pub struct RigidBody {
+    pub shape            : Shape,
+    pub inverse_mass     : f32,       // 0 for bodies which nothing can move
+    pub velocity         : glm::Vec3, // In world units per second
+    pub angular_velocity : glm::Vec3, // How fast the rotation angles of the node change, in radians per second
+    pub drag             : f32,       // Slowing the body down by drag times the square of its speed
+    pub angular_drag     : f32,       // The fraction of its turning speed lost every second
+    pub restitution      : f32,       // How bouncy it is, from 0 to 1
+    pub friction         : f32,
+    force                : glm::Vec3,
+}
+
+impl RigidBody {
+    // A mass of 0 makes a body nothing can move
+    pub fn new(shape: Shape, mass: f32) -> Self {
+        RigidBody {
+            shape,
+            inverse_mass     : if mass > 0.0 { 1.0 / mass } else { 0.0 },
+            velocity         : glm::zero(),
+            angular_velocity : glm::zero(),
+            drag             : 0.0,
+            angular_drag     : 0.0,
+            restitution      : 0.2,
+            friction         : 0.5,
+            force            : glm::zero(),
+        }
+    }
+
+    pub fn mass(&self) -> f32 {
+        if self.inverse_mass > 0.0 { 1.0 / self.inverse_mass } else { 0.0 }
+    }
+
+    pub fn is_fixed(&self) -> bool {
+        self.inverse_mass == 0.0
+    }
+
+    // In world coordinates, acting until the end of the next step
+    pub fn add_force(&mut self, force: &glm::Vec3) {
+        self.force += force;
+    }
+
+    // A sudden change in momentum, like a kick
+    #[allow(dead_code)]
+    pub fn add_impulse(&mut self, impulse: &glm::Vec3) {
+        self.velocity += impulse * self.inverse_mass;
+    }
+}
+
+// Where two bodies touch. The normal points from the first to the second, which is None for the
+// ground.
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug)]
+pub struct Contact {
+    pub first  : usize,
+    pub second : Option<usize>,
+    pub point  : glm::Vec3,
+    pub normal : glm::Vec3,
+    pub depth  : f32,
+}
+
+// Moves the bodies on by one step, returning where they touched. The bodies belong to the nodes
+// they are paired with.
+pub fn step(bodies: &mut [(*mut SceneNode, RigidBody)], gravity: &glm::Vec3, timestep: f32, ground: &dyn Ground) -> Vec<Contact> {
+    integrate(bodies, gravity, timestep);
+
+    let shapes: Vec<WorldShape> = bodies.iter().map(|(node, body)| WorldShape::new(&body.shape, &unsafe { &**node }.local_transform())).collect();
+    let mut contacts: Vec<Contact> = vec![];
+    for (i, j) in sweep_and_prune(&shapes) {
+        if bodies[i].1.is_fixed() && bodies[j].1.is_fixed() {
+            continue;
+        }
+        if let Some((normal, depth, point)) = collide(&shapes[i], &shapes[j]) {
+            contacts.push(Contact { first: i, second: Some(j), point, normal, depth });
+        }
+    }
+    for (i, shape) in shapes.iter().enumerate() {
+        if !bodies[i].1.is_fixed() {
+            if let Some((normal, depth, point)) = ground_contact(shape, ground) {
+                contacts.push(Contact { first: i, second: None, point, normal: -normal, depth });
+            }
+        }
+    }
+
+    for iteration in 0..SOLVER_ITERATIONS {
+        for contact in &contacts {
+            resolve(bodies, contact, iteration == 0);
+        }
+    }
+    contacts
+}
+
+fn integrate(bodies: &mut [(*mut SceneNode, RigidBody)], gravity: &glm::Vec3, timestep: f32) {
+    for (node, body) in bodies.iter_mut() {
+        let force = std::mem::replace(&mut body.force, glm::zero());
+        if body.is_fixed() {
+            continue;
+        }
+        let drag = -body.velocity * glm::length(&body.velocity) * body.drag;
+        body.velocity += ((force + drag) * body.inverse_mass + gravity) * timestep;
+        body.angular_velocity *= (1.0 - body.angular_drag * timestep).max(0.0);
+
+        let node = unsafe { &mut **node };
+        node.position += body.velocity * timestep;
+        node.rotation += body.angular_velocity * timestep;
+    }
+}
+
+// Pushes the bodies apart along the normal, and slows their sliding along each other. Positions
+// are only corrected once, the velocities every iteration.
+fn resolve(bodies: &mut [(*mut SceneNode, RigidBody)], contact: &Contact, correct_positions: bool) {
+    let (first, second) = (&bodies[contact.first].1, contact.second.map(|j| &bodies[j].1));
+    let first_inverse_mass = first.inverse_mass;
+    let second_inverse_mass = second.map_or(0.0, |body| body.inverse_mass);
+    let total_inverse_mass = first_inverse_mass + second_inverse_mass;
+    if total_inverse_mass == 0.0 {
+        return;
+    }
+    let restitution = second.map_or(first.restitution, |body| first.restitution.max(body.restitution));
+    let friction = second.map_or(first.friction, |body| (first.friction * body.friction).sqrt());
+    let relative_velocity = second.map_or(glm::zero(), |body| body.velocity) - first.velocity;
+
+    let normal_speed = glm::dot(&relative_velocity, &contact.normal);
+    let mut impulse = glm::Vec3::zeros();
+    if normal_speed < 0.0 {
+        let normal_impulse = -(1.0 + restitution) * normal_speed / total_inverse_mass;
+        impulse += contact.normal * normal_impulse;
+
+        let tangent_velocity = relative_velocity - contact.normal * normal_speed;
+        let tangent_speed = glm::length(&tangent_velocity);
+        if tangent_speed > f32::EPSILON {
+            let friction_impulse = (tangent_speed / total_inverse_mass).min(friction * normal_impulse);
+            impulse -= tangent_velocity / tangent_speed * friction_impulse;
+        }
+    }
+
+    let correction = if correct_positions {
+        contact.normal * ((contact.depth - PENETRATION_SLOP).max(0.0) / total_inverse_mass)
+    } else {
+        glm::zero()
+    };
+
+    let (node, body) = &mut bodies[contact.first];
+    body.velocity -= impulse * first_inverse_mass;
+    unsafe { &mut **node }.position -= correction * first_inverse_mass;
+    if let Some(j) = contact.second {
+        let (node, body) = &mut bodies[j];
+        body.velocity += impulse * second_inverse_mass;
+        unsafe { &mut **node }.position += correction * second_inverse_mass;
+    }
+}
+
+// An oriented box
+#[derive(Clone, Copy)]
+struct Obb {
+    center       : glm::Vec3,
+    axes         : [glm::Vec3; 3], // Of unit length
+    half_extents : glm::Vec3,
+}
+
+impl Obb {
+    fn new(center: &glm::Vec3, half_extents: &glm::Vec3, transform: &glm::Mat4) -> Self {
+        let mut axes = [glm::Vec3::zeros(); 3];
+        let mut scaled = *half_extents;
+        for (i, axis) in axes.iter_mut().enumerate() {
+            let column = transform.column(i).xyz();
+            let length = glm::length(&column);
+            *axis = if length > 0.0 { column / length } else { glm::Vec3::zeros() };
+            scaled[i] *= length;
+        }
+        Obb { center: (transform * glm::vec4(center.x, center.y, center.z, 1.0)).xyz(), axes, half_extents: scaled }
+    }
+
+    fn corners(&self) -> [glm::Vec3; 8] {
+        let mut corners = [self.center; 8];
+        for (i, corner) in corners.iter_mut().enumerate() {
+            for axis in 0..3 {
+                let sign = if i & (1 << axis) != 0 { 1.0 } else { -1.0 };
+                *corner += self.axes[axis] * self.half_extents[axis] * sign;
+            }
+        }
+        corners
+    }
+
+    fn aabb(&self) -> Aabb {
+        let extents = glm::vec3(
+            (0..3).map(|j| self.axes[j].x.abs() * self.half_extents[j]).sum(),
+            (0..3).map(|j| self.axes[j].y.abs() * self.half_extents[j]).sum(),
+            (0..3).map(|j| self.axes[j].z.abs() * self.half_extents[j]).sum(),
+        );
+        Aabb { min: self.center - extents, max: self.center + extents }
+    }
+}
+
+// A shape moved to where its node is
+enum WorldShape {
+    Sphere { center: glm::Vec3, radius: f32 },
+    Box    (Obb),
+    Mesh   { triangles: Vec<[glm::Vec3; 3]>, bounds: Obb },
+}
+
+impl WorldShape {
+    fn new(shape: &Shape, transform: &glm::Mat4) -> Self {
+        let point = |p: &glm::Vec3| (transform * glm::vec4(p.x, p.y, p.z, 1.0)).xyz();
+        match shape {
+            Shape::Sphere { center, radius } => {
+                let scale = (0..3).map(|i| glm::length(&transform.column(i).xyz())).fold(0.0, f32::max);
+                WorldShape::Sphere { center: point(center), radius: radius * scale }
+            }
+            Shape::Box { center, half_extents } => WorldShape::Box(Obb::new(center, half_extents, transform)),
+            Shape::Mesh(mesh) => WorldShape::Mesh {
+                triangles : mesh.triangles.iter().map(|t| [point(&t[0]), point(&t[1]), point(&t[2])]).collect(),
+                bounds    : Obb::new(&mesh.bounds.center(), &mesh.bounds.half_extents(), transform),
+            },
+        }
+    }
+
+    fn aabb(&self) -> Aabb {
+        match self {
+            WorldShape::Sphere { center, radius } => Aabb { min: center - glm::vec3(*radius, *radius, *radius), max: center + glm::vec3(*radius, *radius, *radius) },
+            WorldShape::Box(obb) => obb.aabb(),
+            WorldShape::Mesh { bounds, .. } => bounds.aabb(),
+        }
+    }
+
+    // The points which would touch the ground first
+    fn lowest_points(&self, up: &glm::Vec3) -> Vec<glm::Vec3> {
+        match self {
+            WorldShape::Sphere { center, radius } => vec![center - up * *radius],
+            WorldShape::Box(obb) | WorldShape::Mesh { bounds: obb, .. } => obb.corners().to_vec(),
+        }
+    }
+}
+
+// The pairs of shapes whose bounds overlap. The bounds are sorted along the X axis, so that only
+// those overlapping along it need to be compared on the others.
+fn sweep_and_prune(shapes: &[WorldShape]) -> Vec<(usize, usize)> {
+    let bounds: Vec<Aabb> = shapes.iter().map(WorldShape::aabb).collect();
+    let mut order: Vec<usize> = (0..shapes.len()).collect();
+    order.sort_by(|&a, &b| bounds[a].min.x.total_cmp(&bounds[b].min.x));
+
+    let mut pairs = vec![];
+    let mut active: Vec<usize> = vec![];
+    for &i in &order {
+        active.retain(|&j| bounds[j].max.x >= bounds[i].min.x);
+        for &j in &active {
+            let overlap = (1..3).all(|axis| bounds[i].min[axis] <= bounds[j].max[axis] && bounds[j].min[axis] <= bounds[i].max[axis]);
+            if overlap {
+                pairs.push((i.min(j), i.max(j)));
+            }
+        }
+        active.push(i);
+    }
+    pairs.sort_unstable(); // The same order every run, whatever the sort did with ties
+    pairs
+}
+
+// The normal from a to b, how deep they are in each other and a point where they touch
+fn collide(a: &WorldShape, b: &WorldShape) -> Option<(glm::Vec3, f32, glm::Vec3)> {
+    let flipped = |(normal, depth, point): (glm::Vec3, f32, glm::Vec3)| (-normal, depth, point);
+    match (a, b) {
+        (WorldShape::Sphere { center: a_center, radius: a_radius }, WorldShape::Sphere { center: b_center, radius: b_radius }) => {
+            let offset = b_center - a_center;
+            let distance = glm::length(&offset);
+            let depth = a_radius + b_radius - distance;
+            if depth <= 0.0 {
+                return None;
+            }
+            let normal = if distance > f32::EPSILON { offset / distance } else { glm::vec3(0.0, 1.0, 0.0) };
+            Some((normal, depth, a_center + normal * (a_radius - depth * 0.5)))
+        }
+        (WorldShape::Box(obb), WorldShape::Sphere { center, radius }) => box_sphere(obb, center, *radius),
+        (WorldShape::Sphere { center, radius }, WorldShape::Box(obb)) => box_sphere(obb, center, *radius).map(flipped),
+        (WorldShape::Box(a), WorldShape::Box(b)) => box_box(a, b),
+        (WorldShape::Mesh { triangles, .. }, WorldShape::Sphere { center, radius }) => mesh_sphere(triangles, center, *radius),
+        (WorldShape::Sphere { center, radius }, WorldShape::Mesh { triangles, .. }) => mesh_sphere(triangles, center, *radius).map(flipped),
+        (WorldShape::Mesh { triangles, .. }, WorldShape::Box(obb)) => mesh_box(triangles, obb),
+        (WorldShape::Box(obb), WorldShape::Mesh { triangles, .. }) => mesh_box(triangles, obb).map(flipped),
+        (WorldShape::Mesh { bounds: a, .. }, WorldShape::Mesh { bounds: b, .. }) => box_box(a, b),
+    }
+}
+
+fn box_sphere(obb: &Obb, center: &glm::Vec3, radius: f32) -> Option<(glm::Vec3, f32, glm::Vec3)> {
+    let offset = center - obb.center;
+    let local: [f32; 3] = std::array::from_fn(|i| glm::dot(&offset, &obb.axes[i]));
+    let closest = (0..3).fold(obb.center, |p, i| p + obb.axes[i] * local[i].clamp(-obb.half_extents[i], obb.half_extents[i]));
+    let outward = center - closest;
+    let distance = glm::length(&outward);
+    if distance > radius {
+        return None;
+    }
+    if distance > f32::EPSILON {
+        return Some((outward / distance, radius - distance, closest));
+    }
+    // The center is inside, so out through the closest face
+    let axis = (0..3).min_by(|&i, &j| {
+        (obb.half_extents[i] - local[i].abs()).total_cmp(&(obb.half_extents[j] - local[j].abs()))
+    }).unwrap();
+    let normal = obb.axes[axis] * local[axis].signum();
+    Some((normal, radius + obb.half_extents[axis] - local[axis].abs(), *center))
+}
+
+fn box_box(a: &Obb, b: &Obb) -> Option<(glm::Vec3, f32, glm::Vec3)> {
+    let mut axes = a.axes.to_vec();
+    axes.extend_from_slice(&b.axes);
+    for a_axis in &a.axes {
+        axes.extend(b.axes.iter().map(|b_axis| glm::cross(a_axis, b_axis)));
+    }
+    separating_axis(&a.corners(), &b.corners(), &axes)
+}
+
+fn mesh_sphere(triangles: &[[glm::Vec3; 3]], center: &glm::Vec3, radius: f32) -> Option<(glm::Vec3, f32, glm::Vec3)> {
+    let mut deepest: Option<(glm::Vec3, f32, glm::Vec3)> = None;
+    for [a, b, c] in triangles {
+        let closest = closest_point_on_triangle(center, a, b, c);
+        let outward = center - closest;
+        let distance = glm::length(&outward);
+        if distance >= radius || deepest.is_some_and(|(_, depth, _)| depth >= radius - distance) {
+            continue;
+        }
+        let normal = if distance > f32::EPSILON { outward / distance } else { glm::normalize(&glm::cross(&(b - a), &(c - a))) };
+        deepest = Some((normal, radius - distance, closest));
+    }
+    deepest
+}
+
+fn mesh_box(triangles: &[[glm::Vec3; 3]], obb: &Obb) -> Option<(glm::Vec3, f32, glm::Vec3)> {
+    let bounds = obb.aabb();
+    let corners = obb.corners();
+    let mut deepest: Option<(glm::Vec3, f32, glm::Vec3)> = None;
+    for triangle in triangles {
+        let outside = (0..3).any(|axis| {
+            triangle.iter().all(|p| p[axis] < bounds.min[axis]) || triangle.iter().all(|p| p[axis] > bounds.max[axis])
+        });
+        if outside {
+            continue;
+        }
+        let edges = [triangle[1] - triangle[0], triangle[2] - triangle[1], triangle[0] - triangle[2]];
+        let mut axes = obb.axes.to_vec();
+        axes.push(glm::cross(&edges[0], &edges[1]));
+        for axis in &obb.axes {
+            axes.extend(edges.iter().map(|edge| glm::cross(axis, edge)));
+        }
+        if let Some(contact) = separating_axis(triangle, &corners, &axes) {
+            if deepest.is_none_or(|(_, depth, _)| contact.1 > depth) {
+                deepest = Some(contact);
+            }
+        }
+    }
+    deepest
+}
+
+// Two convex shapes given by their corners overlap unless some axis separates them. Of those
+// that don't, the one they overlap the least along is where they are pushed apart. The point is
+// halfway into the overlap, below the corner of b deepest in a.
+fn separating_axis(a: &[glm::Vec3], b: &[glm::Vec3], axes: &[glm::Vec3]) -> Option<(glm::Vec3, f32, glm::Vec3)> {
+    let project = |points: &[glm::Vec3], axis: &glm::Vec3| {
+        points.iter().fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), p| {
+            let d = glm::dot(p, axis);
+            (min.min(d), max.max(d))
+        })
+    };
+    let mut best: Option<(glm::Vec3, f32)> = None;
+    for axis in axes {
+        let length = glm::length(axis);
+        if length < 1e-6 {
+            continue; // From the cross product of parallel edges
+        }
+        let axis = axis / length;
+        let (a_min, a_max) = project(a, &axis);
+        let (b_min, b_max) = project(b, &axis);
+        let depth = (a_max - b_min).min(b_max - a_min);
+        if depth <= 0.0 {
+            return None;
+        }
+        if best.is_none_or(|(_, best_depth)| depth < best_depth) {
+            let normal = if a_min + a_max <= b_min + b_max { axis } else { -axis };
+            best = Some((normal, depth));
+        }
+    }
+    let (normal, depth) = best?;
+    let deepest = b.iter().min_by(|p, q| glm::dot(p, &normal).total_cmp(&glm::dot(q, &normal)))?;
+    Some((normal, depth, deepest + normal * (depth * 0.5)))
+}
+
+// From Real-Time Collision Detection by Christer Ericson, going by which region of the triangle
+// the point is closest to
+fn closest_point_on_triangle(p: &glm::Vec3, a: &glm::Vec3, b: &glm::Vec3, c: &glm::Vec3) -> glm::Vec3 {
+    let (ab, ac, ap) = (b - a, c - a, p - a);
+    let (d1, d2) = (glm::dot(&ab, &ap), glm::dot(&ac, &ap));
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return *a;
+    }
+    let bp = p - b;
+    let (d3, d4) = (glm::dot(&ab, &bp), glm::dot(&ac, &bp));
+    if d3 >= 0.0 && d4 <= d3 {
+        return *b;
+    }
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab * (d1 / (d1 - d3));
+    }
+    let cp = p - c;
+    let (d5, d6) = (glm::dot(&ab, &cp), glm::dot(&ac, &cp));
+    if d6 >= 0.0 && d5 <= d6 {
+        return *c;
+    }
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac * (d2 / (d2 - d6));
+    }
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && d4 - d3 >= 0.0 && d5 - d6 >= 0.0 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+    let denominator = 1.0 / (va + vb + vc);
+    a + ab * (vb * denominator) + ac * (vc * denominator)
+}
+
+// The deepest of the lowest points of a shape below the ground, pushed out along the normal of
+// the ground there. Nothing happens where there is no ground.
+fn ground_contact(shape: &WorldShape, ground: &dyn Ground) -> Option<(glm::Vec3, f32, glm::Vec3)> {
+    let mut deepest: Option<(glm::Vec3, f32, glm::Vec3)> = None;
+    let up = glm::vec3(0.0, 1.0, 0.0);
+    for point in shape.lowest_points(&up) {
+        let Some(height) = ground.height_at(point.x, point.z) else { continue };
+        let normal = ground.normal_at(point.x, point.z).unwrap_or(up);
+        let depth = (height - point.y) * normal.y;
+        if depth > 0.0 && deepest.is_none_or(|(_, deepest_depth, _)| depth > deepest_depth) {
+            deepest = Some((normal, depth, point));
+        }
+    }
+    deepest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Flat;
+
+    impl Ground for Flat {
+        fn height_at(&self, _x: f32, _z: f32) -> Option<f32> {
+            Some(0.0)
+        }
+
+        fn normal_at(&self, _x: f32, _z: f32) -> Option<glm::Vec3> {
+            Some(glm::vec3(0.0, 1.0, 0.0))
+        }
+    }
+
+    fn sphere(x: f32, y: f32, radius: f32) -> WorldShape {
+        WorldShape::Sphere { center: glm::vec3(x, y, 0.0), radius }
+    }
+
+    fn cube(center: glm::Vec3, yaw: f32) -> WorldShape {
+        let transform = glm::rotate_y(&glm::translation(&center), yaw);
+        WorldShape::Box(Obb::new(&glm::zero(), &glm::vec3(1.0, 1.0, 1.0), &transform))
+    }
+
+    fn assert_close(a: &glm::Vec3, b: &glm::Vec3) {
+        assert!(glm::distance(a, b) < 1e-4, "{:?} is not {:?}", a, b);
+    }
+
+    #[test]
+    fn sweep_and_prune_pairs_overlapping_bounds() {
+        let shapes = [
+            sphere(0.0, 0.0, 1.0),
+            sphere(10.0, 0.0, 1.0),
+            sphere(1.5, 0.0, 1.0),
+            sphere(0.5, 5.0, 1.0), // Overlaps the first along X only
+        ];
+        assert_eq!(sweep_and_prune(&shapes), vec![(0, 2)]);
+    }
+
+    #[test]
+    fn spheres_touch_along_the_line_between_them() {
+        let (normal, depth, _) = collide(&sphere(0.0, 0.0, 1.0), &sphere(1.5, 0.0, 1.0)).unwrap();
+        assert_close(&normal, &glm::vec3(1.0, 0.0, 0.0));
+        assert!((depth - 0.5).abs() < 1e-5);
+        assert!(collide(&sphere(0.0, 0.0, 1.0), &sphere(2.5, 0.0, 1.0)).is_none());
+    }
+
+    #[test]
+    fn sphere_on_a_box_is_pushed_out_of_the_face() {
+        let (normal, depth, point) = collide(&cube(glm::zero(), 0.0), &sphere(0.0, 1.5, 1.0)).unwrap();
+        assert_close(&normal, &glm::vec3(0.0, 1.0, 0.0));
+        assert!((depth - 0.5).abs() < 1e-5);
+        assert_close(&point, &glm::vec3(0.0, 1.0, 0.0));
+        // The other way around, the normal still points from the first to the second
+        let (normal, _, _) = collide(&sphere(0.0, 1.5, 1.0), &cube(glm::zero(), 0.0)).unwrap();
+        assert_close(&normal, &glm::vec3(0.0, -1.0, 0.0));
+        assert!(collide(&cube(glm::zero(), 0.0), &sphere(0.0, 2.5, 1.0)).is_none());
+    }
+
+    #[test]
+    fn boxes_are_pushed_apart_along_the_least_overlap() {
+        let (normal, depth, _) = collide(&cube(glm::zero(), 0.0), &cube(glm::vec3(0.0, 1.8, 0.3), 0.3)).unwrap();
+        assert_close(&normal, &glm::vec3(0.0, 1.0, 0.0));
+        assert!((depth - 0.2).abs() < 1e-4);
+        assert!(collide(&cube(glm::zero(), 0.0), &cube(glm::vec3(0.0, 2.1, 0.0), 0.3)).is_none());
+    }
+
+    #[test]
+    fn dropped_body_comes_to_rest_on_the_ground() {
+        let mut node = SceneNode::new();
+        node.position = glm::vec3(0.0, 3.0, 0.0);
+        let shape = Shape::Sphere { center: glm::zero(), radius: 1.0 };
+        let mut bodies = [(&mut **node as *mut SceneNode, RigidBody::new(shape, 1.0))];
+        for _ in 0..300 {
+            step(&mut bodies, &GRAVITY, 1.0 / 60.0, &Flat);
+            assert!(node.position.y > 1.0 - 0.1, "fell through the ground to {}", node.position.y);
+        }
+        let sunk = 1.0 - node.position.y;
+        assert!((0.0..=PENETRATION_SLOP + 1e-5).contains(&sunk), "sunk {} into the ground", sunk);
+        assert!(glm::length(&bodies[0].1.velocity) < 0.2);
+    }
+
+    #[test]
+    fn fixed_bodies_never_touch() {
+        let (mut a, mut b) = (SceneNode::new(), SceneNode::new());
+        b.position = glm::vec3(0.5, 0.0, 0.0);
+        let shape = Shape::Sphere { center: glm::zero(), radius: 1.0 };
+        let mut bodies = [
+            (&mut **a as *mut SceneNode, RigidBody::new(shape.clone(), 0.0)),
+            (&mut **b as *mut SceneNode, RigidBody::new(shape, 0.0)),
+        ];
+        assert!(step(&mut bodies, &GRAVITY, 1.0 / 60.0, &Flat).is_empty());
+        assert_eq!(b.position, glm::vec3(0.5, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,266 @@
+extern crate nalgebra_glm as glm;
+
+use crate::bounds::Aabb;
+use crate::mesh::Mesh;
+use crate::renderer::DrawCommand;
+use crate::scene_graph::SceneNode;
+
+// Finding what is under the cursor, by casting a ray from the camera through it. The ray is first
+// tested against the world bounds of every subtree, and then against the triangles of the meshes
+// inside of them. The world transforms and bounds have to be kept up to date with
+// SceneNode::update_world.
+
+// Triangles whose normal is this close to perpendicular to the ray are considered missed
+const PARALLEL_EPSILON: f32 = 1e-8;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Ray {
+    pub origin    : glm::Vec3,
+    pub direction : glm::Vec3, // Normalized
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PickHit {
+    pub node        : *mut SceneNode,
+    pub distance    : f32,       // From the origin of the ray, in world units
+    pub triangle    : usize,     // Which triangle of the node's mesh was hit
+    pub barycentric : glm::Vec3, // The weights of the triangle's three vertices at the hit
+}
+
+impl Ray {
+    // The ray through a point on the screen, in pixels with the origin at the top left like window
+    // coordinates, starting on the near plane
+    pub fn from_screen(x: f32, y: f32, width: u32, height: u32, view_projection: &glm::Mat4) -> Ray {
+        let inverse = glm::inverse(view_projection);
+        let ndc = glm::vec2(2.0 * x / width as f32 - 1.0, 1.0 - 2.0 * y / height as f32);
+        let unproject = |z: f32| {
+            let p = inverse * glm::vec4(ndc.x, ndc.y, z, 1.0);
+            p.xyz() / p.w
+        };
+        let (near, far) = (unproject(-1.0), unproject(1.0));
+        Ray { origin: near, direction: glm::normalize(&(far - near)) }
+    }
+
+    pub fn at(&self, distance: f32) -> glm::Vec3 {
+        self.origin + self.direction * distance
+    }
+
+    // The distance at which the ray enters the box, or 0 if it starts inside of it. The slab
+    // test: the ray is inside the box where it is between all three pairs of planes at once.
+    pub fn intersect_aabb(&self, aabb: &Aabb) -> Option<f32> {
+        let (mut enter, mut exit) = (0.0f32, f32::INFINITY);
+        for i in 0..3 {
+            let inverse = 1.0 / self.direction[i];
+            let t0 = (aabb.min[i] - self.origin[i]) * inverse;
+            let t1 = (aabb.max[i] - self.origin[i]) * inverse;
+            // NaN from 0 * infinity means the ray lies in one of the planes, which counts as inside
+            enter = enter.max(t0.min(t1));
+            exit = exit.min(t0.max(t1));
+        }
+        if enter <= exit { Some(enter) } else { None }
+    }
+
+    // Möller–Trumbore. Returns the distance along the ray, and the weights of the second and
+    // third vertices, the first one having the rest. Both sides of the triangle are hit.
+    pub fn intersect_triangle(&self, v0: &glm::Vec3, v1: &glm::Vec3, v2: &glm::Vec3) -> Option<(f32, f32, f32)> {
+        let edge1 = v1 - v0;
+        let edge2 = v2 - v0;
+        let p = glm::cross(&self.direction, &edge2);
+        let determinant = glm::dot(&edge1, &p);
+        if determinant.abs() < PARALLEL_EPSILON {
+            return None;
+        }
+        let inverse_determinant = 1.0 / determinant;
+
+        let to_origin = self.origin - v0;
+        let u = glm::dot(&to_origin, &p) * inverse_determinant;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = glm::cross(&to_origin, &edge1);
+        let v = glm::dot(&self.direction, &q) * inverse_determinant;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let t = glm::dot(&edge2, &q) * inverse_determinant;
+        if t >= 0.0 { Some((t, u, v)) } else { None }
+    }
+
+    // The same ray in another coordinate system. The direction isn't normalized again, so that
+    // distances along the transformed ray are the same as along this one.
+    fn transformed(&self, matrix: &glm::Mat4) -> Ray {
+        let origin = matrix * glm::vec4(self.origin.x, self.origin.y, self.origin.z, 1.0);
+        let direction = matrix * glm::vec4(self.direction.x, self.direction.y, self.direction.z, 0.0);
+        Ray { origin: origin.xyz(), direction: direction.xyz() }
+    }
+}
+
+// The closest triangle of a mesh hit by a ray in its own coordinates
+fn intersect_mesh(ray: &Ray, mesh: &Mesh, closest_so_far: f32) -> Option<(f32, usize, glm::Vec3)> {
+    let vertex = |i: u32| {
+        let i = i as usize;
+        glm::vec3(mesh.vertices[3*i], mesh.vertices[3*i + 1], mesh.vertices[3*i + 2])
+    };
+    let mut closest = None;
+    let mut closest_distance = closest_so_far;
+    for (triangle, indices) in mesh.indices.chunks_exact(3).enumerate() {
+        if let Some((t, u, v)) = ray.intersect_triangle(&vertex(indices[0]), &vertex(indices[1]), &vertex(indices[2])) {
+            if t < closest_distance {
+                closest_distance = t;
+                closest = Some((t, triangle, glm::vec3(1.0 - u - v, u, v)));
+            }
+        }
+    }
+    closest
+}
+
+fn pick_subtree(node: *mut SceneNode, ray: &Ray, meshes: &[Mesh], closest: &mut Option<PickHit>) {
+    let node_ref = unsafe { &*node };
+    let closest_distance = closest.map_or(f32::INFINITY, |hit| hit.distance);
+
+    if let Some(bounds) = &node_ref.world_bounds {
+        match ray.intersect_aabb(bounds) {
+            Some(distance) if distance < closest_distance => { }
+            _ => return,
+        }
+    }
+
+    // Always against the most detailed mesh, whichever level of detail is being drawn
+    let mesh = node_ref.vao_id.checked_sub(1).and_then(|i| meshes.get(i as usize));
+    if let (Some(mesh), true) = (mesh, node_ref.index_count > 0) {
+        let local_ray = ray.transformed(&glm::inverse(&node_ref.world_transform));
+        let in_bounds = node_ref.local_bounds.is_none_or(|bounds| {
+            local_ray.intersect_aabb(&bounds).is_some_and(|distance| distance < closest_distance)
+        });
+        if in_bounds {
+            if let Some((distance, triangle, barycentric)) = intersect_mesh(&local_ray, mesh, closest_distance) {
+                *closest = Some(PickHit { node, distance, triangle, barycentric });
+            }
+        }
+    }
+
+    for &child in &node_ref.children {
+        pick_subtree(child, ray, meshes, closest);
+    }
+}
+
+// The closest node hit by the ray, if any. The meshes are the ones uploaded to the renderer,
+// indexed by mesh id - 1.
+pub fn pick(scene: &mut SceneNode, ray: &Ray, meshes: &[Mesh]) -> Option<PickHit> {
+    let mut closest = None;
+    pick_subtree(scene, ray, meshes, &mut closest);
+    closest
+}
+
+// Every node with something to draw, along with how it is drawn right now. Used for picking on
+// the GPU, by drawing each of them in their own color.
+pub fn pickable_draws(scene: &mut SceneNode) -> Vec<(*mut SceneNode, DrawCommand)> {
+    fn collect(node: *mut SceneNode, draws: &mut Vec<(*mut SceneNode, DrawCommand)>) {
+        let node_ref = unsafe { &*node };
+        let (mesh_id, index_count) = node_ref.drawn_mesh();
+        if index_count > 0 {
+            draws.push((node, DrawCommand {
+                mesh_id,
+                index_count,
+                material  : node_ref.material,
+                transform : node_ref.world_transform,
+            }));
+        }
+        for &child in &node_ref.children {
+            collect(child, draws);
+        }
+    }
+    let mut draws = vec![];
+    collect(scene, &mut draws);
+    draws
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene_graph::Node;
+
+    // A triangle facing +z around the origin
+    fn triangle() -> Mesh {
+        let vertices = vec![-1.0, -1.0, 0.0, 1.0, -1.0, 0.0, 0.0, 1.0, 0.0];
+        Mesh::new(vertices, [0.0, 0.0, 1.0].repeat(3), [1.0; 12].to_vec(), vec![0, 1, 2])
+    }
+
+    fn placed(mesh: &Mesh, distance: f32) -> Node {
+        let mut node = SceneNode::from_vao(1, mesh.index_count);
+        node.local_bounds = Some(mesh.aabb);
+        node.position = glm::vec3(0.0, 0.0, -distance);
+        node
+    }
+
+    const FORWARD: Ray = Ray { origin: glm::Vec3::new(0.0, 0.0, 0.0), direction: glm::Vec3::new(0.0, 0.0, -1.0) };
+
+    #[test]
+    fn the_middle_of_the_screen_is_straight_ahead() {
+        let eye = glm::vec3(1.0, 2.0, 3.0);
+        let forward = glm::normalize(&glm::vec3(1.0, -1.0, -2.0));
+        let view = glm::look_at(&eye, &(eye + forward), &glm::vec3(0.0, 1.0, 0.0));
+        let projection = glm::perspective(4.0 / 3.0, 1.0, 0.1, 100.0);
+        let ray = Ray::from_screen(400.0, 300.0, 800, 600, &(projection * view));
+        assert!(glm::distance(&ray.direction, &forward) < 1e-4, "{:?}", ray);
+        assert!(glm::distance(&ray.origin, &(eye + forward * 0.1)) < 1e-4, "{:?}", ray);
+    }
+
+    #[test]
+    fn triangles_are_hit_with_the_weights_of_their_vertices() {
+        let (v0, v1, v2) = (glm::vec3(0.0, 0.0, 0.0), glm::vec3(1.0, 0.0, 0.0), glm::vec3(0.0, 1.0, 0.0));
+        let ray = Ray { origin: glm::vec3(0.25, 0.5, 2.0), direction: glm::vec3(0.0, 0.0, -1.0) };
+        let (t, u, v) = ray.intersect_triangle(&v0, &v1, &v2).unwrap();
+        assert!((t - 2.0).abs() < 1e-6 && (u - 0.25).abs() < 1e-6 && (v - 0.5).abs() < 1e-6, "{} {} {}", t, u, v);
+
+        let beside = Ray { origin: glm::vec3(0.75, 0.5, 2.0), ..ray };
+        let away = Ray { direction: glm::vec3(0.0, 0.0, 1.0), ..ray };
+        let along = Ray { direction: glm::vec3(1.0, 0.0, 0.0), ..ray };
+        for missing in [beside, away, along] {
+            assert_eq!(missing.intersect_triangle(&v0, &v1, &v2), None, "{:?}", missing);
+        }
+    }
+
+    #[test]
+    fn the_nearest_node_is_picked() {
+        let meshes = [triangle()];
+        let mut root = SceneNode::new();
+        // The farther one is looked at first
+        let far = placed(&meshes[0], 6.0);
+        let near = placed(&meshes[0], 3.0);
+        root.add_child(&far);
+        root.add_child(&near);
+        root.update_world(&glm::identity());
+
+        let near_pointer = &**near as *const SceneNode as *mut SceneNode;
+        let hit = pick(&mut root, &FORWARD, &meshes).unwrap();
+        assert_eq!(hit.node, near_pointer);
+        assert!((hit.distance - 3.0).abs() < 1e-5, "{}", hit.distance);
+        assert_eq!(hit.triangle, 0);
+        assert!(glm::distance(&hit.barycentric, &glm::vec3(0.25, 0.25, 0.5)) < 1e-5, "{:?}", hit.barycentric);
+
+        let aside = Ray { origin: glm::vec3(5.0, 0.0, 0.0), ..FORWARD };
+        assert!(pick(&mut root, &aside, &meshes).is_none());
+
+        // Both can be drawn for picking on the GPU, but the root has nothing to draw
+        let draws = pickable_draws(&mut root);
+        assert_eq!(draws.iter().map(|(node, _)| *node).collect::<Vec<_>>(), vec![&**far as *const SceneNode as *mut SceneNode, near_pointer]);
+        assert_eq!(draws[1].1.transform, near.world_transform);
+    }
+
+    #[test]
+    fn triangles_outside_of_the_bounds_are_never_tested() {
+        let meshes = [triangle()];
+        let mut node = placed(&meshes[0], 3.0);
+        node.update_world(&glm::identity());
+        assert!(pick(&mut node, &FORWARD, &meshes).is_some());
+
+        // Bounds which the ray misses, although the triangle is in its way, as if they were out
+        // of date. Only testing the triangle could find it.
+        node.world_bounds = Some(Aabb { min: glm::vec3(10.0, 10.0, 10.0), max: glm::vec3(11.0, 11.0, 11.0) });
+        assert!(pick(&mut node, &FORWARD, &meshes).is_none());
+        node.world_bounds = None;
+        node.local_bounds = Some(Aabb { min: glm::vec3(2.0, 2.0, -1.0), max: glm::vec3(3.0, 3.0, 1.0) });
+        assert!(pick(&mut node, &FORWARD, &meshes).is_none());
+    }
+}
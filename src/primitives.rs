@@ -0,0 +1,345 @@
+extern crate nalgebra_glm as glm;
+
+use std::collections::HashMap;
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+use crate::mesh::{Helicopter, Mesh};
+
+// Procedural shapes, for debug markers, placeholder props and tests. They are centered on the
+// origin with Y up, and have normals, uvs and a single color. Triangles face outwards,
+// counterclockwise seen from the outside.
+
+// Edges shorter than this fraction of the longest one in their triangle are taken to be points
+const COLLAPSED_EDGE: f32 = 1e-4;
+
+#[derive(Default)]
+struct Builder {
+    vertices : Vec<f32>,
+    normals  : Vec<f32>,
+    uvs      : Vec<f32>,
+    indices  : Vec<u32>,
+}
+
+impl Builder {
+    fn vertex(&mut self, position: &glm::Vec3, normal: &glm::Vec3, uv: &glm::Vec2) -> u32 {
+        self.vertices.extend_from_slice(position.as_slice());
+        self.normals.extend_from_slice(glm::normalize(normal).as_slice());
+        self.uvs.extend_from_slice(uv.as_slice());
+        (self.vertices.len() / 3 - 1) as u32
+    }
+
+    fn position(&self, vertex: u32) -> glm::Vec3 {
+        glm::make_vec3(&self.vertices[3 * vertex as usize..3 * vertex as usize + 3])
+    }
+
+    fn normal(&self, vertex: u32) -> glm::Vec3 {
+        glm::make_vec3(&self.normals[3 * vertex as usize..3 * vertex as usize + 3])
+    }
+
+    // Turned to face the way its normals point. Triangles without any area, like the ones next to
+    // the poles of a sphere, are left out. Rounding keeps the points of a pole from being exactly
+    // the same, so an edge counts as collapsed when it is tiny next to the others.
+    fn triangle(&mut self, a: u32, b: u32, c: u32) {
+        let (pa, pb, pc) = (self.position(a), self.position(b), self.position(c));
+        let edges = [glm::distance(&pa, &pb), glm::distance(&pb, &pc), glm::distance(&pc, &pa)];
+        let longest = edges.iter().copied().fold(0.0, f32::max);
+        let face = glm::cross(&(pb - pa), &(pc - pa));
+        if edges.iter().any(|&edge| edge <= COLLAPSED_EDGE * longest) || glm::length(&face) <= f32::EPSILON * longest * longest {
+            return;
+        }
+        let normal = self.normal(a) + self.normal(b) + self.normal(c);
+        if glm::dot(&face, &normal) >= 0.0 {
+            self.indices.extend_from_slice(&[a, b, c]);
+        } else {
+            self.indices.extend_from_slice(&[a, c, b]);
+        }
+    }
+
+    // A grid of (columns + 1) x (rows + 1) vertices, from the position, normal and uv of each
+    // column and row, joined up into quads
+    fn surface(&mut self, columns: usize, rows: usize, vertex_at: impl Fn(usize, usize) -> (glm::Vec3, glm::Vec3, glm::Vec2)) {
+        let first = (self.vertices.len() / 3) as u32;
+        for row in 0..=rows {
+            for column in 0..=columns {
+                let (position, normal, uv) = vertex_at(column, row);
+                self.vertex(&position, &normal, &uv);
+            }
+        }
+        let index = |column: usize, row: usize| first + (row * (columns + 1) + column) as u32;
+        for row in 0..rows {
+            for column in 0..columns {
+                let (a, b) = (index(column, row), index(column + 1, row));
+                let (c, d) = (index(column, row + 1), index(column + 1, row + 1));
+                self.triangle(a, c, b);
+                self.triangle(b, c, d);
+            }
+        }
+    }
+
+    // A flat disc at height y, facing up or down
+    fn cap(&mut self, radius: f32, y: f32, segments: usize, facing_up: bool) {
+        let normal = glm::vec3(0.0, if facing_up { 1.0 } else { -1.0 }, 0.0);
+        self.surface(segments, 1, |column, row| {
+            let angle = column as f32 / segments as f32 * TAU;
+            let distance = row as f32 * radius;
+            let (sin, cos) = angle.sin_cos();
+            (
+                glm::vec3(distance * cos, y, distance * sin),
+                normal,
+                glm::vec2(0.5 + 0.5 * row as f32 * cos, 0.5 + 0.5 * row as f32 * sin),
+            )
+        });
+    }
+
+    fn build(self, color: [f32; 4]) -> Mesh {
+        let colors = color.iter().copied().cycle().take(self.vertices.len() / 3 * 4).collect();
+        let mut mesh = Mesh::new(self.vertices, self.normals, colors, self.indices);
+        mesh.uvs = self.uvs;
+        mesh
+    }
+}
+
+// On the unit sphere, from the angle around the Y axis and the angle down from the top
+fn spherical(around: f32, down: f32) -> glm::Vec3 {
+    glm::vec3(down.sin() * around.cos(), down.cos(), down.sin() * around.sin())
+}
+
+impl Mesh {
+    // In the XZ plane, facing up
+    pub fn plane(width: f32, depth: f32, x_segments: usize, z_segments: usize, color: [f32; 4]) -> Mesh {
+        let (x_segments, z_segments) = (x_segments.max(1), z_segments.max(1));
+        let mut builder = Builder::default();
+        builder.surface(x_segments, z_segments, |column, row| {
+            let uv = glm::vec2(column as f32 / x_segments as f32, row as f32 / z_segments as f32);
+            (glm::vec3((uv.x - 0.5) * width, 0.0, (uv.y - 0.5) * depth), glm::vec3(0.0, 1.0, 0.0), uv)
+        });
+        builder.build(color)
+    }
+
+    // Every face split into segments x segments quads, with its own vertices so that the edges
+    // stay sharp
+    pub fn cube(size: f32, segments: usize, color: [f32; 4]) -> Mesh {
+        let segments = segments.max(1);
+        let mut builder = Builder::default();
+        let axes = [glm::Vec3::x(), glm::Vec3::y(), glm::Vec3::z()];
+        for axis in 0..3 {
+            for sign in [1.0, -1.0] {
+                let normal = axes[axis] * sign;
+                let (u_axis, v_axis) = (axes[(axis + 1) % 3], axes[(axis + 2) % 3]);
+                builder.surface(segments, segments, |column, row| {
+                    let uv = glm::vec2(column as f32 / segments as f32, row as f32 / segments as f32);
+                    let position = (normal * 0.5 + u_axis * (uv.x - 0.5) + v_axis * (uv.y - 0.5)) * size;
+                    (position, normal, uv)
+                });
+            }
+        }
+        builder.build(color)
+    }
+
+    // Segments around the Y axis and rings from pole to pole
+    pub fn uv_sphere(radius: f32, segments: usize, rings: usize, color: [f32; 4]) -> Mesh {
+        let (segments, rings) = (segments.max(3), rings.max(2));
+        let mut builder = Builder::default();
+        builder.surface(segments, rings, |column, row| {
+            let uv = glm::vec2(column as f32 / segments as f32, row as f32 / rings as f32);
+            let normal = spherical(uv.x * TAU, uv.y * PI);
+            (normal * radius, normal, uv)
+        });
+        builder.build(color)
+    }
+
+    // An icosahedron with every triangle split into four, subdivisions times over, which spreads
+    // the triangles evenly unlike the uv sphere. The uvs are spherical, and wrap around at -X.
+    pub fn icosphere(radius: f32, subdivisions: usize, color: [f32; 4]) -> Mesh {
+        let t = (1.0 + 5.0f32.sqrt()) / 2.0;
+        let mut points: Vec<glm::Vec3> = [
+            (-1.0, t, 0.0), (1.0, t, 0.0), (-1.0, -t, 0.0), (1.0, -t, 0.0),
+            (0.0, -1.0, t), (0.0, 1.0, t), (0.0, -1.0, -t), (0.0, 1.0, -t),
+            (t, 0.0, -1.0), (t, 0.0, 1.0), (-t, 0.0, -1.0), (-t, 0.0, 1.0),
+        ].iter().map(|&(x, y, z)| glm::normalize(&glm::vec3(x, y, z))).collect();
+        let mut triangles: Vec<[usize; 3]> = vec![
+            [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+            [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+            [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+            [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+        ];
+
+        for _ in 0..subdivisions {
+            // Shared edges share their midpoint
+            let mut midpoints: HashMap<(usize, usize), usize> = HashMap::new();
+            let mut midpoint = |a: usize, b: usize, points: &mut Vec<glm::Vec3>| {
+                *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                    points.push(glm::normalize(&(points[a] + points[b])));
+                    points.len() - 1
+                })
+            };
+            triangles = triangles.iter().flat_map(|&[a, b, c]| {
+                let ab = midpoint(a, b, &mut points);
+                let bc = midpoint(b, c, &mut points);
+                let ca = midpoint(c, a, &mut points);
+                [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            }).collect();
+        }
+
+        let mut builder = Builder::default();
+        for point in &points {
+            let uv = glm::vec2(point.z.atan2(point.x) / TAU + 0.5, point.y.clamp(-1.0, 1.0).acos() / PI);
+            builder.vertex(&(point * radius), point, &uv);
+        }
+        for [a, b, c] in triangles {
+            builder.triangle(a as u32, b as u32, c as u32);
+        }
+        builder.build(color)
+    }
+
+    // Standing on end, with caps
+    pub fn cylinder(radius: f32, height: f32, segments: usize, color: [f32; 4]) -> Mesh {
+        let segments = segments.max(3);
+        let mut builder = Builder::default();
+        builder.surface(segments, 1, |column, row| {
+            let uv = glm::vec2(column as f32 / segments as f32, row as f32);
+            let normal = spherical(uv.x * TAU, FRAC_PI_2);
+            (normal * radius + glm::vec3(0.0, (uv.y - 0.5) * height, 0.0), normal, uv)
+        });
+        builder.cap(radius, height * 0.5, segments, true);
+        builder.cap(radius, -height * 0.5, segments, false);
+        builder.build(color)
+    }
+
+    // Pointing up, with a cap at the bottom
+    pub fn cone(radius: f32, height: f32, segments: usize, color: [f32; 4]) -> Mesh {
+        let segments = segments.max(3);
+        let mut builder = Builder::default();
+        builder.surface(segments, 1, |column, row| {
+            let uv = glm::vec2(column as f32 / segments as f32, row as f32);
+            let around = spherical(uv.x * TAU, FRAC_PI_2);
+            let position = around * radius * (1.0 - uv.y) + glm::vec3(0.0, (uv.y - 0.5) * height, 0.0);
+            // Perpendicular to the slope
+            let normal = around * height + glm::vec3(0.0, radius, 0.0);
+            (position, normal, uv)
+        });
+        builder.cap(radius, -height * 0.5, segments, false);
+        builder.build(color)
+    }
+
+    // Lying in the XZ plane, major_radius out from the middle to the center of the tube
+    pub fn torus(major_radius: f32, minor_radius: f32, major_segments: usize, minor_segments: usize, color: [f32; 4]) -> Mesh {
+        let (major_segments, minor_segments) = (major_segments.max(3), minor_segments.max(3));
+        let mut builder = Builder::default();
+        builder.surface(major_segments, minor_segments, |column, row| {
+            let uv = glm::vec2(column as f32 / major_segments as f32, row as f32 / minor_segments as f32);
+            let outwards = spherical(uv.x * TAU, FRAC_PI_2);
+            let (sin, cos) = (uv.y * TAU).sin_cos();
+            let normal = outwards * cos + glm::vec3(0.0, sin, 0.0);
+            (outwards * major_radius + normal * minor_radius, normal, uv)
+        });
+        builder.build(color)
+    }
+
+    // A cylinder of the given height with a half sphere on either end, standing on end
+    pub fn capsule(radius: f32, height: f32, segments: usize, rings: usize, color: [f32; 4]) -> Mesh {
+        let (segments, rings) = (segments.max(3), rings.max(1));
+        let total_height = height + 2.0 * radius;
+        let mut builder = Builder::default();
+        // Rings of the top half, then those of the bottom half, with the cylinder in between
+        builder.surface(segments, 2 * rings + 1, |column, row| {
+            let (down, offset) = if row <= rings {
+                (row as f32 / rings as f32 * FRAC_PI_2, height * 0.5)
+            } else {
+                ((row - 1) as f32 / rings as f32 * FRAC_PI_2, -height * 0.5)
+            };
+            let normal = spherical(column as f32 / segments as f32 * TAU, down);
+            let position = normal * radius + glm::vec3(0.0, offset, 0.0);
+            let uv = glm::vec2(column as f32 / segments as f32, 0.5 - position.y / total_height);
+            (position, normal, uv)
+        });
+        builder.build(color)
+    }
+
+    // In the XZ plane, facing up
+    pub fn disc(radius: f32, segments: usize, color: [f32; 4]) -> Mesh {
+        let mut builder = Builder::default();
+        builder.cap(radius, 0.0, segments.max(3), true);
+        builder.build(color)
+    }
+
+    // The same mesh moved, turned or scaled
+    pub fn transformed(mut self, transform: &glm::Mat4) -> Mesh {
+        let normal_matrix = glm::transpose(&glm::inverse(&glm::mat4_to_mat3(transform)));
+        for position in self.vertices.chunks_exact_mut(3) {
+            let moved = transform * glm::vec4(position[0], position[1], position[2], 1.0);
+            position.copy_from_slice(moved.xyz().as_slice());
+        }
+        for normal in self.normals.chunks_exact_mut(3) {
+            let turned = glm::normalize(&(normal_matrix * glm::make_vec3(normal)));
+            normal.copy_from_slice(turned.as_slice());
+        }
+        let mut mesh = Mesh::new(self.vertices, self.normals, self.colors, self.indices);
+        mesh.uvs = self.uvs;
+        mesh
+    }
+}
+
+impl Helicopter {
+    // Something shaped roughly like the helicopter model, with the parts where the scene expects
+    // them, for when the model can't be loaded
+    pub fn placeholder() -> Helicopter {
+        let along_z = glm::rotation(FRAC_PI_2, &glm::Vec3::x());
+        let along_x = glm::rotation(FRAC_PI_2, &glm::Vec3::z());
+        Helicopter {
+            body: Mesh::capsule(1.2, 10.0, 16, 6, [0.3, 0.3, 0.3, 1.0])
+                .transformed(&(glm::translation(&glm::vec3(0.0, 1.6, 4.0)) * along_z)),
+            door: Mesh::cube(1.0, 1, [0.1, 0.1, 0.3, 1.0])
+                .transformed(&(glm::translation(&glm::vec3(1.1, 1.4, 0.0)) * glm::scaling(&glm::vec3(0.2, 1.4, 1.6)))),
+            main_rotor: Mesh::cylinder(5.5, 0.08, 24, [0.3, 0.1, 0.1, 1.0])
+                .transformed(&glm::translation(&glm::vec3(0.0, 3.2, 0.0))),
+            tail_rotor: Mesh::cylinder(1.2, 0.08, 16, [0.1, 0.3, 0.1, 1.0])
+                .transformed(&(glm::translation(&glm::vec3(0.35, 2.3, 10.4)) * along_x)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: [f32; 4] = [1.0; 4];
+
+    #[test]
+    fn primitives_have_the_expected_vertices_and_triangles() {
+        // With the triangles at the poles and apexes left out
+        let primitives = [
+            ("plane",     Mesh::plane(2.0, 3.0, 4, 5, WHITE),           5 * 6,       2 * 4 * 5),
+            ("cube",      Mesh::cube(1.0, 3, WHITE),                    6 * 4 * 4,   12 * 3 * 3),
+            ("uv_sphere", Mesh::uv_sphere(1.0, 8, 6, WHITE),            9 * 7,       2 * 8 * 5),
+            ("icosphere", Mesh::icosphere(1.0, 2, WHITE),               10 * 16 + 2, 20 * 16),
+            ("cylinder",  Mesh::cylinder(1.0, 2.0, 8, WHITE),           3 * 2 * 9,   4 * 8),
+            ("cone",      Mesh::cone(1.0, 2.0, 8, WHITE),               2 * 2 * 9,   2 * 8),
+            ("torus",     Mesh::torus(2.0, 0.5, 8, 6, WHITE),           9 * 7,       2 * 8 * 6),
+            ("capsule",   Mesh::capsule(1.0, 2.0, 8, 3, WHITE),         9 * 8,       4 * 8 * 3),
+            ("disc",      Mesh::disc(1.0, 8, WHITE),                    2 * 9,       8),
+        ];
+        for (name, mesh, vertices, triangles) in primitives.iter() {
+            assert_eq!((mesh.vertex_count(), mesh.triangle_count()), (*vertices, *triangles), "{}", name);
+            assert!(mesh.validate().is_ok(), "{}", name);
+            assert_eq!((mesh.normals.len(), mesh.colors.len(), mesh.uvs.len()), (3 * vertices, 4 * vertices, 2 * vertices), "{}", name);
+            for normal in mesh.normals.chunks_exact(3) {
+                assert!((glm::length(&glm::make_vec3(normal)) - 1.0).abs() < 1e-5, "{}: {:?}", name, normal);
+            }
+        }
+    }
+
+    #[test]
+    fn closed_shapes_face_outwards() {
+        for mesh in [Mesh::cube(1.0, 2, WHITE), Mesh::uv_sphere(1.0, 12, 8, WHITE), Mesh::icosphere(1.0, 1, WHITE), Mesh::capsule(0.5, 1.0, 8, 2, WHITE)] {
+            for triangle in mesh.indices.chunks_exact(3) {
+                let [a, b, c] = [0, 1, 2].map(|i| glm::make_vec3(&mesh.vertices[3 * triangle[i] as usize..3 * triangle[i] as usize + 3]));
+                let face = glm::cross(&(b - a), &(c - a));
+                assert!(glm::dot(&face, &(a + b + c)) > 0.0, "triangle {:?} faces inwards", triangle);
+            }
+            for (position, normal) in mesh.vertices.chunks_exact(3).zip(mesh.normals.chunks_exact(3)) {
+                assert!(glm::dot(&glm::make_vec3(position), &glm::make_vec3(normal)) > 0.0);
+            }
+        }
+    }
+}
@@ -0,0 +1,382 @@
+extern crate nalgebra_glm as glm;
+
+use image::{Rgba, RgbaImage};
+
+use crate::mesh::Mesh;
+use crate::renderer::{BlendState, CullState, DrawCommand, Lighting, RenderState, Renderer};
+
+// A software implementation of what `simple.vert` and `simple.frag` do on the GPU, along with the
+// fixed function state set up in main(): depth testing with LESS, back face culling of clockwise
+// triangles and SRC_ALPHA, ONE_MINUS_SRC_ALPHA blending. It needs no OpenGL context at all, which
+// makes it useful as a reference to diff the GL output against, or for rendering on a server.
+
+// The six planes of the canonical view volume, as coefficients for (x, y, z, w) in clip space.
+// A vertex is inside of a plane when the dot product with it is positive.
+const CLIP_PLANES: [[f32; 4]; 6] = [
+    [ 1.0,  0.0,  0.0, 1.0], // left
+    [-1.0,  0.0,  0.0, 1.0], // right
+    [ 0.0,  1.0,  0.0, 1.0], // bottom
+    [ 0.0, -1.0,  0.0, 1.0], // top
+    [ 0.0,  0.0,  1.0, 1.0], // near
+    [ 0.0,  0.0, -1.0, 1.0], // far
+];
+
+// The outputs of the vertex shader
+#[derive(Clone, Copy)]
+struct ClipVertex {
+    position : glm::Vec4, // gl_Position
+    color    : glm::Vec4, // vColor
+    normal   : glm::Vec3, // vNormal
+}
+
+impl ClipVertex {
+    fn lerp(&self, other: &ClipVertex, t: f32) -> ClipVertex {
+        ClipVertex {
+            position : glm::lerp(&self.position, &other.position, t),
+            color    : glm::lerp(&self.color, &other.color, t),
+            normal   : glm::lerp(&self.normal, &other.normal, t),
+        }
+    }
+}
+
+// A clip space vertex after the perspective divide and viewport transform
+#[derive(Clone, Copy)]
+struct ScreenVertex {
+    x     : f32,
+    y     : f32,
+    depth : f32,
+    inv_w : f32,
+}
+
+pub struct Rasterizer {
+    pub width       : u32,
+    pub height      : u32,
+    color_buffer    : Vec<glm::Vec4>,
+    depth_buffer    : Vec<f32>,
+    meshes          : Vec<Mesh>, // Indexed by mesh id - 1
+    state           : RenderState,
+    lighting        : Lighting,
+    view_projection : glm::Mat4,
+}
+
+impl Rasterizer {
+    pub fn new(width: u32, height: u32) -> Self {
+        let pixels = (width * height) as usize;
+        Rasterizer {
+            width,
+            height,
+            color_buffer    : vec![glm::zero(); pixels],
+            depth_buffer    : vec![1.0; pixels],
+            meshes          : vec![],
+            state           : RenderState::default(),
+            lighting        : Lighting::default(),
+            view_projection : glm::identity(),
+        }
+    }
+
+    // Every uploaded mesh, indexed by mesh id - 1
+    pub fn meshes(&self) -> &[Mesh] {
+        &self.meshes
+    }
+
+    fn draw_mesh(&mut self, mesh_index: usize, index_count: usize, model: &glm::Mat4, tint: &glm::Vec4) {
+        let model_view_projection = self.view_projection * model;
+        // The inverse transpose, as in simple.vert, which keeps normals normal under non-uniform scale
+        let normal_matrix = glm::transpose(&glm::inverse(&glm::mat4_to_mat3(model)));
+        let mesh = &self.meshes[mesh_index];
+
+        let shade_vertex = |i: usize| -> ClipVertex {
+            let p = glm::vec4(mesh.vertices[3*i], mesh.vertices[3*i + 1], mesh.vertices[3*i + 2], 1.0);
+            // A mesh without colors is white, like the GL renderer leaves the color attribute
+            let color = if mesh.colors.len() >= 4*i + 4 {
+                glm::vec4(mesh.colors[4*i], mesh.colors[4*i + 1], mesh.colors[4*i + 2], mesh.colors[4*i + 3])
+            } else {
+                glm::vec4(1.0, 1.0, 1.0, 1.0)
+            }.component_mul(tint);
+            // An OBJ without normals leaves the normal attribute empty
+            let normal = if mesh.normals.len() >= 3*i + 3 {
+                glm::normalize(&(normal_matrix * glm::vec3(mesh.normals[3*i], mesh.normals[3*i + 1], mesh.normals[3*i + 2])))
+            } else {
+                glm::zero()
+            };
+            ClipVertex { position: model_view_projection * p, color, normal }
+        };
+
+        let index_count = index_count.min(mesh.indices.len());
+        let polygons: Vec<Vec<ClipVertex>> = mesh.indices[..index_count].chunks_exact(3)
+            .map(|triangle| clip_polygon(vec![
+                shade_vertex(triangle[0] as usize),
+                shade_vertex(triangle[1] as usize),
+                shade_vertex(triangle[2] as usize),
+            ]))
+            .collect();
+
+        for polygon in &polygons {
+            // The clipped polygon is convex, so it can be drawn as a triangle fan
+            for i in 1..polygon.len().saturating_sub(1) {
+                self.rasterize_triangle(&polygon[0], &polygon[i], &polygon[i + 1]);
+            }
+        }
+    }
+
+    fn to_screen(&self, v: &ClipVertex) -> ScreenVertex {
+        let inv_w = 1.0 / v.position.w;
+        let ndc = v.position.xyz() * inv_w;
+        ScreenVertex {
+            x     : (ndc.x * 0.5 + 0.5) * self.width as f32,
+            y     : (0.5 - ndc.y * 0.5) * self.height as f32, // images have their origin at the top
+            depth : ndc.z * 0.5 + 0.5,
+            inv_w,
+        }
+    }
+
+    fn rasterize_triangle(&mut self, a: &ClipVertex, b: &ClipVertex, c: &ClipVertex) {
+        let (sa, sb, sc) = (self.to_screen(a), self.to_screen(b), self.to_screen(c));
+
+        // Counter-clockwise triangles face the camera. The y axis is flipped in screen space,
+        // which flips the sign of the area as well.
+        let area = edge_function(&sa, &sb, sc.x, sc.y);
+        let culled = match self.state.cull {
+            CullState::Disabled => area == 0.0,
+            CullState::Back     => area >= 0.0,
+            CullState::Front    => area <= 0.0,
+        };
+        if culled {
+            return;
+        }
+
+        let min_x = sa.x.min(sb.x).min(sc.x).floor().max(0.0) as u32;
+        let min_y = sa.y.min(sb.y).min(sc.y).floor().max(0.0) as u32;
+        let max_x = (sa.x.max(sb.x).max(sc.x).ceil() as u32).min(self.width);
+        let max_y = (sa.y.max(sb.y).max(sc.y).ceil() as u32).min(self.height);
+
+        for py in min_y..max_y {
+            for px in min_x..max_x {
+                let (x, y) = (px as f32 + 0.5, py as f32 + 0.5);
+                let w0 = edge_function(&sb, &sc, x, y) / area;
+                let w1 = edge_function(&sc, &sa, x, y) / area;
+                let w2 = edge_function(&sa, &sb, x, y) / area;
+                if w0 < 0.0 || w1 < 0.0 || w2 < 0.0 {
+                    continue;
+                }
+
+                let depth = w0 * sa.depth + w1 * sb.depth + w2 * sc.depth;
+                let index = (py * self.width + px) as usize;
+                if self.state.depth.test && depth >= self.depth_buffer[index] {
+                    continue;
+                }
+
+                // Screen space barycentrics are not affine in world space, so weigh them by 1/w
+                let (p0, p1, p2) = (w0 * sa.inv_w, w1 * sb.inv_w, w2 * sc.inv_w);
+                let sum = p0 + p1 + p2;
+                let (p0, p1, p2) = (p0 / sum, p1 / sum, p2 / sum);
+
+                let color  = a.color * p0 + b.color * p1 + c.color * p2;
+                let normal = a.normal * p0 + b.normal * p1 + c.normal * p2;
+
+                // simple.frag
+                let light = self.lighting.light_at(&normal);
+                let lit = glm::vec4(color.x * light.x, color.y * light.y, color.z * light.z, color.w);
+
+                self.color_buffer[index] = match self.state.blend {
+                    BlendState::Opaque => lit,
+                    // glBlendFunc(SRC_ALPHA, ONE_MINUS_SRC_ALPHA)
+                    BlendState::AlphaBlend => lit * lit.w + self.color_buffer[index] * (1.0 - lit.w),
+                };
+                if self.state.depth.write {
+                    self.depth_buffer[index] = depth;
+                }
+            }
+        }
+    }
+
+    pub fn to_image(&self) -> RgbaImage {
+        let to_byte = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+        RgbaImage::from_fn(self.width, self.height, |x, y| {
+            let c = self.color_buffer[(y * self.width + x) as usize];
+            Rgba([to_byte(c.x), to_byte(c.y), to_byte(c.z), to_byte(c.w)])
+        })
+    }
+}
+
+// Twice the signed area of the triangle (a, b, p)
+fn edge_function(a: &ScreenVertex, b: &ScreenVertex, px: f32, py: f32) -> f32 {
+    (b.x - a.x) * (py - a.y) - (b.y - a.y) * (px - a.x)
+}
+
+// Sutherland-Hodgman clipping of a convex polygon against the view volume, done in homogeneous
+// clip space so that triangles crossing the w = 0 plane behind the camera are handled as well.
+fn clip_polygon(mut polygon: Vec<ClipVertex>) -> Vec<ClipVertex> {
+    for plane in CLIP_PLANES.iter() {
+        if polygon.is_empty() {
+            break;
+        }
+        let plane = glm::make_vec4(plane);
+        let distance = |v: &ClipVertex| glm::dot(&plane, &v.position);
+
+        let mut clipped = Vec::with_capacity(polygon.len() + 1);
+        for i in 0..polygon.len() {
+            let current = &polygon[i];
+            let next = &polygon[(i + 1) % polygon.len()];
+            let (d_current, d_next) = (distance(current), distance(next));
+
+            if d_current >= 0.0 {
+                clipped.push(*current);
+            }
+            if (d_current >= 0.0) != (d_next >= 0.0) {
+                clipped.push(current.lerp(next, d_current / (d_current - d_next)));
+            }
+        }
+        polygon = clipped;
+    }
+    polygon
+}
+
+impl Renderer for Rasterizer {
+    fn upload_mesh(&mut self, mesh: &Mesh) -> (u32, i32) {
+        self.meshes.push(mesh.clone());
+        (self.meshes.len() as u32, mesh.index_count)
+    }
+
+    // Deleted meshes are left empty, so that the ids of the others stay put
+    fn delete_mesh(&mut self, mesh_id: u32) {
+        if let Some(mesh) = mesh_id.checked_sub(1).and_then(|i| self.meshes.get_mut(i as usize)) {
+            *mesh = Mesh::new(vec![], vec![], vec![], vec![]);
+        }
+    }
+
+    // Looked for every time, which costs next to nothing next to drawing the mesh
+    fn has_translucent_vertices(&self, mesh_id: u32) -> bool {
+        mesh_id.checked_sub(1).and_then(|i| self.meshes.get(i as usize)).is_some_and(Mesh::has_translucent_colors)
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        let pixels = (width * height) as usize;
+        self.width = width;
+        self.height = height;
+        self.color_buffer = vec![glm::zero(); pixels];
+        self.depth_buffer = vec![1.0; pixels];
+    }
+
+    fn begin_frame(&mut self, clear_color: &glm::Vec4, view_projection: &glm::Mat4) {
+        self.view_projection = *view_projection;
+        self.state = RenderState::default();
+        self.color_buffer.iter_mut().for_each(|c| *c = *clear_color);
+        self.depth_buffer.iter_mut().for_each(|d| *d = 1.0);
+    }
+
+    fn set_state(&mut self, state: &RenderState) {
+        self.state = *state;
+    }
+
+    fn set_lighting(&mut self, lighting: &Lighting) {
+        self.lighting = lighting.clone();
+    }
+
+    fn submit(&mut self, command: &DrawCommand) {
+        if let Some(i) = command.mesh_id.checked_sub(1).filter(|&i| (i as usize) < self.meshes.len()) {
+            let index_count = command.index_count.max(0) as usize;
+            self.draw_mesh(i as usize, index_count, &command.transform, &command.material.tint);
+        }
+    }
+
+    fn end_frame(&mut self) { }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::{DirectionalLight, Material};
+
+    const SIZE: u32 = 64;
+
+    fn mesh(vertices: &[[f32; 3]], colors: &[[f32; 4]], indices: Vec<u32>) -> Mesh {
+        let normals = [0.0, 0.0, 1.0].repeat(vertices.len());
+        Mesh::new(vertices.concat(), normals, colors.concat(), indices)
+    }
+
+    // A triangle facing the camera, covering the middle of the screen from the distance
+    fn facing(distance: f32, color: [f32; 4]) -> Mesh {
+        let vertices = [[-2.0, -2.0, -distance], [2.0, -2.0, -distance], [0.0, 2.0, -distance]];
+        mesh(&vertices, &[color; 3], vec![0, 1, 2])
+    }
+
+    // Draw every mesh as it is, with the camera at the origin looking down -z, and only light
+    // which leaves the colors as they are unless lighting is given
+    fn render(meshes: &[Mesh], lighting: Option<Lighting>) -> Rasterizer {
+        let mut rasterizer = Rasterizer::new(SIZE, SIZE);
+        rasterizer.set_lighting(&lighting.unwrap_or(Lighting { ambient: glm::vec3(1.0, 1.0, 1.0), lights: vec![] }));
+        let view_projection = glm::perspective(1.0, std::f32::consts::FRAC_PI_2, 0.1, 100.0);
+        rasterizer.begin_frame(&glm::zero(), &view_projection);
+        for mesh in meshes {
+            let (mesh_id, index_count) = rasterizer.upload_mesh(mesh);
+            rasterizer.submit(&DrawCommand { mesh_id, index_count, material: Material::default(), transform: glm::identity() });
+        }
+        rasterizer.end_frame();
+        rasterizer
+    }
+
+    fn pixel(rasterizer: &Rasterizer, x: u32, y: u32) -> glm::Vec4 {
+        rasterizer.color_buffer[(y * rasterizer.width + x) as usize]
+    }
+
+    #[test]
+    fn the_nearer_triangle_wins_whichever_is_drawn_first() {
+        let (red, green) = ([1.0, 0.0, 0.0, 1.0], [0.0, 1.0, 0.0, 1.0]);
+        for meshes in [[facing(2.0, red), facing(5.0, green)], [facing(5.0, green), facing(2.0, red)]] {
+            assert_eq!(pixel(&render(&meshes, None), SIZE / 2, SIZE / 2), glm::make_vec4(&red));
+        }
+    }
+
+    #[test]
+    fn triangles_reaching_behind_the_camera_are_clipped() {
+        // The ground below the camera, from behind it to far ahead of it
+        let ground = mesh(&[[-3.0, -1.0, 5.0], [3.0, -1.0, 5.0], [0.0, -1.0, -20.0]], &[[1.0; 4]; 3], vec![0, 1, 2]);
+        let rasterizer = render(&[ground], None);
+        // Without clipping, the corners behind the camera would turn up above the horizon
+        for y in 0..SIZE / 2 {
+            for x in 0..SIZE {
+                assert_eq!(pixel(&rasterizer, x, y), glm::Vec4::zeros(), "({}, {})", x, y);
+            }
+        }
+        assert_eq!(pixel(&rasterizer, SIZE / 2, SIZE - 1), glm::vec4(1.0, 1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn colors_are_interpolated_in_perspective() {
+        // A strip of ground going from red right below the camera to blue far ahead of it
+        let (red, blue) = ([1.0, 0.0, 0.0, 1.0], [0.0, 0.0, 1.0, 1.0]);
+        let (near, far) = (2.0, 10.0);
+        let vertices = [[-1.0, -1.0, -near], [1.0, -1.0, -near], [1.0, -1.0, -far], [-1.0, -1.0, -far]];
+        let rasterizer = render(&[mesh(&vertices, &[red, red, blue, blue], vec![0, 1, 2, 0, 2, 3])], None);
+
+        for y in SIZE / 2 + 4..SIZE * 3 / 4 {
+            // With a field of view of 90 degrees, the ground one unit below is seen 1 / -ndc_y ahead
+            let ndc_y = 1.0 - 2.0 * (y as f32 + 0.5) / SIZE as f32;
+            let blueness = (-1.0 / ndc_y - near) / (far - near);
+            let expected = glm::lerp(&glm::make_vec4(&red), &glm::make_vec4(&blue), blueness);
+            assert!(glm::distance(&pixel(&rasterizer, SIZE / 2, y), &expected) < 1e-4, "row {}", y);
+        }
+    }
+
+    #[test]
+    fn surfaces_are_lit_by_the_angle_to_the_light() {
+        let lighting = Lighting {
+            ambient : glm::vec3(0.1, 0.1, 0.1),
+            lights  : vec![DirectionalLight { direction: glm::vec3(0.0, -1.0, -1.0), color: glm::vec3(1.0, 1.0, 1.0) }],
+        };
+        // Facing the camera, with the light coming in at 45 degrees from above
+        let colored = facing(2.0, [1.0, 0.5, 0.25, 1.0]);
+        let expected = glm::vec4(1.0, 0.5, 0.25, 1.0) * (0.1 + std::f32::consts::FRAC_1_SQRT_2);
+        let rasterizer = render(&[colored], Some(lighting.clone()));
+        let color = pixel(&rasterizer, SIZE / 2, SIZE / 2);
+        assert!(glm::distance(&color.xyz(), &expected.xyz()) < 1e-5, "{:?}", color);
+        assert_eq!(color.w, 1.0);
+
+        // Without colors, it's white
+        let mut uncolored = facing(2.0, [0.0; 4]);
+        uncolored.colors.clear();
+        let color = pixel(&render(&[uncolored], Some(lighting)), SIZE / 2, SIZE / 2);
+        assert!(glm::distance(&color, &glm::vec4(0.8071, 0.8071, 0.8071, 1.0)) < 1e-4, "{:?}", color);
+    }
+}
@@ -0,0 +1,386 @@
+extern crate nalgebra_glm as glm;
+
+use std::collections::hash_map::{Entry, HashMap};
+
+use crate::bounds::Frustum;
+use crate::mesh::Mesh;
+use crate::scene_graph::SceneNode;
+
+// The interface between the scene and whatever ends up drawing it. Meshes are uploaded once and
+// referred to by the id handed back, which is what SceneNode::vao_id holds. Every backend hands
+// out ids counting up from 1 in upload order, so uploading the same meshes in the same order to
+// two renderers lets them draw the same scene graph.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DepthState {
+    pub test  : bool, // Discard fragments behind what is already drawn, using LESS
+    pub write : bool, // Store the depth of the fragments which pass
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BlendState {
+    Opaque,
+    AlphaBlend, // SRC_ALPHA, ONE_MINUS_SRC_ALPHA
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CullState {
+    Disabled,
+    Back,  // Discard clockwise triangles
+    Front, // Discard counter-clockwise triangles
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderState {
+    pub depth : DepthState,
+    pub blend : BlendState,
+    pub cull  : CullState,
+}
+
+impl Default for RenderState {
+    fn default() -> Self {
+        RenderState {
+            depth : DepthState { test: true, write: true },
+            blend : BlendState::AlphaBlend,
+            cull  : CullState::Back,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Material {
+    pub tint : glm::Vec4, // Multiplied with the vertex colors, alpha included
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material { tint: glm::vec4(1.0, 1.0, 1.0, 1.0) }
+    }
+}
+
+impl Material {
+    // Whether draws with this material belong in the blended, back-to-front sorted pass. So do
+    // draws of meshes with translucent vertex colors, see Renderer::has_translucent_vertices.
+    pub fn is_transparent(&self) -> bool {
+        self.tint.w < 1.0
+    }
+}
+
+// As many directional lights as simple.frag has room for
+pub const MAX_LIGHTS: usize = 4;
+
+// Light coming from far away in one direction, like from the sun
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DirectionalLight {
+    pub direction : glm::Vec3, // Which way the light travels, need not be normalized
+    pub color     : glm::Vec3, // Also how bright it is
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lighting {
+    pub ambient : glm::Vec3,             // Added to every surface, whichever way it faces
+    pub lights  : Vec<DirectionalLight>, // Only the first MAX_LIGHTS are used
+}
+
+impl Default for Lighting {
+    // A single white light, which is what simple.frag used to hard code
+    fn default() -> Self {
+        Lighting {
+            ambient : glm::zero(),
+            lights  : vec![DirectionalLight { direction: glm::vec3(0.8, -0.5, 0.6), color: glm::vec3(1.0, 1.0, 1.0) }],
+        }
+    }
+}
+
+impl Lighting {
+    // How much light reaches a surface with the normal
+    pub fn light_at(&self, normal: &glm::Vec3) -> glm::Vec3 {
+        self.lights.iter().take(MAX_LIGHTS).fold(self.ambient, |sum, light| {
+            sum + light.color * glm::dot(normal, &(-glm::normalize(&light.direction))).max(0.0)
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DrawCommand {
+    pub mesh_id     : u32,
+    pub index_count : i32,
+    pub material    : Material,
+    pub transform   : glm::Mat4, // The model matrix
+}
+
+pub trait Renderer {
+    // Returns the id to draw the mesh with, along with its index count
+    fn upload_mesh(&mut self, mesh: &Mesh) -> (u32, i32);
+
+    // Free a mesh which won't be drawn again. Its id is not handed out again.
+    fn delete_mesh(&mut self, mesh_id: u32);
+
+    // Whether any of the vertex colors of the mesh have an alpha below 1, as found when uploaded
+    fn has_translucent_vertices(&self, mesh_id: u32) -> bool;
+
+    fn resize(&mut self, width: u32, height: u32);
+
+    // Clears the frame, and resets the render state to the default
+    fn begin_frame(&mut self, clear_color: &glm::Vec4, view_projection: &glm::Mat4);
+
+    fn set_state(&mut self, state: &RenderState);
+
+    // Kept until set again, for all frames to come
+    fn set_lighting(&mut self, _lighting: &Lighting) { }
+
+    fn submit(&mut self, command: &DrawCommand);
+
+    // Draw the same mesh once for every transform. Backends able to do this in one go should.
+    fn submit_instanced(&mut self, mesh_id: u32, index_count: i32, material: &Material, transforms: &[glm::Mat4]) {
+        for transform in transforms {
+            self.submit(&DrawCommand { mesh_id, index_count, material: *material, transform: *transform });
+        }
+    }
+
+    fn end_frame(&mut self);
+}
+
+// Collects the draw commands of a frame, so they can be submitted in an order which makes
+// blending work. Opaque draws go first, front-to-back so that hidden fragments fail the depth
+// test early. Transparent draws follow back-to-front, blended and without writing depth, so
+// that everything behind them has already been drawn and none of them hide each other.
+pub struct RenderQueue {
+    view_projection : glm::Mat4,
+    frustum         : Frustum,
+    opaque          : Vec<(f32, DrawCommand)>, // Along with their depth in clip space
+    transparent     : Vec<(f32, DrawCommand)>,
+    pub culled      : usize, // Number of subtrees skipped since begin()
+}
+
+impl Default for RenderQueue {
+    fn default() -> Self {
+        RenderQueue {
+            view_projection : glm::identity(),
+            frustum         : Frustum::from_view_projection(&glm::identity()),
+            opaque          : vec![],
+            transparent     : vec![],
+            culled          : 0,
+        }
+    }
+}
+
+impl RenderQueue {
+    pub fn begin(&mut self, view_projection: &glm::Mat4) {
+        self.view_projection = *view_projection;
+        self.frustum = Frustum::from_view_projection(view_projection);
+        self.opaque.clear();
+        self.transparent.clear();
+        self.culled = 0;
+    }
+
+    pub fn push(&mut self, command: DrawCommand) {
+        // The depth of the model's origin. Clip space z grows with the distance from the camera
+        // for both perspective and orthographic projections, so there's no need for the divide.
+        let depth = (self.view_projection * command.transform * glm::vec4(0.0, 0.0, 0.0, 1.0)).z;
+        if command.material.is_transparent() {
+            self.transparent.push((depth, command));
+        } else {
+            self.opaque.push((depth, command));
+        }
+    }
+
+    // Walk the scene graph, queueing a draw command for every node with something to draw.
+    // Subtrees whose world bounds are entirely outside of the view frustum are skipped, so the
+    // bounds have to be brought up to date with SceneNode::update_world first.
+    pub fn push_scene(&mut self, node: &SceneNode, transformation_so_far: &glm::Mat4) {
+        if let Some(bounds) = &node.world_bounds {
+            if !self.frustum.intersects_aabb(bounds) {
+                self.culled += 1;
+                return;
+            }
+        }
+
+        let model = transformation_so_far * node.local_transform();
+
+        let (mesh_id, index_count) = node.drawn_mesh();
+        if index_count > 0 {
+            self.push(DrawCommand {
+                mesh_id,
+                index_count,
+                material    : node.material,
+                transform   : model,
+            });
+        }
+        for &child in &node.children {
+            self.push_scene(unsafe { &*child }, &model);
+        }
+    }
+
+    pub fn flush(&mut self, renderer: &mut dyn Renderer) {
+        // Only the renderer knows which meshes have translucent vertex colors
+        let (translucent, opaque): (Vec<_>, Vec<_>) = self.opaque.drain(..)
+            .partition(|(_, command)| renderer.has_translucent_vertices(command.mesh_id));
+        self.opaque = opaque;
+        self.transparent.extend(translucent);
+
+        self.opaque.sort_by(|a, b| a.0.total_cmp(&b.0));
+        self.transparent.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        // Opaque draws of the same mesh and material are batched into a single instanced draw,
+        // ordered by their nearest instance
+        let mut batches: Vec<(DrawCommand, Vec<glm::Mat4>)> = vec![];
+        let mut batch_of: HashMap<BatchKey, usize> = HashMap::new();
+        for (_, command) in self.opaque.drain(..) {
+            match batch_of.entry(BatchKey::of(&command)) {
+                Entry::Occupied(batch) => batches[*batch.get()].1.push(command.transform),
+                Entry::Vacant(batch) => {
+                    batch.insert(batches.len());
+                    batches.push((command, vec![command.transform]));
+                }
+            }
+        }
+
+        renderer.set_state(&RenderState {
+            blend: BlendState::Opaque,
+            ..RenderState::default()
+        });
+        for (command, transforms) in &batches {
+            renderer.submit_instanced(command.mesh_id, command.index_count, &command.material, transforms);
+        }
+
+        renderer.set_state(&RenderState {
+            depth: DepthState { test: true, write: false },
+            blend: BlendState::AlphaBlend,
+            ..RenderState::default()
+        });
+        for (_, command) in self.transparent.drain(..) {
+            renderer.submit(&command);
+        }
+
+        renderer.set_state(&RenderState::default());
+    }
+}
+
+// What draws have to share to be batched together. The tint goes by its bits, as floats can't be
+// hashed, so tints with 0 in one and -0 in the other are batched apart although they are equal.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct BatchKey {
+    mesh_id     : u32,
+    index_count : i32,
+    tint        : [u32; 4],
+}
+
+impl BatchKey {
+    fn of(command: &DrawCommand) -> Self {
+        let tint = command.material.tint;
+        BatchKey {
+            mesh_id     : command.mesh_id,
+            index_count : command.index_count,
+            tint        : [tint.x.to_bits(), tint.y.to_bits(), tint.z.to_bits(), tint.w.to_bits()],
+        }
+    }
+}
+
+// A renderer which draws nothing, but remembers what it was asked to draw. Handy as a mock
+// when checking what the scene submits without having an OpenGL context around.
+#[allow(dead_code)]
+#[derive(Default)]
+pub struct RecordingRenderer {
+    pub index_counts : Vec<i32>,          // Of the uploaded meshes, indexed by id - 1. 0 once deleted.
+    pub translucent  : Vec<bool>,         // Whether they have translucent vertex colors, likewise
+    pub size         : (u32, u32),
+    pub states       : Vec<RenderState>,
+    pub frames       : Vec<Vec<DrawCommand>>, // The commands of every finished frame
+    current_frame    : Vec<DrawCommand>,
+}
+
+impl Renderer for RecordingRenderer {
+    fn upload_mesh(&mut self, mesh: &Mesh) -> (u32, i32) {
+        self.index_counts.push(mesh.index_count);
+        self.translucent.push(mesh.has_translucent_colors());
+        (self.index_counts.len() as u32, mesh.index_count)
+    }
+
+    fn delete_mesh(&mut self, mesh_id: u32) {
+        if let Some(index_count) = mesh_id.checked_sub(1).and_then(|i| self.index_counts.get_mut(i as usize)) {
+            *index_count = 0;
+        }
+    }
+
+    fn has_translucent_vertices(&self, mesh_id: u32) -> bool {
+        mesh_id.checked_sub(1).and_then(|i| self.translucent.get(i as usize)).is_some_and(|&translucent| translucent)
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        self.size = (width, height);
+    }
+
+    fn begin_frame(&mut self, _clear_color: &glm::Vec4, _view_projection: &glm::Mat4) {
+        self.current_frame.clear();
+        self.states.push(RenderState::default());
+    }
+
+    fn set_state(&mut self, state: &RenderState) {
+        self.states.push(*state);
+    }
+
+    fn submit(&mut self, command: &DrawCommand) {
+        self.current_frame.push(*command);
+    }
+
+    fn end_frame(&mut self) {
+        self.frames.push(std::mem::take(&mut self.current_frame));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triangle(alpha: f32) -> Mesh {
+        let colors = [[1.0, 1.0, 1.0, 1.0], [1.0, 1.0, 1.0, alpha], [1.0, 1.0, 1.0, 1.0]].concat();
+        Mesh::new(vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 1.0].repeat(3), colors, vec![0, 1, 2])
+    }
+
+    // Which meshes were drawn in a frame, with the camera at the origin looking down -z
+    fn drawn(renderer: &mut RecordingRenderer, draws: &[(u32, Material, f32)]) -> Vec<u32> {
+        let view_projection = glm::perspective(1.0, 1.0, 0.1, 100.0);
+        let mut queue = RenderQueue::default();
+        queue.begin(&view_projection);
+        for &(mesh_id, material, distance) in draws {
+            queue.push(DrawCommand { mesh_id, index_count: 3, material, transform: glm::translation(&glm::vec3(0.0, 0.0, -distance)) });
+        }
+        renderer.begin_frame(&glm::zero(), &view_projection);
+        queue.flush(renderer);
+        renderer.end_frame();
+        renderer.frames.last().unwrap().iter().map(|command| command.mesh_id).collect()
+    }
+
+    #[test]
+    fn opaque_draws_are_batched_by_mesh_and_material() {
+        let mut renderer = RecordingRenderer::default();
+        let (a, b) = (renderer.upload_mesh(&triangle(1.0)).0, renderer.upload_mesh(&triangle(1.0)).0);
+        let solid = Material::default();
+        let red = Material { tint: glm::vec4(1.0, 0.0, 0.0, 1.0) };
+        let order = drawn(&mut renderer, &[(a, solid, 3.0), (b, solid, 1.0), (a, solid, 2.0), (a, red, 1.5), (b, solid, 4.0)]);
+        // Each batch where its nearest draw would be, with the rest of its draws right after it
+        assert_eq!(order, vec![b, b, a, a, a]);
+        let depths: Vec<f32> = renderer.frames[0].iter().map(|command| -command.transform[(2, 3)]).collect();
+        assert_eq!(depths, vec![1.0, 4.0, 1.5, 2.0, 3.0]);
+        assert_eq!(renderer.frames[0][2].material, red);
+    }
+
+    #[test]
+    fn translucent_draws_come_after_opaque_ones_back_to_front() {
+        let mut renderer = RecordingRenderer::default();
+        let opaque = renderer.upload_mesh(&triangle(1.0)).0;
+        let translucent = renderer.upload_mesh(&triangle(0.5)).0;
+        assert!(!renderer.has_translucent_vertices(opaque));
+        assert!(renderer.has_translucent_vertices(translucent));
+
+        let solid = Material::default();
+        let glass = Material { tint: glm::vec4(1.0, 1.0, 1.0, 0.5) };
+        // Opaque front to back, then translucent back to front, whether by tint or by vertex color
+        let order = drawn(&mut renderer, &[(translucent, solid, 1.0), (opaque, solid, 10.0), (opaque, glass, 5.0), (opaque, solid, 2.0), (translucent, solid, 20.0)]);
+        assert_eq!(order, vec![opaque, opaque, translucent, opaque, translucent]);
+        let depths: Vec<f32> = renderer.frames[0].iter().map(|command| -command.transform[(2, 3)]).collect();
+        assert_eq!(depths, vec![2.0, 10.0, 20.0, 5.0, 1.0]);
+    }
+}
@@ -0,0 +1,1142 @@
+extern crate nalgebra_glm as glm;
+
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::assets::{AssetManager, Handle};
+use crate::camera::{Camera, CameraController, FollowController, FreeFlyController, OrbitController, Projection};
+use crate::chunked_terrain::ChunkedTerrain;
+use crate::flight::FlightModel;
+use crate::components::{DoorController, FlightController, PathFollower, Spinner, World};
+use crate::json::Json;
+use crate::mesh::{Ground, Helicopter, Mesh, Terrain};
+use crate::physics::{RigidBody, Shape, TriangleMesh};
+use crate::renderer::{DirectionalLight, Lighting, Material, Renderer};
+use crate::scene_graph::{LodLevel, Node, SceneNode};
+use crate::terrain_generator::{Heightmap, TerrainGenerator};
+
+// Scenes described in JSON files, so that switching between them doesn't take recompiling:
+//
+//   {
+//     "meshes":  { "<name>": { "type": "obj", "path": "resources/thing.obj", "lods": true },
+//                  "<name>": { "type": "heightmap", "path": "resources/heights.png", "size": 400, "height": 12 }, ... },
+//     "nodes":   [ { "name": "thing", "mesh": "<name>", "position": [0, 1, 0], "tags": ["<tag>"],
+//                   "components": [ { "type": "spinner", "axis": "y", "speed": 1 } ], "children": [...] } ],
+//     "ground":  "<path of a node>",
+//     "lights":  [ { "type": "directional", "direction": [0, -1, 0], "color": [1, 1, 1] } ],
+//     "cameras": [ { "name": "overview", "position": [0, 5, 20], "controller": { "type": "free_fly" } } ]
+//   }
+//
+// Nodes are referred to by path: the names of the nodes from the top down, joined by slashes, as
+// in "helicopter_3/tail_rotor". Anything which is left out gets a default, see the parse
+// functions below for what each part can have.
+
+// Procedural terrain, for when the mesh of the ground can't be loaded. Chunks are loaded within
+// the distance of the camera, in world units.
+const CHUNK_SIZE: f32 = 64.0;
+const CHUNK_RESOLUTION: usize = 33;
+const CHUNK_LOAD_DISTANCE: f32 = 400.0;
+
+// The smallest fraction of the screen height each level of detail is used for
+const LOD_SCREEN_SIZES: [f32; 4] = [0.3, 0.1, 0.03, 0.0];
+
+const AXES: [&str; 3] = ["x", "y", "z"];
+
+// The parameters of every primitive, in the order its constructor takes them. The ones named
+// segments, rings or subdivisions are counts.
+const PRIMITIVES: [(&str, &[&str]); 9] = [
+    ("plane",     &["width", "depth", "x_segments", "z_segments"]),
+    ("cube",      &["size", "segments"]),
+    ("uv_sphere", &["radius", "segments", "rings"]),
+    ("icosphere", &["radius", "subdivisions"]),
+    ("cylinder",  &["radius", "height", "segments"]),
+    ("cone",      &["radius", "height", "segments"]),
+    ("torus",     &["major_radius", "minor_radius", "major_segments", "minor_segments"]),
+    ("capsule",   &["radius", "height", "segments", "rings"]),
+    ("disc",      &["radius", "segments"]),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HelicopterPart {
+    Body,
+    Door,
+    MainRotor,
+    TailRotor,
+}
+
+const HELICOPTER_PARTS: [(&str, HelicopterPart); 4] = [
+    ("body",       HelicopterPart::Body),
+    ("door",       HelicopterPart::Door),
+    ("main_rotor", HelicopterPart::MainRotor),
+    ("tail_rotor", HelicopterPart::TailRotor),
+];
+
+impl HelicopterPart {
+    fn of(self, helicopter: &Helicopter) -> &Mesh {
+        match self {
+            HelicopterPart::Body      => &helicopter.body,
+            HelicopterPart::Door      => &helicopter.door,
+            HelicopterPart::MainRotor => &helicopter.main_rotor,
+            HelicopterPart::TailRotor => &helicopter.tail_rotor,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        HELICOPTER_PARTS.iter().find(|(_, part)| *part == self).unwrap().0
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MeshSource {
+    Obj        { path: String },                       // With a single mesh in it
+    Helicopter { path: String, part: HelicopterPart }, // One part of a helicopter model
+    Primitive  { shape: String, parameters: Vec<f32>, color: [f32; 4] }, // See PRIMITIVES
+    Heightmap  { path: String, size: f32, height: f32, resolution: usize, craters: usize }, // See TerrainGenerator
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MeshDescription {
+    pub source : MeshSource,
+    pub lods   : bool, // Whether to simplify it into levels of detail
+}
+
+// What a node does on its own, see components.rs. Doors are described closed, where the node is.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ComponentDescription {
+    Spinner(Spinner),
+    PathFollower(PathFollower),
+    DoorController { axis: usize, travel: f32, speed: f32 },
+    RigidBody(RigidBodyDescription),
+    FlightController(FlightModel),
+}
+
+// In the coordinates of the node. The last two take the mesh of the node.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ShapeDescription {
+    Sphere { center: glm::Vec3, radius: f32 },
+    Box    { center: glm::Vec3, half_extents: glm::Vec3 },
+    Bounds, // The box around the mesh
+    Mesh,   // The triangles of the mesh
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RigidBodyDescription {
+    pub shape        : ShapeDescription,
+    pub mass         : f32, // 0 for bodies which nothing can move
+    pub drag         : f32,
+    pub angular_drag : f32,
+    pub restitution  : f32,
+    pub friction     : f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NodeDescription {
+    pub name            : Option<String>, // Without one, neither the node nor what is below it can be referred to
+    pub mesh            : Option<String>, // The name of one of the meshes
+    pub position        : glm::Vec3,
+    pub rotation        : glm::Vec3,
+    pub scale           : glm::Vec3,
+    pub reference_point : glm::Vec3,
+    pub tint            : glm::Vec4,
+    pub tags            : Vec<String>,    // For finding all nodes of a kind, like the helicopters
+    pub components      : Vec<ComponentDescription>,
+    pub children        : Vec<NodeDescription>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ControllerDescription {
+    FreeFly { speed: f32 },
+    Orbit   { distance: f32, min_distance: f32, zoom_speed: f32 }, // Around what is in front of the camera
+    Follow  { target: String, offset: glm::Vec3, look_at: glm::Vec3, stiffness: f32 }, // The target is a path
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CameraDescription {
+    pub name       : String,
+    pub position   : glm::Vec3,
+    pub yaw        : f32,
+    pub pitch      : f32,
+    pub projection : Projection,
+    pub controller : ControllerDescription,
+}
+
+impl Default for CameraDescription {
+    fn default() -> Self {
+        CameraDescription {
+            name       : "camera".to_string(),
+            position   : glm::vec3(0.0, 10.0, 50.0),
+            yaw        : 0.0,
+            pitch      : 0.0,
+            projection : Projection::Perspective { fov_y: 0.6, near: 1.0, far: 1000.0 },
+            controller : ControllerDescription::FreeFly { speed: 15.0 },
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SceneDescription {
+    pub meshes   : Vec<(String, MeshDescription)>,
+    pub nodes    : Vec<NodeDescription>,  // The children of the root of the scene
+    pub ground   : Option<String>,        // The path of the node to stand on, which shouldn't be transformed
+    pub lighting : Lighting,
+    pub cameras  : Vec<CameraDescription>, // Never empty
+}
+
+// Reading the parts of objects, with errors saying which part is wrong
+
+fn field<'a>(object: &'a Json, name: &str) -> Result<&'a Json, String> {
+    object.get(name).ok_or_else(|| format!("Missing {}", name))
+}
+
+fn number(object: &Json, name: &str) -> Result<f32, String> {
+    field(object, name)?.as_f64().map(|value| value as f32).ok_or_else(|| format!("{} should be a number", name))
+}
+
+fn number_or(object: &Json, name: &str, default: f32) -> Result<f32, String> {
+    if object.get(name).is_some() { number(object, name) } else { Ok(default) }
+}
+
+fn string<'a>(object: &'a Json, name: &str) -> Result<&'a str, String> {
+    field(object, name)?.as_str().ok_or_else(|| format!("{} should be a string", name))
+}
+
+fn optional_string(object: &Json, name: &str) -> Result<Option<String>, String> {
+    if object.get(name).is_some() { string(object, name).map(|value| Some(value.to_string())) } else { Ok(None) }
+}
+
+fn bool_or(object: &Json, name: &str, default: bool) -> Result<bool, String> {
+    match object.get(name) {
+        Some(value) => value.as_bool().ok_or_else(|| format!("{} should be true or false", name)),
+        None => Ok(default),
+    }
+}
+
+fn array<'a>(object: &'a Json, name: &str) -> Result<&'a [Json], String> {
+    match object.get(name) {
+        Some(value) => value.as_array().ok_or_else(|| format!("{} should be an array", name)),
+        None => Ok(&[]),
+    }
+}
+
+fn numbers_or<const N: usize>(object: &Json, name: &str, default: [f32; N]) -> Result<[f32; N], String> {
+    let items = match object.get(name) {
+        Some(value) => value.as_array().ok_or_else(|| format!("{} should be an array", name))?,
+        None => return Ok(default),
+    };
+    let mut numbers = [0.0; N];
+    if items.len() != N {
+        return Err(format!("{} should have {} numbers", name, N));
+    }
+    for (number, item) in numbers.iter_mut().zip(items) {
+        *number = item.as_f64().ok_or_else(|| format!("{} should have {} numbers", name, N))? as f32;
+    }
+    Ok(numbers)
+}
+
+fn vec3_or(object: &Json, name: &str, default: glm::Vec3) -> Result<glm::Vec3, String> {
+    numbers_or(object, name, [default.x, default.y, default.z]).map(|v| glm::make_vec3(&v))
+}
+
+fn vec3(object: &Json, name: &str) -> Result<glm::Vec3, String> {
+    field(object, name)?;
+    vec3_or(object, name, glm::zero())
+}
+
+fn axis(object: &Json) -> Result<usize, String> {
+    let name = string(object, "axis")?;
+    AXES.iter().position(|&axis| axis == name).ok_or_else(|| format!("Unknown axis {}, expected x, y or z", name))
+}
+
+fn vec3_json(v: &glm::Vec3) -> Json {
+    Json::from(vec![v.x, v.y, v.z])
+}
+
+impl MeshDescription {
+    fn parse(json: &Json) -> Result<Self, String> {
+        let source = match string(json, "type")? {
+            "obj" => MeshSource::Obj { path: string(json, "path")?.to_string() },
+            "helicopter" => {
+                let part_name = string(json, "part")?;
+                let part = HELICOPTER_PARTS.iter().find(|(name, _)| *name == part_name).map(|&(_, part)| part)
+                    .ok_or_else(|| format!("Unknown helicopter part {}", part_name))?;
+                MeshSource::Helicopter { path: string(json, "path")?.to_string(), part }
+            }
+            "heightmap" => {
+                let defaults = TerrainGenerator::default();
+                let count = |name: &str, default: usize, least: usize| match number_or(json, name, default as f32)? {
+                    value if value.fract() == 0.0 && value >= least as f32 => Ok(value as usize),
+                    _ => Err(format!("{} of the heightmap should be a whole number, at least {}", name, least)),
+                };
+                MeshSource::Heightmap {
+                    path       : string(json, "path")?.to_string(),
+                    size       : number_or(json, "size", defaults.size)?,
+                    height     : number_or(json, "height", defaults.height)?,
+                    resolution : count("resolution", defaults.resolution, 2)?,
+                    craters    : count("craters", 0, 0)?,
+                }
+            }
+            shape => {
+                let (shape, names) = PRIMITIVES.iter().find(|(name, _)| *name == shape)
+                    .ok_or_else(|| format!("Unknown type of mesh {}", shape))?;
+                let parameters = names.iter().map(|name| number(json, name)).collect::<Result<Vec<f32>, String>>()?;
+                MeshSource::Primitive { shape: shape.to_string(), parameters, color: numbers_or(json, "color", [1.0; 4])? }
+            }
+        };
+        Ok(MeshDescription { source, lods: bool_or(json, "lods", false)? })
+    }
+
+    fn to_json(&self) -> Json {
+        let mut fields = match &self.source {
+            MeshSource::Obj { path } => vec![("type", Json::from("obj")), ("path", Json::from(path.as_str()))],
+            MeshSource::Helicopter { path, part } => vec![
+                ("type", Json::from("helicopter")),
+                ("path", Json::from(path.as_str())),
+                ("part", Json::from(part.name())),
+            ],
+            MeshSource::Primitive { shape, parameters, color } => {
+                let names = PRIMITIVES.iter().find(|(name, _)| name == shape).map_or(&[][..], |(_, names)| names);
+                let mut fields = vec![("type", Json::from(shape.as_str()))];
+                fields.extend(names.iter().zip(parameters).map(|(&name, &value)| (name, Json::from(value))));
+                fields.push(("color", Json::from(color.to_vec())));
+                fields
+            }
+            MeshSource::Heightmap { path, size, height, resolution, craters } => vec![
+                ("type", Json::from("heightmap")),
+                ("path", Json::from(path.as_str())),
+                ("size", Json::from(*size)),
+                ("height", Json::from(*height)),
+                ("resolution", Json::from(*resolution)),
+                ("craters", Json::from(*craters)),
+            ],
+        };
+        if self.lods {
+            fields.push(("lods", Json::from(true)));
+        }
+        Json::object(fields)
+    }
+}
+
+impl ComponentDescription {
+    fn parse(json: &Json) -> Result<Self, String> {
+        match string(json, "type")? {
+            "spinner" => Ok(ComponentDescription::Spinner(Spinner { axis: axis(json)?, speed: number(json, "speed")? })),
+            "path_follower" => Ok(ComponentDescription::PathFollower(PathFollower {
+                phase    : number_or(json, "phase", 0.0)?,
+                altitude : number_or(json, "altitude", 0.0)?,
+            })),
+            "door_controller" => Ok(ComponentDescription::DoorController {
+                axis   : axis(json)?,
+                travel : number(json, "travel")?,
+                speed  : number(json, "speed")?,
+            }),
+            "rigid_body" => {
+                let defaults = RigidBody::new(Shape::Sphere { center: glm::zero(), radius: 0.0 }, 0.0);
+                Ok(ComponentDescription::RigidBody(RigidBodyDescription {
+                    shape        : ShapeDescription::parse(field(json, "shape")?)?,
+                    mass         : number(json, "mass")?,
+                    drag         : number_or(json, "drag", defaults.drag)?,
+                    angular_drag : number_or(json, "angular_drag", defaults.angular_drag)?,
+                    restitution  : number_or(json, "restitution", defaults.restitution)?,
+                    friction     : number_or(json, "friction", defaults.friction)?,
+                }))
+            }
+            "flight_controller" => {
+                let defaults = FlightModel::default();
+                Ok(ComponentDescription::FlightController(FlightModel {
+                    max_thrust   : number_or(json, "max_thrust", defaults.max_thrust)?,
+                    max_tilt     : number_or(json, "max_tilt", defaults.max_tilt)?,
+                    tilt_rate    : number_or(json, "tilt_rate", defaults.tilt_rate)?,
+                    yaw_rate     : number_or(json, "yaw_rate", defaults.yaw_rate)?,
+                    spool_rate   : number_or(json, "spool_rate", defaults.spool_rate)?,
+                    drag         : number_or(json, "drag", defaults.drag)?,
+                    rotor_radius : number_or(json, "rotor_radius", defaults.rotor_radius)?,
+                    rotor_height : number_or(json, "rotor_height", defaults.rotor_height)?,
+                }))
+            }
+            other => Err(format!("Unknown type of component {}", other)),
+        }
+    }
+
+    fn to_json(self) -> Json {
+        match self {
+            ComponentDescription::Spinner(Spinner { axis, speed }) => Json::object(vec![
+                ("type", Json::from("spinner")), ("axis", Json::from(AXES[axis])), ("speed", Json::from(speed)),
+            ]),
+            ComponentDescription::PathFollower(PathFollower { phase, altitude }) => Json::object(vec![
+                ("type", Json::from("path_follower")), ("phase", Json::from(phase)), ("altitude", Json::from(altitude)),
+            ]),
+            ComponentDescription::DoorController { axis, travel, speed } => Json::object(vec![
+                ("type", Json::from("door_controller")), ("axis", Json::from(AXES[axis])), ("travel", Json::from(travel)), ("speed", Json::from(speed)),
+            ]),
+            ComponentDescription::RigidBody(body) => Json::object(vec![
+                ("type", Json::from("rigid_body")),
+                ("shape", body.shape.to_json()),
+                ("mass", Json::from(body.mass)),
+                ("drag", Json::from(body.drag)),
+                ("angular_drag", Json::from(body.angular_drag)),
+                ("restitution", Json::from(body.restitution)),
+                ("friction", Json::from(body.friction)),
+            ]),
+            ComponentDescription::FlightController(model) => Json::object(vec![
+                ("type", Json::from("flight_controller")),
+                ("max_thrust", Json::from(model.max_thrust)),
+                ("max_tilt", Json::from(model.max_tilt)),
+                ("tilt_rate", Json::from(model.tilt_rate)),
+                ("yaw_rate", Json::from(model.yaw_rate)),
+                ("spool_rate", Json::from(model.spool_rate)),
+                ("drag", Json::from(model.drag)),
+                ("rotor_radius", Json::from(model.rotor_radius)),
+                ("rotor_height", Json::from(model.rotor_height)),
+            ]),
+        }
+    }
+}
+
+impl ShapeDescription {
+    fn parse(json: &Json) -> Result<Self, String> {
+        match string(json, "type")? {
+            "sphere" => Ok(ShapeDescription::Sphere { center: vec3_or(json, "center", glm::zero())?, radius: number(json, "radius")? }),
+            "box" => Ok(ShapeDescription::Box { center: vec3_or(json, "center", glm::zero())?, half_extents: vec3(json, "half_extents")? }),
+            "bounds" => Ok(ShapeDescription::Bounds),
+            "mesh" => Ok(ShapeDescription::Mesh),
+            other => Err(format!("Unknown type of shape {}", other)),
+        }
+    }
+
+    fn to_json(self) -> Json {
+        match self {
+            ShapeDescription::Sphere { center, radius } => Json::object(vec![
+                ("type", Json::from("sphere")), ("center", vec3_json(&center)), ("radius", Json::from(radius)),
+            ]),
+            ShapeDescription::Box { center, half_extents } => Json::object(vec![
+                ("type", Json::from("box")), ("center", vec3_json(&center)), ("half_extents", vec3_json(&half_extents)),
+            ]),
+            ShapeDescription::Bounds => Json::object(vec![("type", Json::from("bounds"))]),
+            ShapeDescription::Mesh => Json::object(vec![("type", Json::from("mesh"))]),
+        }
+    }
+}
+
+impl RigidBodyDescription {
+    fn instantiate(&self, mesh: Option<&Mesh>) -> Result<RigidBody, String> {
+        let mesh = || mesh.ok_or_else(|| "The shape of the rigid body is made from the mesh of the node, which has none".to_string());
+        let shape = match self.shape {
+            ShapeDescription::Sphere { center, radius } => Shape::Sphere { center, radius },
+            ShapeDescription::Box { center, half_extents } => Shape::Box { center, half_extents },
+            ShapeDescription::Bounds => Shape::Box { center: mesh()?.aabb.center(), half_extents: mesh()?.aabb.half_extents() },
+            ShapeDescription::Mesh => Shape::Mesh(Arc::new(TriangleMesh::new(mesh()?))),
+        };
+        let mut body = RigidBody::new(shape, self.mass);
+        body.drag = self.drag;
+        body.angular_drag = self.angular_drag;
+        body.restitution = self.restitution;
+        body.friction = self.friction;
+        Ok(body)
+    }
+
+    // Keeping the shape, which is the same as the body's, and the mass as it was written if the
+    // body still has it, rather than the inverse of its inverse
+    fn update(&mut self, body: &RigidBody) {
+        if 1.0 / self.mass != body.inverse_mass {
+            self.mass = body.mass();
+        }
+        self.drag = body.drag;
+        self.angular_drag = body.angular_drag;
+        self.restitution = body.restitution;
+        self.friction = body.friction;
+    }
+}
+
+impl NodeDescription {
+    fn parse(json: &Json) -> Result<Self, String> {
+        let name = optional_string(json, "name")?;
+        let parse = || -> Result<Self, String> {
+            Ok(NodeDescription {
+                name            : name.clone(),
+                mesh            : optional_string(json, "mesh")?,
+                position        : vec3_or(json, "position", glm::zero())?,
+                rotation        : vec3_or(json, "rotation", glm::zero())?,
+                scale           : vec3_or(json, "scale", glm::vec3(1.0, 1.0, 1.0))?,
+                reference_point : vec3_or(json, "reference_point", glm::zero())?,
+                tint            : glm::make_vec4(&numbers_or(json, "tint", [1.0; 4])?),
+                tags            : array(json, "tags")?.iter()
+                    .map(|tag| tag.as_str().map(str::to_string).ok_or_else(|| "tags should be strings".to_string()))
+                    .collect::<Result<_, _>>()?,
+                components      : array(json, "components")?.iter().map(ComponentDescription::parse).collect::<Result<_, _>>()?,
+                children        : array(json, "children")?.iter().map(NodeDescription::parse).collect::<Result<_, _>>()?,
+            })
+        };
+        parse().map_err(|e| format!("Node {}: {}", name.as_deref().unwrap_or("without a name"), e))
+    }
+
+    // Leaving out what is the same as the default
+    fn to_json(&self) -> Json {
+        let mut fields = vec![];
+        if let Some(name) = &self.name {
+            fields.push(("name", Json::from(name.as_str())));
+        }
+        if let Some(mesh) = &self.mesh {
+            fields.push(("mesh", Json::from(mesh.as_str())));
+        }
+        for (name, value, default) in [
+            ("position", &self.position, glm::zero()),
+            ("rotation", &self.rotation, glm::zero()),
+            ("scale", &self.scale, glm::vec3(1.0, 1.0, 1.0)),
+            ("reference_point", &self.reference_point, glm::zero()),
+        ] {
+            if *value != default {
+                fields.push((name, vec3_json(value)));
+            }
+        }
+        if self.tint != glm::vec4(1.0, 1.0, 1.0, 1.0) {
+            fields.push(("tint", Json::from(self.tint.as_slice().to_vec())));
+        }
+        if !self.tags.is_empty() {
+            fields.push(("tags", Json::from(self.tags.clone())));
+        }
+        if !self.components.is_empty() {
+            fields.push(("components", Json::Array(self.components.iter().map(|component| component.to_json()).collect())));
+        }
+        if !self.children.is_empty() {
+            fields.push(("children", Json::Array(self.children.iter().map(NodeDescription::to_json).collect())));
+        }
+        Json::object(fields)
+    }
+}
+
+fn parse_projection(json: &Json) -> Result<Projection, String> {
+    let (near, far) = (number_or(json, "near", 1.0)?, number_or(json, "far", 1000.0)?);
+    match string(json, "type")? {
+        "perspective" => Ok(Projection::Perspective { fov_y: number(json, "fov_y")?, near, far }),
+        "orthographic" => Ok(Projection::Orthographic { height: number(json, "height")?, near, far }),
+        other => Err(format!("Unknown type of projection {}", other)),
+    }
+}
+
+fn projection_json(projection: &Projection) -> Json {
+    match *projection {
+        Projection::Perspective { fov_y, near, far } => Json::object(vec![
+            ("type", Json::from("perspective")), ("fov_y", Json::from(fov_y)), ("near", Json::from(near)), ("far", Json::from(far)),
+        ]),
+        Projection::Orthographic { height, near, far } => Json::object(vec![
+            ("type", Json::from("orthographic")), ("height", Json::from(height)), ("near", Json::from(near)), ("far", Json::from(far)),
+        ]),
+    }
+}
+
+impl ControllerDescription {
+    fn parse(json: &Json) -> Result<Self, String> {
+        match string(json, "type")? {
+            "free_fly" => Ok(ControllerDescription::FreeFly { speed: number_or(json, "speed", 15.0)? }),
+            "orbit" => Ok(ControllerDescription::Orbit {
+                distance     : number_or(json, "distance", 40.0)?,
+                min_distance : number_or(json, "min_distance", 5.0)?,
+                zoom_speed   : number_or(json, "zoom_speed", 20.0)?,
+            }),
+            "follow" => Ok(ControllerDescription::Follow {
+                target    : string(json, "target")?.to_string(),
+                offset    : vec3_or(json, "offset", glm::vec3(0.0, 5.0, 20.0))?,
+                look_at   : vec3_or(json, "look_at", glm::zero())?,
+                stiffness : number_or(json, "stiffness", 4.0)?,
+            }),
+            other => Err(format!("Unknown type of camera controller {}", other)),
+        }
+    }
+
+    fn to_json(&self) -> Json {
+        match self {
+            ControllerDescription::FreeFly { speed } => Json::object(vec![("type", Json::from("free_fly")), ("speed", Json::from(*speed))]),
+            ControllerDescription::Orbit { distance, min_distance, zoom_speed } => Json::object(vec![
+                ("type", Json::from("orbit")),
+                ("distance", Json::from(*distance)),
+                ("min_distance", Json::from(*min_distance)),
+                ("zoom_speed", Json::from(*zoom_speed)),
+            ]),
+            ControllerDescription::Follow { target, offset, look_at, stiffness } => Json::object(vec![
+                ("type", Json::from("follow")),
+                ("target", Json::from(target.as_str())),
+                ("offset", vec3_json(offset)),
+                ("look_at", vec3_json(look_at)),
+                ("stiffness", Json::from(*stiffness)),
+            ]),
+        }
+    }
+}
+
+impl CameraDescription {
+    fn parse(json: &Json) -> Result<Self, String> {
+        let default = CameraDescription::default();
+        let name = string(json, "name")?.to_string();
+        let parse = || -> Result<Self, String> {
+            Ok(CameraDescription {
+                name       : name.clone(),
+                position   : vec3_or(json, "position", default.position)?,
+                yaw        : number_or(json, "yaw", default.yaw)?,
+                pitch      : number_or(json, "pitch", default.pitch)?,
+                projection : json.get("projection").map_or(Ok(default.projection), parse_projection)?,
+                controller : json.get("controller").map_or(Ok(default.controller.clone()), ControllerDescription::parse)?,
+            })
+        };
+        parse().map_err(|e| format!("Camera {}: {}", name, e))
+    }
+
+    fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("name", Json::from(self.name.as_str())),
+            ("position", vec3_json(&self.position)),
+            ("yaw", Json::from(self.yaw)),
+            ("pitch", Json::from(self.pitch)),
+            ("projection", projection_json(&self.projection)),
+            ("controller", self.controller.to_json()),
+        ])
+    }
+
+    // The camera, and what moves it. Follow cameras need their target to be in the scene.
+    pub fn instantiate(&self, scene: &LoadedScene, aspect_ratio: f32) -> Result<(Camera, Box<dyn CameraController>), String> {
+        let camera = Camera::new(self.position, self.yaw, self.pitch, self.projection, aspect_ratio);
+        let mut controller: Box<dyn CameraController> = match &self.controller {
+            ControllerDescription::FreeFly { speed } => Box::new(FreeFlyController { speed: *speed }),
+            ControllerDescription::Orbit { distance, min_distance, zoom_speed } => Box::new(OrbitController {
+                target       : glm::zero(),
+                distance     : *distance,
+                min_distance : *min_distance,
+                zoom_speed   : *zoom_speed,
+            }),
+            ControllerDescription::Follow { target, offset, look_at, stiffness } => Box::new(FollowController {
+                target    : scene.find(target).ok_or_else(|| format!("Camera {}: no node {} to follow", self.name, target))?,
+                offset    : *offset,
+                look_at   : *look_at,
+                stiffness : *stiffness,
+            }),
+        };
+        controller.activate(&camera);
+        Ok((camera, controller))
+    }
+}
+
+fn parse_lighting(lights: &[Json]) -> Result<Lighting, String> {
+    let mut lighting = Lighting { ambient: glm::zero(), lights: vec![] };
+    for light in lights {
+        let color = vec3_or(light, "color", glm::vec3(1.0, 1.0, 1.0))?;
+        match string(light, "type")? {
+            "ambient" => lighting.ambient += color,
+            "directional" => lighting.lights.push(DirectionalLight { direction: vec3_or(light, "direction", glm::vec3(0.0, -1.0, 0.0))?, color }),
+            other => return Err(format!("Unknown type of light {}", other)),
+        }
+    }
+    Ok(lighting)
+}
+
+fn lighting_json(lighting: &Lighting) -> Json {
+    let mut lights = vec![];
+    if lighting.ambient != glm::Vec3::zeros() {
+        lights.push(Json::object(vec![("type", Json::from("ambient")), ("color", vec3_json(&lighting.ambient))]));
+    }
+    lights.extend(lighting.lights.iter().map(|light| Json::object(vec![
+        ("type", Json::from("directional")),
+        ("direction", vec3_json(&light.direction)),
+        ("color", vec3_json(&light.color)),
+    ])));
+    Json::Array(lights)
+}
+
+impl SceneDescription {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let json = Json::parse(text)?;
+        let meshes = match json.get("meshes") {
+            Some(Json::Object(meshes)) => meshes.iter()
+                .map(|(name, mesh)| Ok((name.clone(), MeshDescription::parse(mesh).map_err(|e| format!("Mesh {}: {}", name, e))?)))
+                .collect::<Result<Vec<_>, String>>()?,
+            Some(_) => return Err("meshes should be an object".to_string()),
+            None => vec![],
+        };
+        let lighting = match json.get("lights") {
+            Some(_) => parse_lighting(array(&json, "lights")?)?,
+            None => Lighting::default(),
+        };
+        let mut cameras = array(&json, "cameras")?.iter().map(CameraDescription::parse).collect::<Result<Vec<_>, _>>()?;
+        if cameras.is_empty() {
+            cameras.push(CameraDescription::default());
+        }
+        Ok(SceneDescription {
+            meshes,
+            nodes    : array(&json, "nodes")?.iter().map(NodeDescription::parse).collect::<Result<_, _>>()?,
+            ground   : optional_string(&json, "ground")?,
+            lighting,
+            cameras,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        SceneDescription::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn to_json(&self) -> Json {
+        let mut fields = vec![
+            ("meshes", Json::Object(self.meshes.iter().map(|(name, mesh)| (name.clone(), mesh.to_json())).collect())),
+            ("nodes", Json::Array(self.nodes.iter().map(NodeDescription::to_json).collect())),
+        ];
+        if let Some(ground) = &self.ground {
+            fields.push(("ground", Json::from(ground.as_str())));
+        }
+        fields.push(("lights", lighting_json(&self.lighting)));
+        fields.push(("cameras", Json::Array(self.cameras.iter().map(CameraDescription::to_json).collect())));
+        Json::object(fields)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        fs::write(path, self.to_json().pretty()).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    // Start loading the meshes in the files, in the background
+    pub fn request_meshes(&self, assets: &mut AssetManager) -> Vec<MeshRequest> {
+        self.meshes.iter().map(|(_, mesh)| match &mesh.source {
+            MeshSource::Obj { path } => MeshRequest::Obj(assets.mesh(path)),
+            MeshSource::Helicopter { path, part } => MeshRequest::Helicopter(assets.helicopter(path), *part),
+            MeshSource::Primitive { .. } => MeshRequest::Primitive,
+            MeshSource::Heightmap { path, .. } => MeshRequest::Heightmap(assets.heightmap(path)),
+        }).collect()
+    }
+}
+
+// A mesh of the scene on its way, in the same order as SceneDescription::meshes
+pub enum MeshRequest {
+    Obj(Handle<Mesh>),
+    Helicopter(Handle<Helicopter>, HelicopterPart),
+    Primitive, // Built once it's needed, which is quick
+    Heightmap(Handle<Heightmap>), // Turned into terrain once it's needed
+}
+
+// A mesh of the scene, once it has loaded
+enum LoadedMesh {
+    Shared(Arc<Mesh>),
+    Part(Arc<Helicopter>, HelicopterPart),
+    Built(Mesh),
+}
+
+impl LoadedMesh {
+    fn get(&self) -> &Mesh {
+        match self {
+            LoadedMesh::Shared(mesh) => mesh,
+            LoadedMesh::Part(helicopter, part) => part.of(helicopter),
+            LoadedMesh::Built(mesh) => mesh,
+        }
+    }
+}
+
+impl MeshRequest {
+    fn wait(&self, source: &MeshSource) -> Result<LoadedMesh, String> {
+        match (self, source) {
+            (MeshRequest::Obj(handle), _) => handle.wait().map(LoadedMesh::Shared),
+            (MeshRequest::Helicopter(handle, part), _) => handle.wait().map(|helicopter| LoadedMesh::Part(helicopter, *part)),
+            (MeshRequest::Primitive, MeshSource::Primitive { shape, parameters, color }) => build_primitive(shape, parameters, *color).map(LoadedMesh::Built),
+            (MeshRequest::Primitive, _) => Err("Not a primitive".to_string()),
+            (MeshRequest::Heightmap(handle), &MeshSource::Heightmap { size, height, resolution, craters, .. }) => {
+                let generator = TerrainGenerator { size, height, resolution, craters, ..TerrainGenerator::default() };
+                handle.wait().map(|image| LoadedMesh::Built(generator.generate_from_heightmap(&image)))
+            }
+            (MeshRequest::Heightmap(_), _) => Err("Not a heightmap".to_string()),
+        }
+    }
+}
+
+fn build_primitive(shape: &str, p: &[f32], color: [f32; 4]) -> Result<Mesh, String> {
+    let names = PRIMITIVES.iter().find(|(name, _)| *name == shape).map(|(_, names)| names).ok_or_else(|| format!("Unknown primitive {}", shape))?;
+    for (name, value) in names.iter().zip(p) {
+        let is_count = name.ends_with("segments") || name.ends_with("rings") || *name == "subdivisions";
+        if is_count && (value.fract() != 0.0 || *value < 0.0) {
+            return Err(format!("{} of the {} should be a whole number", name, shape));
+        }
+        if !is_count && (value.is_nan() || *value <= 0.0) {
+            return Err(format!("{} of the {} should be above 0", name, shape));
+        }
+    }
+    let n = |i: usize| p[i] as usize;
+    Ok(match shape {
+        "plane"     => Mesh::plane(p[0], p[1], n(2), n(3), color),
+        "cube"      => Mesh::cube(p[0], n(1), color),
+        "uv_sphere" => Mesh::uv_sphere(p[0], n(1), n(2), color),
+        "icosphere" => Mesh::icosphere(p[0], n(1), color),
+        "cylinder"  => Mesh::cylinder(p[0], p[1], n(2), color),
+        "cone"      => Mesh::cone(p[0], p[1], n(2), color),
+        "torus"     => Mesh::torus(p[0], p[1], n(2), n(3), color),
+        "capsule"   => Mesh::capsule(p[0], p[1], n(2), n(3), color),
+        "disc"      => Mesh::disc(p[0], n(1), color),
+        _ => unreachable!(),
+    })
+}
+
+// Upload a mesh to both the GL renderer and the software reference renderer. Both hand out mesh
+// ids in upload order, so the ids stored in the scene graph are valid for either of them.
+fn upload_mesh(renderer: &mut dyn Renderer, reference: &mut dyn Renderer, mesh: &Mesh) -> (u32, i32) {
+    let (mesh_id, index_count) = renderer.upload_mesh(mesh);
+    let (reference_id, _) = reference.upload_mesh(mesh);
+    debug_assert_eq!(mesh_id, reference_id);
+    (mesh_id, index_count)
+}
+
+// Simplify the mesh into progressively coarser levels of detail, and upload all of them
+fn upload_lods(renderer: &mut dyn Renderer, reference: &mut dyn Renderer, mesh: &Mesh, name: &str) -> Vec<LodLevel> {
+    let before = std::time::Instant::now();
+    let lods = mesh.generate_lods(LOD_SCREEN_SIZES.len());
+    println!("Simplified {} in {:.3}ms:", name, before.elapsed().as_micros() as f32 / 1e3);
+
+    lods.iter().zip(LOD_SCREEN_SIZES.iter()).map(|(lod, &min_screen_size)| {
+        println!("    {} triangles, error at most {:.4}", lod.mesh.triangle_count(), lod.max_error);
+        let (vao_id, index_count) = upload_mesh(renderer, reference, &lod.mesh);
+        LodLevel { vao_id, index_count, min_screen_size }
+    }).collect()
+}
+
+// The ground the scene stands on: the mesh of the ground node if there is one, otherwise
+// endless terrain generated around the camera
+pub enum Surface {
+    Model(Terrain),
+    Streamed(ChunkedTerrain),
+    None,
+}
+
+struct NoGround;
+
+impl Ground for NoGround {
+    fn height_at(&self, _x: f32, _z: f32) -> Option<f32> {
+        None
+    }
+
+    fn normal_at(&self, _x: f32, _z: f32) -> Option<glm::Vec3> {
+        None
+    }
+}
+
+impl Surface {
+    pub fn ground(&self) -> &dyn Ground {
+        match self {
+            Surface::Model(terrain) => terrain,
+            Surface::Streamed(chunks) => chunks,
+            Surface::None => &NoGround,
+        }
+    }
+}
+
+// A scene description turned into a scene graph, with its meshes uploaded
+pub struct LoadedScene {
+    pub root        : Node,
+    pub description : SceneDescription,
+    pub nodes       : Vec<*mut SceneNode>,        // Those of the description, depth first
+    pub world       : World,
+    pub ground      : Option<*mut SceneNode>,
+    pub surface     : Surface,
+}
+
+// The meshes of a scene, uploaded
+struct Uploaded {
+    vao_id      : u32,
+    index_count : i32,
+    lods        : Vec<LodLevel>,
+    aabb        : crate::bounds::Aabb,
+}
+
+// Siblings with the same name couldn't be told apart in paths. The prefix is as for build_node.
+fn check_names(siblings: &[NodeDescription], prefix: Option<&str>) -> Result<(), String> {
+    for (i, sibling) in siblings.iter().enumerate() {
+        if let Some(name) = &sibling.name {
+            if siblings[..i].iter().any(|other| other.name.as_ref() == Some(name)) {
+                let path = match prefix {
+                    Some(prefix) if !prefix.is_empty() => format!("{}/{}", prefix, name),
+                    _ => name.clone(),
+                };
+                return Err(format!("There is more than one node {}", path));
+            }
+        }
+    }
+    Ok(())
+}
+
+impl LoadedScene {
+    // Meshes which fail to load are left out of the scene, with a message. Helicopters are
+    // replaced by a placeholder instead, and the ground by procedural terrain.
+    pub fn build(description: SceneDescription, requests: &[MeshRequest], renderer: &mut dyn Renderer, reference: &mut dyn Renderer) -> Result<LoadedScene, String> {
+        let mut loaded = vec![];
+        let mut uploaded = vec![];
+        let mut missing_helicopters: Vec<String> = vec![];
+        for ((name, mesh), request) in description.meshes.iter().zip(requests) {
+            let mesh_data = match (request.wait(&mesh.source), &mesh.source) {
+                (Err(e), MeshSource::Helicopter { part, .. }) => {
+                    // Once for every model, rather than for every part
+                    if !missing_helicopters.contains(&e) {
+                        println!("{}, using a placeholder helicopter", e);
+                        missing_helicopters.push(e);
+                    }
+                    Ok(LoadedMesh::Built(part.of(&Helicopter::placeholder()).clone()))
+                }
+                (result, _) => result,
+            };
+            match mesh_data {
+                Ok(mesh_data) => {
+                    let upload = if mesh.lods {
+                        let lods = upload_lods(renderer, reference, mesh_data.get(), name);
+                        Uploaded { vao_id: lods[0].vao_id, index_count: lods[0].index_count, lods, aabb: mesh_data.get().aabb }
+                    } else {
+                        let (vao_id, index_count) = upload_mesh(renderer, reference, mesh_data.get());
+                        Uploaded { vao_id, index_count, lods: vec![], aabb: mesh_data.get().aabb }
+                    };
+                    loaded.push(Some(mesh_data));
+                    uploaded.push(Some(upload));
+                }
+                Err(e) => {
+                    println!("Mesh {}: {}", name, e);
+                    loaded.push(None);
+                    uploaded.push(None);
+                }
+            }
+        }
+
+        let mut scene = LoadedScene {
+            root        : SceneNode::new(),
+            description : description.clone(),
+            nodes       : vec![],
+            world       : World::default(),
+            ground      : None,
+            surface     : Surface::None,
+        };
+        check_names(&description.nodes, Some(""))?;
+        for node in &description.nodes {
+            let child = scene.build_node(node, Some(""), &loaded, &uploaded)?;
+            scene.root.add_child(unsafe { &*child });
+        }
+
+        if let Some(path) = &description.ground {
+            scene.ground = Some(scene.find(path).ok_or_else(|| format!("No ground node {}", path))?);
+            if let Surface::None = scene.surface {
+                println!("The ground has no mesh, generating the terrain around the camera instead.");
+                scene.surface = Surface::Streamed(ChunkedTerrain::new(TerrainGenerator::default(), CHUNK_SIZE, CHUNK_RESOLUTION, CHUNK_LOAD_DISTANCE));
+            }
+        }
+        Ok(scene)
+    }
+
+    // The prefix is the path of the parent, empty at the top and None below nodes without a name
+    fn build_node(&mut self, description: &NodeDescription, prefix: Option<&str>, loaded: &[Option<LoadedMesh>], uploaded: &[Option<Uploaded>]) -> Result<*mut SceneNode, String> {
+        let mesh_index = match &description.mesh {
+            Some(mesh) => Some(self.description.meshes.iter().position(|(name, _)| name == mesh).ok_or_else(|| format!("No mesh {}", mesh))?),
+            None => None,
+        };
+        let mut node = match mesh_index.and_then(|i| uploaded[i].as_ref()) {
+            Some(upload) => {
+                let mut node = SceneNode::from_vao(upload.vao_id, upload.index_count);
+                node.local_bounds = Some(upload.aabb);
+                node.lods = upload.lods.clone();
+                node
+            }
+            None => SceneNode::new(),
+        };
+        node.position = description.position;
+        node.rotation = description.rotation;
+        node.scale = description.scale;
+        node.reference_point = description.reference_point;
+        node.material = Material { tint: description.tint };
+        node.name = description.name.clone();
+        node.tags = description.tags.clone();
+        let node_ptr = &mut **node as *mut SceneNode;
+        self.nodes.push(node_ptr);
+
+        let path = match (prefix, &description.name) {
+            (Some(""), Some(name)) => Some(name.clone()),
+            (Some(prefix), Some(name)) => Some(format!("{}/{}", prefix, name)),
+            _ => None,
+        };
+        if path.is_some() && self.description.ground == path {
+            if let Some(mesh) = mesh_index.and_then(|i| loaded[i].as_ref()) {
+                self.surface = Surface::Model(Terrain::new(mesh.get()));
+            }
+        }
+
+        // Flight controllers are set up once the rotors below them are
+        let mut flight: Option<FlightModel> = None;
+        for component in &description.components {
+            let duplicate = match *component {
+                ComponentDescription::Spinner(spinner) => self.world.spinners.insert(node_ptr, spinner).is_some(),
+                ComponentDescription::PathFollower(follower) => self.world.followers.insert(node_ptr, follower).is_some(),
+                ComponentDescription::DoorController { axis, travel, speed } => {
+                    self.world.doors.insert(node_ptr, DoorController::new(&node, axis, travel, speed)).is_some()
+                }
+                ComponentDescription::RigidBody(body) => {
+                    // The physics moves nodes as if their position were in world coordinates
+                    if prefix != Some("") {
+                        return Err(format!("Node {} has a rigid body, which only nodes at the top of the scene can have",
+                                           path.as_deref().unwrap_or("without a name")));
+                    }
+                    let mesh = mesh_index.and_then(|i| loaded[i].as_ref()).map(LoadedMesh::get);
+                    let body = body.instantiate(mesh).map_err(|e| format!("Node {}: {}", path.as_deref().unwrap_or("without a name"), e))?;
+                    self.world.bodies.insert(node_ptr, body).is_some()
+                }
+                ComponentDescription::FlightController(model) => flight.replace(model).is_some(),
+            };
+            if duplicate {
+                return Err(format!("Node {} has more than one component of a kind", path.as_deref().unwrap_or("without a name")));
+            }
+        }
+        check_names(&description.children, path.as_deref())?;
+        for child in &description.children {
+            let child = self.build_node(child, path.as_deref(), loaded, uploaded)?;
+            node.add_child(unsafe { &*child });
+        }
+        if let Some(model) = flight {
+            if self.world.bodies.get(node_ptr).is_none() {
+                return Err(format!("Node {} has a flight controller but no rigid body", path.as_deref().unwrap_or("without a name")));
+            }
+            let rotors = self.world.spinners.iter()
+                .filter(|&(rotor, _)| !std::ptr::eq(rotor, node_ptr) && node.contains(rotor))
+                .map(|(rotor, spinner)| (rotor, spinner.speed))
+                .collect();
+            self.world.aircraft.insert(node_ptr, FlightController::new(&node, model, rotors));
+        }
+        Ok(node_ptr)
+    }
+
+    // The node at a path, like "helicopter_3/tail_rotor"
+    pub fn find(&self, path: &str) -> Option<*mut SceneNode> {
+        self.root.find(path)
+    }
+
+    pub fn path_of(&self, node: *const SceneNode) -> Option<String> {
+        self.root.path_of(node)
+    }
+
+    // Every node with a tag, in the order of the description
+    pub fn tagged(&self, tag: &str) -> Vec<*mut SceneNode> {
+        self.root.find_tagged(tag)
+    }
+
+    // The description of the scene as it is now, with the cameras where they are and the
+    // components as they are set up now, leaving out those which were taken off. Doors are left
+    // closed, as that's where they are loaded.
+    pub fn describe(&self, cameras: &[Camera]) -> SceneDescription {
+        let mut description = self.description.clone();
+        let mut nodes = self.nodes.iter();
+        fn update(descriptions: &mut [NodeDescription], nodes: &mut std::slice::Iter<*mut SceneNode>, world: &World) {
+            for description in descriptions {
+                let node = unsafe { &**nodes.next().unwrap() };
+                description.position = node.position;
+                description.rotation = node.rotation;
+                description.scale = node.scale;
+                description.reference_point = node.reference_point;
+                description.tint = node.material.tint;
+                let mut closed = None;
+                description.components.retain_mut(|component| match component {
+                    ComponentDescription::Spinner(spinner) => world.spinners.get(node).map(|current| {
+                        *spinner = *current;
+                        // Rotors as they turn at full speed, whatever the engine is doing now
+                        if let Some(speed) = world.aircraft.iter().find_map(|(_, aircraft)| aircraft.full_speed(node)) {
+                            spinner.speed = speed;
+                        }
+                    }).is_some(),
+                    ComponentDescription::PathFollower(follower) => world.followers.get(node).map(|current| *follower = *current).is_some(),
+                    ComponentDescription::DoorController { axis, travel, speed } => world.doors.get(node).map(|door| {
+                        (*axis, *travel, *speed) = (door.axis, door.travel, door.speed);
+                        closed = Some(door.closed);
+                    }).is_some(),
+                    ComponentDescription::RigidBody(body) => world.bodies.get(node).map(|current| body.update(current)).is_some(),
+                    ComponentDescription::FlightController(model) => world.aircraft.get(node).map(|aircraft| *model = aircraft.model).is_some(),
+                });
+                if let Some(closed) = closed {
+                    description.position = closed;
+                }
+                update(&mut description.children, nodes, world);
+            }
+        }
+        update(&mut description.nodes, &mut nodes, &self.world);
+        for (description, camera) in description.cameras.iter_mut().zip(cameras) {
+            description.position = camera.position;
+            description.yaw = camera.yaw;
+            description.pitch = camera.pitch;
+        }
+        description
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::RecordingRenderer;
+
+    fn build(description: SceneDescription, assets: &mut AssetManager) -> LoadedScene {
+        let requests = description.request_meshes(assets);
+        LoadedScene::build(description, &requests, &mut RecordingRenderer::default(), &mut RecordingRenderer::default()).unwrap()
+    }
+
+    #[test]
+    fn saved_scenes_load_the_way_they_were() {
+        // Without the models, which is where the placeholders and the generated terrain come in
+        let mut assets = AssetManager::new(vec![std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))]);
+        let description = SceneDescription::parse(include_str!("../resources/scenes/lunar.json")).unwrap();
+        let mut scene = build(description, &mut assets);
+
+        let terrain = unsafe { &mut *scene.find("terrain").unwrap() };
+        terrain.position = glm::vec3(1.0, -2.0, 3.0);
+        terrain.rotation = glm::vec3(0.0, 0.5, 0.0);
+        terrain.scale = glm::vec3(2.0, 1.0, 2.0);
+        terrain.material.tint = glm::vec4(0.5, 0.5, 0.5, 1.0);
+        let flown = scene.find("helicopter_1").unwrap();
+        assert!(scene.world.followers.remove(flown).is_some());
+
+        let saved = scene.describe(&[]);
+        assert_eq!(SceneDescription::parse(&saved.to_json().pretty()).unwrap(), saved);
+        let reloaded = build(saved, &mut assets);
+
+        assert_eq!(reloaded.root.tree(), scene.root.tree());
+        assert_eq!(reloaded.nodes.len(), scene.nodes.len());
+        for (&a, &b) in scene.nodes.iter().zip(&reloaded.nodes) {
+            let (a, b) = unsafe { (&*a, &*b) };
+            assert_eq!((&a.name, &a.tags), (&b.name, &b.tags));
+            assert_eq!(a.local_transform(), b.local_transform(), "{:?}", a.name);
+            assert_eq!(a.material, b.material, "{:?}", a.name);
+            assert_eq!(scene.path_of(a), reloaded.path_of(b));
+        }
+        assert_eq!(reloaded.tagged("helicopter").len(), scene.tagged("helicopter").len());
+        assert!(reloaded.world.followers.get(reloaded.find("helicopter_1").unwrap()).is_none());
+        assert_eq!(reloaded.world.followers.len(), scene.world.followers.len());
+        assert_eq!(reloaded.world.entities().len(), scene.world.entities().len());
+    }
+
+    #[test]
+    fn only_nodes_at_the_top_have_rigid_bodies() {
+        let body = r#"{ "type": "rigid_body", "shape": { "type": "sphere", "radius": 1 }, "mass": 1 }"#;
+        let top = format!(r#"{{ "nodes": [ {{ "name": "ball", "components": [ {} ] }} ] }}"#, body);
+        let nested = format!(r#"{{ "nodes": [ {{ "name": "cart", "children": [ {{ "name": "ball", "components": [ {} ] }} ] }} ] }}"#, body);
+        let mut assets = AssetManager::new(vec![]);
+
+        let scene = build(SceneDescription::parse(&top).unwrap(), &mut assets);
+        assert!(scene.world.bodies.get(scene.find("ball").unwrap()).is_some());
+        let description = SceneDescription::parse(&nested).unwrap();
+        let requests = description.request_meshes(&mut assets);
+        let error = LoadedScene::build(description, &requests, &mut RecordingRenderer::default(), &mut RecordingRenderer::default()).err().unwrap();
+        assert_eq!(error, "Node cart/ball has a rigid body, which only nodes at the top of the scene can have");
+    }
+
+    #[test]
+    fn heightmaps_make_the_ground() {
+        let directory = std::env::temp_dir().join(format!("gloom-scene-file-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let image = Heightmap::from_fn(2, 2, |x, _| image::Luma([if x == 0 { 0 } else { u16::MAX }]));
+        image.save(directory.join("heights.png")).unwrap();
+
+        let text = r#"{
+            "meshes": { "moon": { "type": "heightmap", "path": "heights.png", "size": 10, "height": 4, "resolution": 5 } },
+            "nodes":  [ { "name": "ground", "mesh": "moon" } ],
+            "ground": "ground"
+        }"#;
+        let description = SceneDescription::parse(text).unwrap();
+        assert_eq!(SceneDescription::parse(&description.to_json().pretty()).unwrap(), description);
+
+        let mut assets = AssetManager::new(vec![directory.clone()]);
+        let requests = description.request_meshes(&mut assets);
+        let scene = LoadedScene::build(description, &requests, &mut RecordingRenderer::default(), &mut RecordingRenderer::default()).unwrap();
+        assert!(matches!(scene.surface, Surface::Model(_)));
+        let height = scene.surface.ground().height_at(2.5, 1.0).unwrap();
+        assert!((height - 3.0).abs() < 1e-4, "{}", height);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
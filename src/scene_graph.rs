@@ -1,8 +1,12 @@
 extern crate nalgebra_glm as glm;
 
+use std::fmt::Write;
 use std::mem::ManuallyDrop;
 use std::pin::Pin;
 
+use crate::bounds::Aabb;
+use crate::renderer::Material;
+
 // Used to create an unholy abomination upon which you should not cast your gaze. This ended up
 // being a necessity due to wanting to keep the code written by students as "straight forward" as
 // possible. It is very very double plus ungood Rust, and intentionally leaks memory like a sieve.
@@ -16,14 +20,38 @@ use std::pin::Pin;
 // having what I arbitrarily decided to be the required level of "simplicity of use".
 pub type Node = ManuallyDrop<Pin<Box<SceneNode>>>;
 
+// How much of the screen should be how detailed
+#[derive(Clone, Copy, Debug)]
+pub struct LodLevel {
+    pub vao_id          : u32,
+    pub index_count     : i32,
+    pub min_screen_size : f32, // Below this fraction of the screen height, use a coarser level
+}
+
+// How far past a threshold the screen size has to go before switching level, as a fraction of
+// the threshold. Keeps nodes sitting right at a threshold from flickering between two levels.
+const LOD_HYSTERESIS: f32 = 0.15;
+
 pub struct SceneNode {
+    pub name : Option<String>,         // What I'm called in paths, among the children of my parent
+    pub tags : Vec<String>,            // What kinds of things I am
+
     pub position        : glm::Vec3,   // Where I should be in relation to my parent
     pub rotation        : glm::Vec3,   // How I should be rotated, around the X, the Y and the Z axes
     pub scale           : glm::Vec3,   // How I should be scaled
     pub reference_point : glm::Vec3,   // The point I shall rotate and scale about
 
-    pub vao_id      : u32,             // What I should draw
+    pub vao_id      : u32,             // What I should draw, as a mesh id from the Renderer
     pub index_count : i32,             // How much of it there is to draw
+    pub material    : Material,        // How I should look
+
+    pub local_bounds : Option<Aabb>,   // Around what I draw, in my own coordinates
+    pub world_bounds : Option<Aabb>,   // Around me and all I command. None if I can't be culled
+    pub world_transform : glm::Mat4,   // Where I ended up, after all of my parents' transformations
+
+    pub lods        : Vec<LodLevel>,   // What I could draw instead, from most to least detailed
+    pub current_lod : usize,           // Which of them I'm drawing
+
 
     pub children: Vec<*mut SceneNode>, // Those I command
 }
@@ -32,24 +60,40 @@ impl SceneNode {
 
     pub fn new() -> Node {
         ManuallyDrop::new(Pin::new(Box::new(SceneNode {
+            name            : None,
+            tags            : vec![],
             position        : glm::zero(),
             rotation        : glm::zero(),
             scale           : glm::vec3(1.0, 1.0, 1.0),
             reference_point : glm::zero(),
             vao_id          : 0,
             index_count     : -1,
+            material        : Material::default(),
+            local_bounds    : None,
+            world_bounds    : None,
+            world_transform : glm::identity(),
+            lods            : vec![],
+            current_lod     : 0,
             children        : vec![],
         })))
     }
 
     pub fn from_vao(vao_id: u32, index_count: i32) -> Node {
         ManuallyDrop::new(Pin::new(Box::new(SceneNode {
+            name            : None,
+            tags            : vec![],
             position        : glm::zero(),
             rotation        : glm::zero(),
             scale           : glm::vec3(1.0, 1.0, 1.0),
             reference_point : glm::zero(),
             vao_id,
             index_count,
+            material        : Material::default(),
+            local_bounds    : None,
+            world_bounds    : None,
+            world_transform : glm::identity(),
+            lods            : vec![],
+            current_lod     : 0,
             children: vec![],
         })))
     }
@@ -65,39 +109,226 @@ impl SceneNode {
         }
     }
 
+    // Detach a child, without freeing it. Returns whether it was one of my children.
+    pub fn remove_child(&mut self, child: *const SceneNode) -> bool {
+        let count = self.children.len();
+        self.children.retain(|&c| !std::ptr::eq(c, child));
+        self.children.len() != count
+    }
+
+    // The transformation taking my coordinates into those of my parent
+    pub fn local_transform(&self) -> glm::Mat4 {
+        let t = glm::translation(&self.position);
+
+        let mut r: glm::Mat4 = glm::identity();
+        r = glm::rotate_x(&r, self.rotation.x);
+        r = glm::rotate_y(&r, self.rotation.y);
+        r = glm::rotate_z(&r, self.rotation.z);
+
+        let s = glm::scaling(&self.scale);
+
+        let to_pivot   = glm::translation(&self.reference_point);
+        let from_pivot = glm::translation(&(-self.reference_point));
+        t * to_pivot * r * s * from_pivot
+    }
+
+    // Recompute the world transforms and bounds of me and everything below me. Has to be done
+    // after moving nodes around, before anything relies on them being up to date.
+    pub fn update_world(&mut self, transformation_so_far: &glm::Mat4) {
+        self.update_subtree(transformation_so_far);
+    }
+
+    fn update_subtree(&mut self, transformation_so_far: &glm::Mat4) -> SubtreeBounds {
+        let model = transformation_so_far * self.local_transform();
+        self.world_transform = model;
+
+        let mut bounds = match (self.drawn_mesh().1 > 0, self.local_bounds) {
+            (false, _)          => SubtreeBounds::Empty,
+            (true, Some(local)) => SubtreeBounds::Bounded(local.transformed(&model)),
+            (true, None)        => SubtreeBounds::Unbounded, // Drawn, but of unknown size
+        };
+        for &child in &self.children {
+            let child_bounds = unsafe { (*child).update_subtree(&model) };
+            bounds = match (bounds, child_bounds) {
+                (SubtreeBounds::Unbounded, _) | (_, SubtreeBounds::Unbounded) => SubtreeBounds::Unbounded,
+                (SubtreeBounds::Empty, other) | (other, SubtreeBounds::Empty) => other,
+                (SubtreeBounds::Bounded(a), SubtreeBounds::Bounded(b)) => SubtreeBounds::Bounded(a.union(&b)),
+            };
+        }
+
+        self.world_bounds = match bounds {
+            SubtreeBounds::Bounded(aabb) => Some(aabb),
+            _ => None,
+        };
+        bounds
+    }
+
+    // The mesh to draw, which depends on the level of detail if I have any
+    pub fn drawn_mesh(&self) -> (u32, i32) {
+        match self.lods.get(self.current_lod) {
+            Some(lod) => (lod.vao_id, lod.index_count),
+            None => (self.vao_id, self.index_count),
+        }
+    }
+
+    // Pick a level of detail given the height of my bounds on screen, as a fraction of the
+    // height of the screen
+    pub fn select_lod(&mut self, screen_size: f32) {
+        while self.current_lod + 1 < self.lods.len()
+            && screen_size < self.lods[self.current_lod].min_screen_size * (1.0 - LOD_HYSTERESIS)
+        {
+            self.current_lod += 1;
+        }
+        while self.current_lod > 0
+            && screen_size > self.lods[self.current_lod - 1].min_screen_size * (1.0 + LOD_HYSTERESIS)
+        {
+            self.current_lod -= 1;
+        }
+    }
+
+    // Select the level of detail of me and everything below me, as seen by a camera
+    pub fn update_lods(&mut self, view_projection: &glm::Mat4, transformation_so_far: &glm::Mat4) {
+        let model = transformation_so_far * self.local_transform();
+
+        if let (true, Some(bounds)) = (self.lods.len() > 1, self.local_bounds) {
+            let sphere = bounds.bounding_sphere().transformed(&model);
+            let clip_w = (view_projection * glm::vec4(sphere.center.x, sphere.center.y, sphere.center.z, 1.0)).w;
+
+            // With a camera that doesn't scale, the second row of the view projection matrix has
+            // the length of the vertical scaling of the projection, for both perspective and
+            // orthographic projections. A full screen height is 2 in normalized device coordinates.
+            let y_scale = glm::length(&glm::vec3(view_projection[(1, 0)], view_projection[(1, 1)], view_projection[(1, 2)]));
+            let screen_size = if clip_w > 0.0 {
+                sphere.radius * y_scale / clip_w
+            } else {
+                f32::INFINITY // The camera is inside of or right next to the bounds
+            };
+            self.select_lod(screen_size);
+        }
+
+        for &child in &self.children {
+            unsafe { (*child).update_lods(view_projection, &model) };
+        }
+    }
+
+    // Whether the node is me or somewhere below me
+    pub fn contains(&self, node: *const SceneNode) -> bool {
+        std::ptr::eq(self, node) || self.children.iter().any(|&child| unsafe { (*child).contains(node) })
+    }
+
     #[allow(dead_code)]
     pub fn n_children(&self) -> usize {
         self.children.len()
     }
 
+    // The child with a name
+    pub fn child_named(&self, name: &str) -> Option<*mut SceneNode> {
+        self.children.iter().copied().find(|&child| unsafe { (*child).name.as_deref() } == Some(name))
+    }
+
+    // The node at a path below me, made of the names of the nodes on the way down joined by
+    // slashes, as in "helicopter_3/tail_rotor". Nodes without a name can't be on the way.
+    pub fn find(&self, path: &str) -> Option<*mut SceneNode> {
+        let mut node = self as *const SceneNode as *mut SceneNode;
+        for name in path.split('/') {
+            node = unsafe { (*node).child_named(name)? };
+        }
+        Some(node)
+    }
+
+    // The path from me down to a node, if there is one
+    pub fn path_of(&self, node: *const SceneNode) -> Option<String> {
+        for &child in &self.children {
+            let child = unsafe { &*child };
+            if let Some(name) = &child.name {
+                if std::ptr::eq(child, node) {
+                    return Some(name.clone());
+                }
+                if let Some(path) = child.path_of(node) {
+                    return Some(format!("{}/{}", name, path));
+                }
+            }
+        }
+        None
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    // Me and everything below me with a tag, depth first
+    pub fn find_tagged(&self, tag: &str) -> Vec<*mut SceneNode> {
+        let mut found = vec![];
+        self.collect_tagged(tag, &mut found);
+        found
+    }
+
+    fn collect_tagged(&self, tag: &str, found: &mut Vec<*mut SceneNode>) {
+        if self.has_tag(tag) {
+            found.push(self as *const SceneNode as *mut SceneNode);
+        }
+        for &child in &self.children {
+            unsafe { (*child).collect_tagged(tag, found) };
+        }
+    }
+
+    // Me and everything below me, one line each:
+    //
+    //     (unnamed)
+    //     ├── terrain  mesh 9 (3 levels of detail)
+    //     └── helicopter_1 #helicopter  mesh 1 (1152 indices)  at [0.00, 5.00, 0.00]
+    //         ├── door  mesh 5 (120 indices)
+    //         ...
+    pub fn tree(&self) -> String {
+        let mut out = String::new();
+        self.write_tree(&mut out, "", "");
+        out
+    }
+
+    fn write_tree(&self, out: &mut String, first_prefix: &str, prefix: &str) {
+        out.push_str(first_prefix);
+        out.push_str(self.name.as_deref().unwrap_or("(unnamed)"));
+        for tag in &self.tags {
+            write!(out, " #{}", tag).unwrap();
+        }
+        if self.lods.len() > 1 {
+            write!(out, "  mesh {} ({} levels of detail)", self.lods[0].vao_id, self.lods.len()).unwrap();
+        } else if self.index_count > 0 {
+            write!(out, "  mesh {} ({} indices)", self.vao_id, self.index_count).unwrap();
+        }
+        let vec3 = |v: &glm::Vec3| format!("[{:.2}, {:.2}, {:.2}]", v.x, v.y, v.z);
+        if self.position != glm::Vec3::zeros() {
+            write!(out, "  at {}", vec3(&self.position)).unwrap();
+        }
+        if self.rotation != glm::Vec3::zeros() {
+            write!(out, "  rotated {}", vec3(&self.rotation)).unwrap();
+        }
+        if self.scale != glm::vec3(1.0, 1.0, 1.0) {
+            write!(out, "  scaled {}", vec3(&self.scale)).unwrap();
+        }
+        out.push('\n');
+
+        for (i, &child) in self.children.iter().enumerate() {
+            let last = i + 1 == self.children.len();
+            let (branch, continuation) = if last { ("└── ", "    ") } else { ("├── ", "│   ") };
+            unsafe { (*child).write_tree(out, &format!("{}{}", prefix, branch), &format!("{}{}", prefix, continuation)) };
+        }
+    }
+
     #[allow(dead_code)]
     pub fn print(&self) {
-        println!(
-"SceneNode {{
-    VAO:       {}
-    Indices:   {}
-    Children:  {}
-    Position:  [{:.2}, {:.2}, {:.2}]
-    Rotation:  [{:.2}, {:.2}, {:.2}]
-    Reference: [{:.2}, {:.2}, {:.2}]
-}}",
-            self.vao_id,
-            self.index_count,
-            self.children.len(),
-            self.position.x,
-            self.position.y,
-            self.position.z,
-            self.rotation.x,
-            self.rotation.y,
-            self.rotation.z,
-            self.reference_point.x,
-            self.reference_point.y,
-            self.reference_point.z,
-        );
+        print!("{}", self.tree());
     }
 
 }
 
+#[derive(Clone, Copy)]
+enum SubtreeBounds {
+    Empty,         // Nothing is drawn
+    Bounded(Aabb),
+    Unbounded,     // Something is drawn without bounds, so it can never be culled
+}
+
 
 // You can also use square brackets to access the children of a SceneNode
 use std::ops::{Index, IndexMut};
@@ -116,3 +347,78 @@ impl IndexMut<usize> for SceneNode {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moved(transform: &glm::Mat4, p: glm::Vec3) -> glm::Vec3 {
+        (transform * glm::vec4(p.x, p.y, p.z, 1.0)).xyz()
+    }
+
+    #[test]
+    fn scales_and_turns_about_the_reference_point() {
+        let mut node = SceneNode::new();
+        node.position = glm::vec3(10.0, 0.0, 0.0);
+        node.rotation = glm::vec3(0.0, std::f32::consts::FRAC_PI_2, 0.0);
+        node.scale = glm::vec3(2.0, 3.0, 1.0);
+        node.reference_point = glm::vec3(1.0, 1.0, 0.0);
+        let transform = node.local_transform();
+
+        // The reference point stays put, apart from the position
+        assert!(glm::distance(&moved(&transform, node.reference_point), &glm::vec3(11.0, 1.0, 0.0)) < 1e-5);
+        // Scaled before it is turned, so x is doubled and then turned onto -z
+        assert!(glm::distance(&moved(&transform, glm::vec3(2.0, 2.0, 0.0)), &glm::vec3(11.0, 4.0, -2.0)) < 1e-5);
+    }
+
+    fn named(name: Option<&str>, tags: &[&str]) -> Node {
+        let mut node = SceneNode::new();
+        node.name = name.map(str::to_string);
+        node.tags = tags.iter().map(|tag| tag.to_string()).collect();
+        node
+    }
+
+    #[test]
+    fn nodes_are_found_by_path_and_by_tag() {
+        //  (unnamed)
+        //  ├── helicopter_1 #helicopter
+        //  │   ├── door
+        //  │   └── main_rotor #rotor
+        //  └── (unnamed)
+        //      └── helicopter_2 #helicopter
+        //          └── main_rotor #rotor
+        let mut root = named(None, &[]);
+        let mut first = named(Some("helicopter_1"), &["helicopter"]);
+        let door = named(Some("door"), &[]);
+        let first_rotor = named(Some("main_rotor"), &["rotor"]);
+        let mut group = named(None, &[]);
+        let mut second = named(Some("helicopter_2"), &["helicopter"]);
+        let second_rotor = named(Some("main_rotor"), &["rotor"]);
+        first.add_child(&door);
+        first.add_child(&first_rotor);
+        second.add_child(&second_rotor);
+        group.add_child(&second);
+        root.add_child(&first);
+        root.add_child(&group);
+        let pointer = |node: &Node| &***node as *const SceneNode as *mut SceneNode;
+
+        assert_eq!(root.find("helicopter_1"), Some(pointer(&first)));
+        assert_eq!(root.find("helicopter_1/main_rotor"), Some(pointer(&first_rotor)));
+        assert_eq!(first.find("door"), Some(pointer(&door)));
+        for missing in ["helicopter_2", "helicopter_2/main_rotor", "helicopter_1/tail_rotor", "door", "", "helicopter_1/"] {
+            assert_eq!(root.find(missing), None, "{:?}", missing);
+        }
+
+        assert_eq!(root.path_of(pointer(&door)).as_deref(), Some("helicopter_1/door"));
+        assert_eq!(group.path_of(pointer(&second_rotor)).as_deref(), Some("helicopter_2/main_rotor"));
+        // No path leads through a node without a name, nor to the node itself
+        assert_eq!(root.path_of(pointer(&second_rotor)), None);
+        assert_eq!(root.path_of(pointer(&group)), None);
+        assert_eq!(first.path_of(pointer(&first)), None);
+
+        assert_eq!(root.find_tagged("helicopter"), vec![pointer(&first), pointer(&second)]);
+        assert_eq!(root.find_tagged("rotor"), vec![pointer(&first_rotor), pointer(&second_rotor)]);
+        assert_eq!(second.find_tagged("helicopter"), vec![pointer(&second)]);
+        assert!(root.find_tagged("wing").is_empty());
+    }
+}
@@ -16,6 +16,7 @@ pub struct ShaderBuilder {
 }
 
 #[allow(dead_code)]
+#[derive(Clone, Copy, Debug)]
 pub enum ShaderType {
     Vertex,
     Fragment,
@@ -24,6 +25,25 @@ pub enum ShaderType {
     Geometry,
 }
 
+// A shader read from disk but not compiled yet, which can be done off the render thread
+pub struct ShaderSource {
+    pub shader_type : ShaderType,
+    pub source      : String,
+}
+
+impl ShaderSource {
+    // The type of shader comes from the extension of the file
+    pub fn load(path: &Path) -> Result<ShaderSource, String> {
+        let extension = path.extension()
+            .ok_or_else(|| format!("Failed to read extension of file with path: {}", path.display()))?;
+        let shader_type = ShaderType::from_ext(extension)
+            .map_err(|e| format!("Unknown shader extension {}", e))?;
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read shader source. {}", e))?;
+        Ok(ShaderSource { shader_type, source })
+    }
+}
+
 impl Shader {
     // Make sure the shader is active before calling this
     pub unsafe fn get_uniform_location(&self, name: &str) -> i32 {
@@ -69,17 +89,15 @@ impl ShaderBuilder {
         }
     }
 
+    #[allow(dead_code)]
     pub unsafe fn attach_file(self, shader_path: &str) -> ShaderBuilder {
-        let path = Path::new(shader_path);
-        if let Some(extension) = path.extension() {
-            let shader_type = ShaderType::from_ext(extension)
-                .expect("Failed to parse file extension.");
-            let shader_src = std::fs::read_to_string(path)
-                .expect(&format!("Failed to read shader source. {}", shader_path));
-            self.compile_shader(&shader_src, shader_type)
-        } else {
-            panic!("Failed to read extension of file with path: {}", shader_path);
-        }
+        let shader_source = ShaderSource::load(Path::new(shader_path))
+            .unwrap_or_else(|e| panic!("{}", e));
+        self.attach_source(&shader_source)
+    }
+
+    pub unsafe fn attach_source(self, shader_source: &ShaderSource) -> ShaderBuilder {
+        self.compile_shader(&shader_source.source, shader_source.shader_type)
     }
 
     pub unsafe fn compile_shader(mut self, shader_src: &str, shader_type: ShaderType) -> ShaderBuilder {
@@ -0,0 +1,450 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use crate::mesh::Mesh;
+
+// Mesh simplification using the quadric error metric of Garland & Heckbert, "Surface
+// Simplification Using Quadric Error Metrics" (1997). Every vertex accumulates the planes of the
+// triangles around it as a quadric, which measures the squared distance from a point to all of
+// those planes. Edges are collapsed cheapest first, into the point minimizing the error of both
+// of their ends, until the mesh is small enough.
+//
+// Vertices sharing a position are welded before simplifying, so that seams where the OBJ loader
+// split vertices don't tear open. The simplified mesh gets smooth normals, and the color of one
+// of the welded vertices.
+
+// Edges of the mesh boundary get a plane perpendicular to their triangle as well, weighted this
+// heavily, to keep the outline of open meshes like the terrain in place
+const BOUNDARY_WEIGHT: f64 = 1000.0;
+
+pub struct SimplifiedMesh {
+    pub mesh      : Mesh,
+    pub max_error : f32, // The largest distance error of any collapse, in the units of the mesh
+}
+
+// A symmetric 4x4 matrix, storing only its upper triangle
+#[derive(Clone, Copy, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    // For the plane ax + by + cz + d = 0, with (a, b, c) of unit length
+    fn from_plane(a: f64, b: f64, c: f64, d: f64) -> Quadric {
+        Quadric([
+            a*a, a*b, a*c, a*d,
+                 b*b, b*c, b*d,
+                      c*c, c*d,
+                           d*d,
+        ])
+    }
+
+    fn add(&self, other: &Quadric) -> Quadric {
+        let mut sum = *self;
+        sum.0.iter_mut().zip(other.0.iter()).for_each(|(a, b)| *a += b);
+        sum
+    }
+
+    fn scaled(&self, factor: f64) -> Quadric {
+        Quadric(self.0.map(|q| q * factor))
+    }
+
+    // The sum of squared distances from the point to the planes of the quadric
+    fn error(&self, p: &[f64; 3]) -> f64 {
+        let q = &self.0;
+        let [x, y, z] = *p;
+        q[0]*x*x + 2.0*q[1]*x*y + 2.0*q[2]*x*z + 2.0*q[3]*x
+                 +     q[4]*y*y + 2.0*q[5]*y*z + 2.0*q[6]*y
+                                +     q[7]*z*z + 2.0*q[8]*z
+                                               +     q[9]
+    }
+
+    // The point of least error, unless the quadric is too close to singular to tell, like for
+    // the planes of a flat or cylindrical surface
+    fn optimum(&self) -> Option<[f64; 3]> {
+        let q = &self.0;
+        let a = [
+            [q[0], q[1], q[2]],
+            [q[1], q[4], q[5]],
+            [q[2], q[5], q[7]],
+        ];
+        let rhs = [-q[3], -q[6], -q[8]];
+
+        let det = det3(&a);
+        let scale = q[0].abs() + q[4].abs() + q[7].abs();
+        if det.abs() <= 1e-10 * scale * scale * scale {
+            return None;
+        }
+
+        // Cramer's rule
+        let mut solution = [0.0; 3];
+        for (column, value) in solution.iter_mut().enumerate() {
+            let mut replaced = a;
+            for row in 0..3 {
+                replaced[row][column] = rhs[row];
+            }
+            *value = det3(&replaced) / det;
+        }
+        Some(solution)
+    }
+}
+
+fn det3(m: &[[f64; 3]; 3]) -> f64 {
+    m[0][0] * (m[1][1]*m[2][2] - m[1][2]*m[2][1])
+  - m[0][1] * (m[1][0]*m[2][2] - m[1][2]*m[2][0])
+  + m[0][2] * (m[1][0]*m[2][1] - m[1][1]*m[2][0])
+}
+
+fn sub(a: &[f64; 3], b: &[f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn cross(a: &[f64; 3], b: &[f64; 3]) -> [f64; 3] {
+    [a[1]*b[2] - a[2]*b[1], a[2]*b[0] - a[0]*b[2], a[0]*b[1] - a[1]*b[0]]
+}
+
+fn dot(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    a[0]*b[0] + a[1]*b[1] + a[2]*b[2]
+}
+
+fn length(a: &[f64; 3]) -> f64 {
+    dot(a, a).sqrt()
+}
+
+fn triangle_normal(a: &[f64; 3], b: &[f64; 3], c: &[f64; 3]) -> [f64; 3] {
+    cross(&sub(b, a), &sub(c, a))
+}
+
+// A possible collapse of the edge (a, b), valid as long as neither end has changed since
+struct Collapse {
+    cost      : f64,
+    a         : usize,
+    b         : usize,
+    version_a : u32,
+    version_b : u32,
+    target    : [f64; 3],
+}
+
+// Ordered so that the cheapest collapse is at the top of the max-heap
+impl Ord for Collapse {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cost == other.cost
+    }
+}
+impl Eq for Collapse {}
+
+struct Simplifier {
+    positions       : Vec<[f64; 3]>,
+    quadrics        : Vec<Quadric>,
+    versions        : Vec<u32>,
+    point_triangles : Vec<Vec<usize>>, // The triangles around each point, possibly dead ones
+    triangles       : Vec<[usize; 3]>,
+    triangle_alive  : Vec<bool>,
+    alive_count     : usize,
+}
+
+impl Simplifier {
+    fn new(positions: Vec<[f64; 3]>, triangles: Vec<[usize; 3]>) -> Simplifier {
+        let mut quadrics = vec![Quadric::default(); positions.len()];
+        let mut point_triangles = vec![vec![]; positions.len()];
+        let mut edge_triangles: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+
+        for (t, triangle) in triangles.iter().enumerate() {
+            let [a, b, c] = triangle.map(|i| positions[i]);
+            let normal = triangle_normal(&a, &b, &c);
+            let area = length(&normal);
+            if area > 0.0 {
+                let n = normal.map(|x| x / area);
+                let plane = Quadric::from_plane(n[0], n[1], n[2], -dot(&n, &a));
+                for &i in triangle {
+                    quadrics[i] = quadrics[i].add(&plane);
+                }
+            }
+            for k in 0..3 {
+                point_triangles[triangle[k]].push(t);
+                let (i, j) = (triangle[k], triangle[(k + 1) % 3]);
+                edge_triangles.entry((i.min(j), i.max(j))).or_default().push(t);
+            }
+        }
+
+        for (&(i, j), adjacent) in &edge_triangles {
+            if adjacent.len() != 1 {
+                continue;
+            }
+            let [a, b, c] = triangles[adjacent[0]].map(|k| positions[k]);
+            let face_normal = triangle_normal(&a, &b, &c);
+            let perpendicular = cross(&sub(&positions[j], &positions[i]), &face_normal);
+            let norm = length(&perpendicular);
+            if norm > 0.0 {
+                let n = perpendicular.map(|x| x / norm);
+                let plane = Quadric::from_plane(n[0], n[1], n[2], -dot(&n, &positions[i]))
+                    .scaled(BOUNDARY_WEIGHT);
+                quadrics[i] = quadrics[i].add(&plane);
+                quadrics[j] = quadrics[j].add(&plane);
+            }
+        }
+
+        Simplifier {
+            versions       : vec![0; positions.len()],
+            triangle_alive : vec![true; triangles.len()],
+            alive_count    : triangles.len(),
+            positions,
+            quadrics,
+            point_triangles,
+            triangles,
+        }
+    }
+
+    fn collapse_candidate(&self, a: usize, b: usize) -> Collapse {
+        let quadric = self.quadrics[a].add(&self.quadrics[b]);
+        let (pa, pb) = (&self.positions[a], &self.positions[b]);
+        let midpoint = [(pa[0] + pb[0]) * 0.5, (pa[1] + pb[1]) * 0.5, (pa[2] + pb[2]) * 0.5];
+
+        // The optimum of a nearly singular quadric may be far off, so only trust it nearby
+        let edge_length = length(&sub(pa, pb));
+        let target = quadric.optimum()
+            .filter(|p| length(&sub(p, &midpoint)) <= edge_length)
+            .unwrap_or_else(|| {
+                *[*pa, *pb, midpoint].iter()
+                    .min_by(|p, q| quadric.error(p).total_cmp(&quadric.error(q)))
+                    .unwrap()
+            });
+
+        Collapse {
+            cost      : quadric.error(&target).max(0.0),
+            a,
+            b,
+            version_a : self.versions[a],
+            version_b : self.versions[b],
+            target,
+        }
+    }
+
+    fn neighbours(&self, point: usize) -> Vec<usize> {
+        let mut neighbours: Vec<usize> = self.point_triangles[point].iter()
+            .filter(|&&t| self.triangle_alive[t])
+            .flat_map(|&t| self.triangles[t])
+            .filter(|&p| p != point)
+            .collect();
+        neighbours.sort_unstable();
+        neighbours.dedup();
+        neighbours
+    }
+
+    // Moving a and b to the target must not turn any of the remaining triangles around
+    fn flips_triangles(&self, a: usize, b: usize, target: &[f64; 3]) -> bool {
+        self.point_triangles[a].iter().chain(self.point_triangles[b].iter())
+            .filter(|&&t| self.triangle_alive[t])
+            .map(|&t| self.triangles[t])
+            .filter(|triangle| !(triangle.contains(&a) && triangle.contains(&b)))
+            .any(|triangle| {
+                let before = triangle.map(|i| self.positions[i]);
+                let after = triangle.map(|i| if i == a || i == b { *target } else { self.positions[i] });
+                let n_before = triangle_normal(&before[0], &before[1], &before[2]);
+                let n_after = triangle_normal(&after[0], &after[1], &after[2]);
+                dot(&n_before, &n_after) <= 0.0
+            })
+    }
+
+    // Collapses b into a
+    fn collapse(&mut self, a: usize, b: usize, target: [f64; 3]) {
+        self.positions[a] = target;
+        self.quadrics[a] = self.quadrics[a].add(&self.quadrics[b]);
+        self.versions[a] += 1;
+        self.versions[b] += 1;
+
+        for t in std::mem::take(&mut self.point_triangles[b]) {
+            if !self.triangle_alive[t] {
+                continue;
+            }
+            if self.triangles[t].contains(&a) {
+                self.triangle_alive[t] = false;
+                self.alive_count -= 1;
+            } else {
+                self.triangles[t].iter_mut().filter(|i| **i == b).for_each(|i| *i = a);
+                self.point_triangles[a].push(t);
+            }
+        }
+        let triangle_alive = &self.triangle_alive;
+        self.point_triangles[a].retain(|&t| triangle_alive[t]);
+    }
+
+    fn run(&mut self, target_triangles: usize) -> f64 {
+        let edges: HashSet<(usize, usize)> = self.triangles.iter()
+            .flat_map(|t| [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])])
+            .map(|(i, j)| (i.min(j), i.max(j)))
+            .collect();
+        let mut heap: BinaryHeap<Collapse> = edges.iter()
+            .map(|&(i, j)| self.collapse_candidate(i, j))
+            .collect();
+
+        let mut max_error: f64 = 0.0;
+        while self.alive_count > target_triangles {
+            let candidate = match heap.pop() {
+                Some(candidate) => candidate,
+                None => break,
+            };
+            let (a, b) = (candidate.a, candidate.b);
+            if candidate.version_a != self.versions[a] || candidate.version_b != self.versions[b] {
+                continue; // Stale
+            }
+            if self.flips_triangles(a, b, &candidate.target) {
+                continue;
+            }
+
+            self.collapse(a, b, candidate.target);
+            max_error = max_error.max(candidate.cost);
+            for neighbour in self.neighbours(a) {
+                heap.push(self.collapse_candidate(a, neighbour));
+            }
+        }
+        max_error.sqrt()
+    }
+}
+
+impl Mesh {
+    // Simplify the mesh down to at most the given number of triangles, or as close to it as
+    // possible without folding the surface over itself
+    pub fn simplified(&self, target_triangles: usize) -> SimplifiedMesh {
+        // Weld vertices sharing the exact same position into points
+        let mut point_of_position: HashMap<[u32; 3], usize> = HashMap::new();
+        let mut positions: Vec<[f64; 3]> = vec![];
+        let mut point_vertex: Vec<usize> = vec![]; // One of the vertices making up each point
+        let vertex_points: Vec<usize> = self.vertices.chunks_exact(3).enumerate()
+            .map(|(vertex, p)| {
+                *point_of_position.entry([p[0].to_bits(), p[1].to_bits(), p[2].to_bits()]).or_insert_with(|| {
+                    positions.push([p[0] as f64, p[1] as f64, p[2] as f64]);
+                    point_vertex.push(vertex);
+                    positions.len() - 1
+                })
+            })
+            .collect();
+
+        let triangles: Vec<[usize; 3]> = self.indices.chunks_exact(3)
+            .map(|t| [vertex_points[t[0] as usize], vertex_points[t[1] as usize], vertex_points[t[2] as usize]])
+            .filter(|t| t[0] != t[1] && t[1] != t[2] && t[2] != t[0])
+            .collect();
+
+        let mut simplifier = Simplifier::new(positions, triangles);
+        let max_error = simplifier.run(target_triangles);
+
+        // Compact the surviving points and triangles into a new mesh
+        let mut new_index: Vec<Option<u32>> = vec![None; simplifier.positions.len()];
+        let mut vertices = vec![];
+        let mut colors = vec![];
+        let mut indices = vec![];
+        for (t, triangle) in simplifier.triangles.iter().enumerate() {
+            if !simplifier.triangle_alive[t] {
+                continue;
+            }
+            for &point in triangle {
+                let index = *new_index[point].get_or_insert_with(|| {
+                    let p = simplifier.positions[point];
+                    vertices.extend_from_slice(&[p[0] as f32, p[1] as f32, p[2] as f32]);
+                    let vertex = point_vertex[point];
+                    colors.extend_from_slice(self.colors.get(4*vertex..4*vertex + 4).unwrap_or(&[1.0; 4]));
+                    (vertices.len() / 3 - 1) as u32
+                });
+                indices.push(index);
+            }
+        }
+
+        let normals = smooth_normals(&vertices, &indices);
+        SimplifiedMesh {
+            mesh      : Mesh::new(vertices, normals, colors, indices),
+            max_error : max_error as f32,
+        }
+    }
+
+    // Level 0 is the mesh itself, and every following level has about half the triangles of
+    // the one before it
+    pub fn generate_lods(&self, levels: usize) -> Vec<SimplifiedMesh> {
+        let mut lods = vec![SimplifiedMesh { mesh: self.clone(), max_error: 0.0 }];
+        while lods.len() < levels {
+            let previous = lods.last().unwrap();
+            let mut next = previous.mesh.simplified(previous.mesh.triangle_count() / 2);
+            next.max_error = next.max_error.max(previous.max_error);
+            lods.push(next);
+        }
+        lods
+    }
+}
+
+// Area weighted vertex normals
+fn smooth_normals(vertices: &[f32], indices: &[u32]) -> Vec<f32> {
+    let position = |i: u32| {
+        let i = i as usize;
+        [vertices[3*i] as f64, vertices[3*i + 1] as f64, vertices[3*i + 2] as f64]
+    };
+    let mut normals = vec![[0.0f64; 3]; vertices.len() / 3];
+    for t in indices.chunks_exact(3) {
+        let normal = triangle_normal(&position(t[0]), &position(t[1]), &position(t[2]));
+        for &i in t {
+            let n = &mut normals[i as usize];
+            n.iter_mut().zip(normal.iter()).for_each(|(a, b)| *a += b);
+        }
+    }
+    normals.iter()
+        .flat_map(|n| {
+            let norm = length(n).max(1e-20);
+            n.map(|x| (x / norm) as f32)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triangles(mesh: &Mesh) -> Vec<[glm::Vec3; 3]> {
+        let position = |i: u32| glm::make_vec3(&mesh.vertices[3 * i as usize..3 * i as usize + 3]);
+        mesh.indices.chunks_exact(3).map(|t| [position(t[0]), position(t[1]), position(t[2])]).collect()
+    }
+
+    #[test]
+    fn levels_have_fewer_triangles_and_more_error() {
+        let lods = Mesh::icosphere(1.0, 3, [1.0; 4]).generate_lods(4);
+        assert_eq!(lods.len(), 4);
+        assert_eq!(lods[0].max_error, 0.0);
+        for pair in lods.windows(2) {
+            assert!(pair[1].mesh.triangle_count() < pair[0].mesh.triangle_count());
+            assert!(pair[1].max_error > pair[0].max_error, "{} after {}", pair[1].max_error, pair[0].max_error);
+        }
+    }
+
+    #[test]
+    fn keeps_the_outline_of_a_plane() {
+        let plane = Mesh::plane(2.0, 2.0, 16, 16, [1.0; 4]);
+        let simplified = plane.simplified(plane.triangle_count() / 8).mesh;
+        assert!(simplified.triangle_count() <= plane.triangle_count() / 8);
+
+        // No holes, and every edge on the outside of the mesh is still on the outside of the plane
+        let area: f32 = triangles(&simplified).iter().map(|[a, b, c]| glm::length(&glm::cross(&(b - a), &(c - a))) / 2.0).sum();
+        assert!((area - 4.0).abs() < 1e-3, "area {}", area);
+        let mut edges: HashMap<(u32, u32), usize> = HashMap::new();
+        for t in simplified.indices.chunks_exact(3) {
+            for k in 0..3 {
+                let (i, j) = (t[k], t[(k + 1) % 3]);
+                *edges.entry((i.min(j), i.max(j))).or_default() += 1;
+            }
+        }
+        let on_border = |i: u32| {
+            let p = &simplified.vertices[3 * i as usize..3 * i as usize + 3];
+            (p[0].abs() - 1.0).abs() < 1e-5 || (p[2].abs() - 1.0).abs() < 1e-5
+        };
+        for (&(i, j), &count) in &edges {
+            if count == 1 {
+                assert!(on_border(i) && on_border(j), "boundary edge {}-{} moved inside", i, j);
+            }
+        }
+        assert_eq!((simplified.aabb.min, simplified.aabb.max), (plane.aabb.min, plane.aabb.max));
+    }
+}
@@ -0,0 +1,355 @@
+extern crate nalgebra_glm as glm;
+
+use std::path::Path;
+
+use image::{ImageBuffer, Luma};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::mesh::Mesh;
+
+// Generates terrain meshes, either from a grayscale heightmap image or from fractal Perlin noise,
+// optionally pockmarked with craters. Everything random is drawn from generators seeded with
+// `seed`, so the same settings always give the same terrain. Noise generated terrain goes on
+// forever: any square of it can be generated on its own, and squares next to each other match up
+// along their edges, so it can be generated in chunks.
+
+// Vertex colors, blended by height and darkened on steep slopes
+const LOWLAND_COLOR  : [f32; 3] = [0.36, 0.35, 0.34];
+const HIGHLAND_COLOR : [f32; 3] = [0.78, 0.77, 0.75];
+const SLOPE_SHADE    : f32 = 0.45; // How much darker a vertical cliff is than flat ground
+
+// A grayscale image of heights, black the lowest and white the highest
+pub type Heightmap = ImageBuffer<Luma<u16>, Vec<u16>>;
+
+pub fn load_heightmap(path: &Path) -> Result<Heightmap, String> {
+    Ok(image::open(path).map_err(|e| format!("Failed to load heightmap: {}", e))?.into_luma16())
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TerrainGenerator {
+    pub size          : f32,        // Width and depth in world units of generate(), centered on the origin
+    pub resolution    : usize,      // Vertices along each side of generate()
+    pub height        : f32,        // Heights range from 0 to this, before craters are dug
+    pub seed          : u64,
+    pub octaves       : u32,        // Layers of noise, each finer and fainter than the last
+    pub frequency     : f32,        // Cycles of the first octave across the terrain
+    pub lacunarity    : f32,        // Frequency multiplier from one octave to the next
+    pub persistence   : f32,        // Amplitude multiplier from one octave to the next
+    pub craters       : usize,      // For every size by size square
+    pub crater_radius : (f32, f32), // Smallest and largest, in world units
+    pub crater_depth  : f32,        // As a fraction of the radius
+}
+
+impl Default for TerrainGenerator {
+    fn default() -> Self {
+        TerrainGenerator {
+            size          : 400.0,
+            resolution    : 129,
+            height        : 12.0,
+            seed          : 1969,
+            octaves       : 6,
+            frequency     : 4.0,
+            lacunarity    : 2.0,
+            persistence   : 0.5,
+            craters       : 40,
+            crater_radius : (3.0, 40.0),
+            crater_depth  : 0.2,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Crater {
+    center : glm::Vec2, // On the X-Z plane
+    radius : f32,
+}
+
+impl Crater {
+    // The rim fades out within half a radius outside of the crater
+    fn reach(&self) -> f32 {
+        self.radius * 1.5
+    }
+
+    // How much the crater raises or lowers the ground at a point. Nothing at all beyond its
+    // reach, so that terrain generated without it where it doesn't reach still meets up with
+    // terrain generated with it.
+    fn offset(&self, point: &glm::Vec2, depth_fraction: f32) -> f32 {
+        let depth = self.radius * depth_fraction;
+        let rim_height = depth * 0.3;
+        let d = glm::distance(point, &self.center) / self.radius;
+        let falloff = |d: f32| (-((d - 1.0) / 0.25).powi(2)).exp();
+        if d < 1.0 {
+            // A parabolic bowl rising to the top of the rim
+            -depth * (1.0 - d * d) + rim_height * d * d
+        } else if d * self.radius < self.reach() {
+            // Bell shaped, lowered to end at nothing
+            let end = falloff(self.reach() / self.radius);
+            rim_height * (falloff(d) - end) / (1.0 - end)
+        } else {
+            0.0
+        }
+    }
+}
+
+// Ken Perlin's improved noise, in two dimensions, with the permutation shuffled by the seed
+struct Perlin {
+    permutation: [u8; 512], // Repeated twice, so that lookups don't have to wrap
+}
+
+impl Perlin {
+    fn new(rng: &mut StdRng) -> Self {
+        let mut shuffled: Vec<u8> = (0..=255).collect();
+        shuffled.shuffle(rng);
+        let mut permutation = [0; 512];
+        for (i, p) in permutation.iter_mut().enumerate() {
+            *p = shuffled[i % 256];
+        }
+        Perlin { permutation }
+    }
+
+    // The dot product of the offset with one of eight gradient directions picked by the hash
+    fn gradient(hash: u8, x: f32, y: f32) -> f32 {
+        match hash & 7 {
+            0 =>  x + y,
+            1 => -x + y,
+            2 =>  x - y,
+            3 => -x - y,
+            4 =>  x,
+            5 => -x,
+            6 =>  y,
+            _ => -y,
+        }
+    }
+
+    // Roughly between -1 and 1
+    fn noise(&self, x: f32, y: f32) -> f32 {
+        let fade = |t: f32| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (xi, yi) = ((x0 as i64 & 255) as usize, (y0 as i64 & 255) as usize);
+        let (xf, yf) = (x - x0, y - y0);
+        let (u, v) = (fade(xf), fade(yf));
+
+        let p = &self.permutation;
+        let hash = |dx: usize, dy: usize| p[p[xi + dx] as usize + yi + dy];
+        let bottom = glm::lerp_scalar(Perlin::gradient(hash(0, 0), xf, yf), Perlin::gradient(hash(1, 0), xf - 1.0, yf), u);
+        let top = glm::lerp_scalar(Perlin::gradient(hash(0, 1), xf, yf - 1.0), Perlin::gradient(hash(1, 1), xf - 1.0, yf - 1.0), u);
+        glm::lerp_scalar(bottom, top, v)
+    }
+
+    // Fractal Brownian motion: octaves of noise added together, normalized to between 0 and 1
+    fn fbm(&self, x: f32, y: f32, generator: &TerrainGenerator) -> f32 {
+        let (mut sum, mut total_amplitude) = (0.0, 0.0);
+        let (mut frequency, mut amplitude) = (1.0, 1.0);
+        for _ in 0..generator.octaves {
+            sum += self.noise(x * frequency, y * frequency) * amplitude;
+            total_amplitude += amplitude;
+            frequency *= generator.lacunarity;
+            amplitude *= generator.persistence;
+        }
+        if total_amplitude > 0.0 { (sum / total_amplitude * 0.5 + 0.5).clamp(0.0, 1.0) } else { 0.5 }
+    }
+}
+
+impl TerrainGenerator {
+    // Noise and craters, covering size by size units around the origin
+    #[allow(dead_code)]
+    pub fn generate(&self) -> Mesh {
+        let before = std::time::Instant::now();
+        let half_size = self.size * 0.5;
+        let mesh = self.generate_area(&glm::vec2(-half_size, -half_size), self.size, self.resolution);
+        println!("Generated terrain from seed {} with {} triangles in {:.3}ms.",
+            self.seed, mesh.triangle_count(), before.elapsed().as_micros() as f32 / 1e3);
+        mesh
+    }
+
+    // A square of noise and craters, with the corner closest to negative infinity at `corner`.
+    // The vertices are in world coordinates.
+    pub fn generate_area(&self, corner: &glm::Vec2, size: f32, resolution: usize) -> Mesh {
+        let perlin = Perlin::new(&mut StdRng::seed_from_u64(self.seed));
+        // Including those only reaching the border of build_mesh, which the normals along the edges come from
+        let border = size / (resolution.max(2) - 1) as f32;
+        let craters = self.craters_near(&(corner - glm::vec2(border, border)), size + 2.0 * border);
+        let half_size = self.size * 0.5;
+        let frequency = self.frequency / self.size;
+        self.build_mesh(corner, size, resolution, |p| {
+            let noise = perlin.fbm((p.x + half_size) * frequency, (p.y + half_size) * frequency, self);
+            noise * self.height + craters.iter().map(|crater| crater.offset(p, self.crater_depth)).sum::<f32>()
+        })
+    }
+
+    // A heightmap, black at 0 and white at `height`, stretched over size by size units around
+    // the origin, with craters on top if any are asked for
+    pub fn generate_from_heightmap(&self, image: &Heightmap) -> Mesh {
+        let (width, height) = image.dimensions();
+
+        // Bilinear sampling, so that the resolution doesn't have to match the image. Samples
+        // outside of it are clamped to its edges.
+        let pixel = |x: u32, y: u32| image.get_pixel(x.min(width - 1), y.min(height - 1))[0] as f32 / u16::MAX as f32;
+        let sample = |u: f32, v: f32| {
+            let (x, y) = (u.clamp(0.0, 1.0) * (width - 1) as f32, v.clamp(0.0, 1.0) * (height - 1) as f32);
+            let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+            let (fx, fy) = (x.fract(), y.fract());
+            let bottom = glm::lerp_scalar(pixel(x0, y0), pixel(x0 + 1, y0), fx);
+            let top = glm::lerp_scalar(pixel(x0, y0 + 1), pixel(x0 + 1, y0 + 1), fx);
+            glm::lerp_scalar(bottom, top, fy)
+        };
+
+        let half_size = self.size * 0.5;
+        let corner = glm::vec2(-half_size, -half_size);
+        let craters = self.craters_near(&corner, self.size);
+        self.build_mesh(&corner, self.size, self.resolution, |p| {
+            let image_height = sample((p.x + half_size) / self.size, (p.y + half_size) / self.size) * self.height;
+            image_height + craters.iter().map(|crater| crater.offset(p, self.crater_depth)).sum::<f32>()
+        })
+    }
+
+    // The world is divided into size by size squares, lined up with the square of generate(),
+    // each with its own craters. Small craters are far more common than large ones, as they are
+    // on the moon.
+    fn craters_in_square(&self, square: (i64, i64)) -> Vec<Crater> {
+        let square_seed = self.seed ^ (square.0 as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ (square.1 as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+        let mut rng = StdRng::seed_from_u64(square_seed);
+        let corner = glm::vec2(square.0 as f32, square.1 as f32) * self.size - glm::vec2(self.size, self.size) * 0.5;
+        let (min_radius, max_radius) = self.crater_radius;
+        (0..self.craters).map(|_| Crater {
+            center : corner + glm::vec2(rng.gen::<f32>(), rng.gen::<f32>()) * self.size,
+            radius : min_radius * (max_radius / min_radius).powf(rng.gen::<f32>().powi(3)),
+        }).collect()
+    }
+
+    // Every crater reaching into the given square
+    fn craters_near(&self, corner: &glm::Vec2, size: f32) -> Vec<Crater> {
+        if self.craters == 0 {
+            return vec![];
+        }
+        let reach = self.crater_radius.1 * 1.5;
+        let square = |c: f32| ((c + self.size * 0.5) / self.size).floor() as i64;
+        let (first_x, last_x) = (square(corner.x - reach), square(corner.x + size + reach));
+        let (first_z, last_z) = (square(corner.y - reach), square(corner.y + size + reach));
+
+        let mut craters = vec![];
+        for z in first_z..=last_z {
+            for x in first_x..=last_x {
+                craters.extend(self.craters_in_square((x, z)).into_iter().filter(|crater| {
+                    let closest = glm::clamp_vec(&crater.center, corner, &(corner + glm::vec2(size, size)));
+                    glm::distance(&closest, &crater.center) < crater.reach()
+                }));
+            }
+        }
+        craters
+    }
+
+    // A grid of resolution by resolution vertices over the square, at the heights given by the
+    // function of X and Z
+    fn build_mesh(&self, corner: &glm::Vec2, size: f32, resolution: usize, height_at: impl Fn(&glm::Vec2) -> f32) -> Mesh {
+        let resolution = resolution.max(2);
+        let cell_size = size / (resolution - 1) as f32;
+
+        // With a border of one vertex all around, so that the normals along the edges match the
+        // neighbouring squares
+        let bordered = resolution + 2;
+        let heights: Vec<f32> = (0..bordered * bordered).map(|i| {
+            let (x, z) = ((i % bordered) as f32 - 1.0, (i / bordered) as f32 - 1.0);
+            height_at(&(corner + glm::vec2(x, z) * cell_size))
+        }).collect();
+        let height = |x: usize, z: usize| heights[(z + 1) * bordered + x + 1];
+
+        let mut vertices = Vec::with_capacity(resolution * resolution * 3);
+        let mut normals = Vec::with_capacity(resolution * resolution * 3);
+        let mut colors = Vec::with_capacity(resolution * resolution * 4);
+        for z in 0..resolution {
+            for x in 0..resolution {
+                let y = height(x, z);
+                vertices.extend_from_slice(&[corner.x + x as f32 * cell_size, y, corner.y + z as f32 * cell_size]);
+
+                // Central differences, reaching into the border
+                let dx = (heights[(z + 1) * bordered + x + 2] - heights[(z + 1) * bordered + x]) / (2.0 * cell_size);
+                let dz = (heights[(z + 2) * bordered + x + 1] - heights[z * bordered + x + 1]) / (2.0 * cell_size);
+                let normal = glm::normalize(&glm::vec3(-dx, 1.0, -dz));
+                normals.extend_from_slice(&[normal.x, normal.y, normal.z]);
+
+                // Measured against the height of the noise rather than of this square, so that
+                // the colors match up with the neighbours too
+                let height_fraction = if self.height > 0.0 { (y / self.height).clamp(0.0, 1.0) } else { 0.5 };
+                let shade = 1.0 - SLOPE_SHADE * (1.0 - normal.y);
+                let color = glm::lerp(&glm::make_vec3(&LOWLAND_COLOR), &glm::make_vec3(&HIGHLAND_COLOR), height_fraction) * shade;
+                colors.extend_from_slice(&[color.x, color.y, color.z, 1.0]);
+            }
+        }
+
+        // Two counter-clockwise triangles per grid cell, seen from above
+        let mut indices = Vec::with_capacity((resolution - 1) * (resolution - 1) * 6);
+        let row = resolution as u32;
+        for z in 0..resolution as u32 - 1 {
+            for x in 0..resolution as u32 - 1 {
+                let i = z * row + x;
+                indices.extend_from_slice(&[i, i + row, i + 1, i + 1, i + row, i + row + 1]);
+            }
+        }
+
+        Mesh::new(vertices, normals, colors, indices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heights(mesh: &Mesh) -> Vec<f32> {
+        mesh.vertices.chunks_exact(3).map(|p| p[1]).collect()
+    }
+
+    #[test]
+    fn the_seed_decides_the_terrain() {
+        let generator = TerrainGenerator::default();
+        let corner = glm::vec2(150.0, -300.0);
+        let chunk = heights(&generator.generate_area(&corner, 64.0, 33));
+        assert_eq!(chunk, heights(&generator.generate_area(&corner, 64.0, 33)));
+
+        let other = TerrainGenerator { seed: generator.seed + 1, ..generator };
+        let other_chunk = heights(&other.generate_area(&corner, 64.0, 33));
+        let differences = chunk.iter().zip(&other_chunk).filter(|(a, b)| (*a - *b).abs() > 1e-3).count();
+        assert!(differences > chunk.len() / 2, "only {} of {} heights differ", differences, chunk.len());
+    }
+
+    #[test]
+    fn neighbouring_chunks_meet_along_their_edges() {
+        // Across the border between two squares of craters, at x = 200
+        let generator = TerrainGenerator::default();
+        let (size, resolution) = (64.0, 33);
+        let corner = glm::vec2(136.0, -20.0);
+        let chunk = generator.generate_area(&corner, size, resolution);
+        let right = generator.generate_area(&(corner + glm::vec2(size, 0.0)), size, resolution);
+        let above = generator.generate_area(&(corner + glm::vec2(0.0, size)), size, resolution);
+
+        // The vertex and its normal and color, at a column and row of a chunk
+        let at = |mesh: &Mesh, x: usize, z: usize| {
+            let i = z * resolution + x;
+            [&mesh.vertices[3*i..3*i + 3], &mesh.normals[3*i..3*i + 3], &mesh.colors[4*i..4*i + 4]].concat()
+        };
+        for i in 0..resolution {
+            let edges = [
+                (at(&chunk, resolution - 1, i), at(&right, 0, i)),
+                (at(&chunk, i, resolution - 1), at(&above, i, 0)),
+            ];
+            for (a, b) in edges {
+                assert!(a.iter().zip(&b).all(|(a, b)| (a - b).abs() < 1e-4), "{:?} != {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn heightmaps_go_from_nothing_to_the_height() {
+        // Black on the left, white on the right
+        let image = Heightmap::from_fn(2, 2, |x, _| Luma([if x == 0 { 0 } else { u16::MAX }]));
+        let generator = TerrainGenerator { size: 10.0, height: 4.0, resolution: 3, craters: 0, ..TerrainGenerator::default() };
+        let mesh = generator.generate_from_heightmap(&image);
+        assert_eq!(mesh.vertex_count(), 9);
+        for p in mesh.vertices.chunks_exact(3) {
+            let expected = (p[0] + 5.0) / 10.0 * 4.0;
+            assert!((p[1] - expected).abs() < 1e-5, "{} at x = {}, expected {}", p[1], p[0], expected);
+        }
+    }
+}
@@ -0,0 +1,42 @@
+; ModuleID = 'probe7.ec2f88c67c612580-cgu.0'
+source_filename = "probe7.ec2f88c67c612580-cgu.0"
+target datalayout = "e-m:e-p270:32:32-p271:32:32-p272:64:64-i64:64-i128:128-f80:128-n8:16:32:64-S128"
+target triple = "x86_64-unknown-linux-gnu"
+
+; core::num::<impl u32>::to_ne_bytes
+; Function Attrs: inlinehint nonlazybind uwtable
+define internal i32 @"_ZN4core3num21_$LT$impl$u20$u32$GT$11to_ne_bytes17h6c07e83bbd078c13E"(i32 %self) unnamed_addr #0 {
+start:
+  %_0 = alloca [4 x i8], align 1
+  store i32 %self, ptr %_0, align 1
+  %0 = load i32, ptr %_0, align 1
+  ret i32 %0
+}
+
+; probe7::probe
+; Function Attrs: nonlazybind uwtable
+define void @_ZN6probe75probe17h4164f5d78b36d385E() unnamed_addr #1 {
+start:
+  %0 = alloca [4 x i8], align 4
+  %_1 = alloca [4 x i8], align 1
+; call core::num::<impl u32>::to_ne_bytes
+  %1 = call i32 @"_ZN4core3num21_$LT$impl$u20$u32$GT$11to_ne_bytes17h6c07e83bbd078c13E"(i32 1) #3
+  store i32 %1, ptr %0, align 4
+  call void @llvm.memcpy.p0.p0.i64(ptr align 1 %_1, ptr align 4 %0, i64 4, i1 false)
+  ret void
+}
+
+; Function Attrs: nocallback nofree nounwind willreturn memory(argmem: readwrite)
+declare void @llvm.memcpy.p0.p0.i64(ptr noalias writeonly captures(none), ptr noalias readonly captures(none), i64, i1 immarg) #2
+
+attributes #0 = { inlinehint nonlazybind uwtable "probe-stack"="inline-asm" "target-cpu"="x86-64" }
+attributes #1 = { nonlazybind uwtable "probe-stack"="inline-asm" "target-cpu"="x86-64" }
+attributes #2 = { nocallback nofree nounwind willreturn memory(argmem: readwrite) }
+attributes #3 = { inlinehint }
+
+!llvm.module.flags = !{!0, !1}
+!llvm.ident = !{!2}
+
+!0 = !{i32 8, !"PIC Level", i32 2}
+!1 = !{i32 2, !"RtLibUseGOT", i32 1}
+!2 = !{!"rustc version 1.95.0 (59807616e 2026-04-14)"}